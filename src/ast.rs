@@ -1,23 +1,59 @@
+mod arena;
 mod arithmetic_op;
+mod attribute;
 mod block;
-mod block_decl;
+mod const_decl;
+mod enum_variant;
+mod error;
+mod expansion;
 mod expr;
 mod expr_kind;
 mod field;
+mod fn_decl;
+mod fold;
+mod impl_decl;
+mod import;
+mod interface_decl;
 mod let_decl;
+mod node_id;
 mod num_lit;
-mod pipe_expr;
-mod piped_expr_kind;
+mod pattern;
+mod pattern_kind;
+mod record_lit;
+mod span;
 mod stmt;
 mod stmt_kind;
+mod type_expr;
+mod unary_op;
+mod visit;
+mod visit_mut;
 
+pub use arena::*;
 pub use arithmetic_op::*;
+pub use attribute::*;
 pub use block::*;
-pub use block_decl::*;
+pub use const_decl::*;
+pub use enum_variant::*;
+pub use error::*;
+pub use expansion::*;
 pub use expr::*;
 pub use expr_kind::*;
 pub use field::*;
+pub use fn_decl::*;
+pub use fold::*;
+pub use impl_decl::*;
+pub use import::*;
+pub use interface_decl::*;
 pub use let_decl::*;
+pub use node_id::*;
 pub use num_lit::*;
+pub use pattern::*;
+pub use pattern_kind::*;
+pub use record_lit::*;
+pub use span::*;
 pub use stmt::*;
 pub use stmt_kind::*;
+pub use type_expr::*;
+pub use unary_op::*;
+pub use visit::*;
+pub use visit_mut::*;
@@ -0,0 +1,422 @@
+use std::ops::Index;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+	walk_expr, walk_pattern, walk_stmt, walk_type_expr, DanglingIdError, Expr, ExprId, ExprKind,
+	NodeId, Pattern, PatternId, PatternKind, Span, Stmt, StmtId, StmtKind, TypeExpr, TypeExprId,
+	TypeExprKind, Visitor,
+};
+
+/// A node of any kind that has an ID
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum Node {
+	Stmt(Stmt),
+	Expr(Expr),
+	Pattern(Pattern),
+	TypeExpr(TypeExpr),
+}
+
+impl Node {
+	pub fn span(&self) -> Span {
+		match self {
+			Node::Stmt(stmt) => stmt.span,
+			Node::Expr(expr) => expr.span,
+			Node::Pattern(pattern) => pattern.span,
+			Node::TypeExpr(type_expr) => type_expr.span,
+		}
+	}
+
+	/// What kind of node it is, as errors name it
+	pub fn kind_name(&self) -> &'static str {
+		match self {
+			Node::Stmt(_) => "statement",
+			Node::Expr(_) => "expression",
+			Node::Pattern(_) => "pattern",
+			Node::TypeExpr(_) => "type expression",
+		}
+	}
+}
+
+/// The nodes of a file's tree, which refer to their children by ID.
+///
+/// A node is added after its children and never removed, so its ID stays valid
+/// for as long as the arena and side tables can be indexed by it. A subtree is
+/// shared by copying its ID into another node, which then becomes its parent,
+/// and copied into another arena with a `Fold`.
+///
+/// Reading an arena back checks that each node only refers to nodes of the
+/// right kind added before it, and works out the parents again from the nodes
+/// rather than trusting the ones written out.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "WrittenArena")]
+pub struct Arena {
+	nodes: Vec<Node>,
+	/// The node each one was last made a child of, if it is still one of its
+	/// children
+	parents: Vec<Option<NodeId>>,
+}
+
+impl Arena {
+	pub fn alloc_stmt(&mut self, kind: StmtKind, span: Span) -> StmtId {
+		StmtId(self.alloc(Node::Stmt(Stmt { kind, span })))
+	}
+
+	pub fn alloc_expr(&mut self, kind: ExprKind, span: Span) -> ExprId {
+		ExprId(self.alloc(Node::Expr(Expr { kind, span })))
+	}
+
+	pub fn alloc_pattern(&mut self, kind: PatternKind, span: Span) -> PatternId {
+		PatternId(self.alloc(Node::Pattern(Pattern { kind, span })))
+	}
+
+	pub fn alloc_type_expr(&mut self, kind: TypeExprKind, span: Span) -> TypeExprId {
+		TypeExprId(self.alloc(Node::TypeExpr(TypeExpr { kind, span })))
+	}
+
+	fn alloc(&mut self, node: Node) -> NodeId {
+		let id =
+			NodeId(u32::try_from(self.nodes.len()).expect("an arena holds fewer than 2^32 nodes"));
+		self.nodes.push(node);
+		self.parents.push(None);
+		self.adopt_children(id);
+		id
+	}
+
+	/// Changes a statement in place, keeping its ID and span
+	pub fn update_stmt<T>(&mut self, id: StmtId, f: impl FnOnce(&mut StmtKind) -> T) -> T {
+		let old_children = self.children(id);
+		let Node::Stmt(stmt) = &mut self.nodes[id.0.index()] else {
+			unreachable!("a `StmtId` refers to a statement")
+		};
+		let result = f(&mut stmt.kind);
+		self.readopt_children(id.into(), old_children);
+		result
+	}
+
+	/// Changes an expression in place, keeping its ID and span
+	pub fn update_expr<T>(&mut self, id: ExprId, f: impl FnOnce(&mut ExprKind) -> T) -> T {
+		let old_children = self.children(id);
+		let Node::Expr(expr) = &mut self.nodes[id.0.index()] else {
+			unreachable!("an `ExprId` refers to an expression")
+		};
+		let result = f(&mut expr.kind);
+		self.readopt_children(id.into(), old_children);
+		result
+	}
+
+	/// Adopts the children of a changed node, leaving those of the children it had
+	/// before that it no longer has without a parent
+	fn readopt_children(&mut self, id: NodeId, old_children: Vec<NodeId>) {
+		for child in old_children {
+			if self.parents[child.index()] == Some(id) {
+				self.parents[child.index()] = None;
+			}
+		}
+
+		self.adopt_children(id);
+	}
+
+	fn adopt_children(&mut self, id: NodeId) {
+		for child in self.children(id) {
+			self.parents[child.index()] = Some(id);
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.nodes.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.nodes.is_empty()
+	}
+
+	pub fn get(&self, id: NodeId) -> Option<&Node> {
+		self.nodes.get(id.index())
+	}
+
+	/// The node `id` is a child of, which is `None` for top-level statements
+	pub fn parent(&self, id: impl Into<NodeId>) -> Option<NodeId> {
+		self.parents.get(id.into().index()).copied().flatten()
+	}
+
+	/// The nodes `id` is nested in, innermost first
+	pub fn ancestors(&self, id: impl Into<NodeId>) -> impl Iterator<Item = NodeId> + '_ {
+		std::iter::successors(self.parent(id), |&id| self.parent(id))
+	}
+
+	/// The children of a node in source order, including those nested in its
+	/// blocks, declarations and other parts without IDs of their own
+	pub fn children(&self, id: impl Into<NodeId>) -> Vec<NodeId> {
+		let id = id.into();
+		let mut children = Children {
+			arena: self,
+			ids: Vec::new(),
+		};
+
+		match &self.nodes[id.index()] {
+			Node::Stmt(_) => walk_stmt(&mut children, StmtId(id)),
+			Node::Expr(_) => walk_expr(&mut children, ExprId(id)),
+			Node::Pattern(_) => walk_pattern(&mut children, PatternId(id)),
+			Node::TypeExpr(_) => walk_type_expr(&mut children, TypeExprId(id)),
+		}
+
+		children.ids
+	}
+}
+
+/// An arena as written out, whose parents are left out as they follow from
+/// the nodes
+#[derive(Deserialize)]
+struct WrittenArena {
+	nodes: Vec<Node>,
+}
+
+impl TryFrom<WrittenArena> for Arena {
+	type Error = DanglingIdError;
+
+	fn try_from(written: WrittenArena) -> Result<Self, DanglingIdError> {
+		let mut arena = Arena::default();
+
+		for node in written.nodes {
+			let id = NodeId(arena.nodes.len() as u32);
+			arena.nodes.push(node);
+			arena.parents.push(None);
+
+			let mut check = CheckChildren {
+				arena: &arena,
+				parent: id,
+				error: None,
+			};
+			match &arena.nodes[id.index()] {
+				Node::Stmt(_) => walk_stmt(&mut check, StmtId(id)),
+				Node::Expr(_) => walk_expr(&mut check, ExprId(id)),
+				Node::Pattern(_) => walk_pattern(&mut check, PatternId(id)),
+				Node::TypeExpr(_) => walk_type_expr(&mut check, TypeExprId(id)),
+			}
+			if let Some(error) = check.error {
+				return Err(error);
+			}
+
+			arena.adopt_children(id);
+		}
+
+		Ok(arena)
+	}
+}
+
+impl Index<StmtId> for Arena {
+	type Output = Stmt;
+
+	fn index(&self, id: StmtId) -> &Stmt {
+		match &self.nodes[id.0.index()] {
+			Node::Stmt(stmt) => stmt,
+			_ => unreachable!("a `StmtId` refers to a statement"),
+		}
+	}
+}
+
+impl Index<ExprId> for Arena {
+	type Output = Expr;
+
+	fn index(&self, id: ExprId) -> &Expr {
+		match &self.nodes[id.0.index()] {
+			Node::Expr(expr) => expr,
+			_ => unreachable!("an `ExprId` refers to an expression"),
+		}
+	}
+}
+
+impl Index<PatternId> for Arena {
+	type Output = Pattern;
+
+	fn index(&self, id: PatternId) -> &Pattern {
+		match &self.nodes[id.0.index()] {
+			Node::Pattern(pattern) => pattern,
+			_ => unreachable!("a `PatternId` refers to a pattern"),
+		}
+	}
+}
+
+impl Index<TypeExprId> for Arena {
+	type Output = TypeExpr;
+
+	fn index(&self, id: TypeExprId) -> &TypeExpr {
+		match &self.nodes[id.0.index()] {
+			Node::TypeExpr(type_expr) => type_expr,
+			_ => unreachable!("a `TypeExprId` refers to a type expression"),
+		}
+	}
+}
+
+/// Collects the IDs of a node's children without descending into them
+struct Children<'ast> {
+	arena: &'ast Arena,
+	ids: Vec<NodeId>,
+}
+
+impl<'ast> Visitor<'ast> for Children<'ast> {
+	fn arena(&self) -> &'ast Arena {
+		self.arena
+	}
+
+	fn visit_stmt(&mut self, id: StmtId) {
+		self.ids.push(id.into());
+	}
+
+	fn visit_expr(&mut self, id: ExprId) {
+		self.ids.push(id.into());
+	}
+
+	fn visit_pattern(&mut self, id: PatternId) {
+		self.ids.push(id.into());
+	}
+
+	fn visit_type_expr(&mut self, id: TypeExprId) {
+		self.ids.push(id.into());
+	}
+}
+
+/// Finds the first child of a node read back from a dump that isn't a node of
+/// its kind added before it, which also rules out cycles
+struct CheckChildren<'ast> {
+	arena: &'ast Arena,
+	parent: NodeId,
+	error: Option<DanglingIdError>,
+}
+
+impl CheckChildren<'_> {
+	fn check(&mut self, id: NodeId, expected: &'static str) {
+		let valid = id < self.parent && self.arena.nodes[id.index()].kind_name() == expected;
+
+		if !valid {
+			self.error
+				.get_or_insert(DanglingIdError { expected, id: id.0 });
+		}
+	}
+}
+
+impl<'ast> Visitor<'ast> for CheckChildren<'ast> {
+	fn arena(&self) -> &'ast Arena {
+		self.arena
+	}
+
+	fn visit_stmt(&mut self, id: StmtId) {
+		self.check(id.into(), "statement");
+	}
+
+	fn visit_expr(&mut self, id: ExprId) {
+		self.check(id.into(), "expression");
+	}
+
+	fn visit_pattern(&mut self, id: PatternId) {
+		self.check(id.into(), "pattern");
+	}
+
+	fn visit_type_expr(&mut self, id: TypeExprId) {
+		self.check(id.into(), "type expression");
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+
+	use super::*;
+	use crate::{lower::lower, parse::parse};
+
+	#[test]
+	fn numbers_nodes_after_their_children() {
+		let file = parse(&mut "fn f(a: Num) -> Num { a + 1 } f;".as_bytes()).unwrap();
+
+		let spans = (0..file.arena.len() as u32)
+			.map(|id| {
+				let span = file.arena.get(NodeId(id)).unwrap().span();
+				(span.row, span.col)
+			})
+			.collect::<Vec<_>>();
+
+		// `Num`, `Num`, `a`, `1`, `a + 1`, `fn f`, `f`, `f;`
+		assert_eq!(
+			spans,
+			vec![
+				(1, 9),
+				(1, 17),
+				(1, 23),
+				(1, 27),
+				(1, 23),
+				(1, 1),
+				(1, 31),
+				(1, 31)
+			]
+		);
+	}
+
+	#[test]
+	fn finds_parents_and_ancestors() {
+		let file = parse(&mut "fn f(a: Num) -> Num { let b = a * 2; b }".as_bytes()).unwrap();
+
+		let Some(Node::Expr(two)) = file.arena.get(NodeId(4)) else {
+			panic!("expected an expression")
+		};
+		assert_eq!(two.kind, ExprKind::NumLit(2.0));
+
+		// `a * 2`, `let b`, `fn f`
+		let ancestors = file.arena.ancestors(NodeId(4)).collect::<Vec<_>>();
+		assert_eq!(ancestors, vec![NodeId(5), NodeId(6), NodeId(8)]);
+		assert_eq!(file.arena.parent(file.stmts[0]), None);
+		assert_eq!(file.arena.children(file.stmts[0]).len(), 4);
+		assert!(file.arena.get(NodeId(9)).is_none());
+	}
+
+	#[test]
+	fn keys_side_tables() {
+		let file = parse(&mut "1 + 2; 3;".as_bytes()).unwrap();
+
+		// Tags each literal with the statement it's in
+		let mut statements = HashMap::new();
+		for id in (0..file.arena.len() as u32).map(NodeId) {
+			if let Some(Node::Expr(Expr {
+				kind: ExprKind::NumLit(_),
+				..
+			})) = file.arena.get(id)
+			{
+				let stmt = file.arena.ancestors(id).last().unwrap_or(id);
+				statements.insert(id, stmt);
+			}
+		}
+
+		assert_eq!(
+			statements,
+			HashMap::from([
+				(NodeId(0), NodeId(3)),
+				(NodeId(1), NodeId(3)),
+				(NodeId(4), NodeId(5)),
+			])
+		);
+	}
+
+	#[test]
+	fn lowering_rewrites_nodes_in_place() {
+		let file = parse(&mut "a >> f(b);".as_bytes()).unwrap();
+		let pipe = file.arena.children(file.stmts[0])[0];
+		assert_eq!(pipe, NodeId(4));
+
+		// `f(a, b)` takes the place and the ID of the pipe, and the call `f(b)` it
+		// was made from is left out of the tree
+		let file = lower(file).unwrap();
+		let Some(Node::Expr(call)) = file.arena.get(pipe) else {
+			panic!("expected an expression")
+		};
+		assert!(matches!(call.kind, ExprKind::FnInvoke(..)));
+		assert_eq!(
+			file.arena.children(pipe),
+			vec![NodeId(1), NodeId(0), NodeId(2)]
+		);
+		for child in file.arena.children(pipe) {
+			assert_eq!(file.arena.parent(child), Some(pipe));
+		}
+		assert_eq!(file.arena.parent(NodeId(3)), None);
+		assert_eq!(file.arena.len(), 6);
+	}
+}
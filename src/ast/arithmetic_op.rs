@@ -1,8 +1,122 @@
-#[derive(Debug, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+
+use crate::intern::Symbol;
+
+/// Operators other than `&&`, `||` and the pipes can be overloaded. When the
+/// left operand is a record whose type implements the operator's interface from
+/// the prelude, the operator calls that method with the right operand, and
+/// otherwise it has its builtin meaning. The checker resolves the method when it
+/// knows the record's type, and otherwise the impl is looked up as the program
+/// runs.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub enum BinaryOp {
 	Add,
 	Subtract,
 	Multiply,
 	Divide,
+	Modulo,
+	Power,
+	/// `x >> f`, which calls `f(x)`. When `f` is a call itself, `x` fills its `_`
+	/// placeholder argument, as in `x >> f(a, _)` for `f(a, x)`, or is passed as
+	/// the first argument if there is no placeholder, as in `x >> f(a)` for
+	/// `f(x, a)`. Pipes are replaced with the calls they stand for when lowering.
 	Pipe,
+	/// `x ?>> f`, which pipes the value inside `x` into `f` like `>>` if `x` is an
+	/// `Ok` or `Some`, and otherwise evaluates to `x` itself without calling `f`.
+	/// Each stage of such a pipeline should return a `Result` or `Option` in turn.
+	TryPipe,
+	Equal,
+	NotEqual,
+	LessThan,
+	LessThanOrEqual,
+	GreaterThan,
+	GreaterThanOrEqual,
+	/// Short-circuiting `&&`
+	And,
+	/// Short-circuiting `||`
+	Or,
+	BitAnd,
+	BitOr,
+	BitXor,
+}
+
+impl BinaryOp {
+	/// The operator as it is written in source
+	pub fn symbol(&self) -> &'static str {
+		match self {
+			Self::Add => "+",
+			Self::Subtract => "-",
+			Self::Multiply => "*",
+			Self::Divide => "/",
+			Self::Modulo => "%",
+			Self::Power => "^",
+			Self::Pipe => ">>",
+			Self::TryPipe => "?>>",
+			Self::Equal => "==",
+			Self::NotEqual => "!=",
+			Self::LessThan => "<",
+			Self::LessThanOrEqual => "<=",
+			Self::GreaterThan => ">",
+			Self::GreaterThanOrEqual => ">=",
+			Self::And => "&&",
+			Self::Or => "||",
+			Self::BitAnd => "&",
+			Self::BitOr => "|",
+			Self::BitXor => "~",
+		}
+	}
+
+	/// How the operator can be given a meaning for records. `!=` negates
+	/// `equals`, which falls back to comparing the fields when there is no impl,
+	/// and the ordering operators compare the number `compare` returns to zero.
+	pub fn overload(&self) -> Option<Overload> {
+		let overload = |interface, method| Some(Overload { interface, method });
+
+		match self {
+			Self::Add => overload(Symbol::ADD, Symbol::ADD_METHOD),
+			Self::Subtract => overload(Symbol::SUBTRACT, Symbol::SUBTRACT_METHOD),
+			Self::Multiply => overload(Symbol::MULTIPLY, Symbol::MULTIPLY_METHOD),
+			Self::Divide => overload(Symbol::DIVIDE, Symbol::DIVIDE_METHOD),
+			Self::Modulo => overload(Symbol::MODULO, Symbol::MODULO_METHOD),
+			Self::Power => overload(Symbol::POWER, Symbol::POWER_METHOD),
+			Self::BitAnd => overload(Symbol::BIT_AND, Symbol::BIT_AND_METHOD),
+			Self::BitOr => overload(Symbol::BIT_OR, Symbol::BIT_OR_METHOD),
+			Self::BitXor => overload(Symbol::BIT_XOR, Symbol::BIT_XOR_METHOD),
+			Self::Equal | Self::NotEqual => overload(Symbol::EQUAL, Symbol::EQUALS_METHOD),
+			Self::LessThan
+			| Self::LessThanOrEqual
+			| Self::GreaterThan
+			| Self::GreaterThanOrEqual => overload(Symbol::COMPARE, Symbol::COMPARE_METHOD),
+			Self::Pipe | Self::TryPipe | Self::And | Self::Or => None,
+		}
+	}
+
+	/// Equality and ordering operators, which can't be chained without parentheses
+	pub fn is_comparison(&self) -> bool {
+		matches!(
+			self,
+			Self::Equal
+				| Self::NotEqual
+				| Self::LessThan
+				| Self::LessThanOrEqual
+				| Self::GreaterThan
+				| Self::GreaterThanOrEqual
+		)
+	}
+}
+
+/// The prelude interface and its method that give an operator a meaning for
+/// the records of the types implementing it
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Overload {
+	pub interface: Symbol,
+	pub method: Symbol,
+}
+
+impl Overload {
+	/// Indexing `x[key]`, which calls `x.index(key)`
+	pub const INDEX: Overload = Overload {
+		interface: Symbol::INDEX,
+		method: Symbol::INDEX_METHOD,
+	};
 }
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+use super::{ExprId, Span};
+use crate::intern::Symbol;
+
+/// Metadata attached to a declaration, written `@name(args)` or `#[name(args)]`
+/// with optional arguments, like `@deprecated("use y")` or `#[allow(unused)]`.
+///
+/// Any name is accepted and the arguments are never evaluated, so their
+/// meaning is up to the passes that look for them.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Attribute {
+	pub name: Symbol,
+	pub args: Vec<ExprId>,
+	pub span: Span,
+}
+
+impl Attribute {
+	/// Finds the attribute called `name` among those of a declaration
+	pub fn find<'a>(attributes: &'a [Attribute], name: &str) -> Option<&'a Attribute> {
+		attributes.iter().find(|attribute| attribute.name == name)
+	}
+}
+
+/// Spans are ignored so that trees can be compared by shape alone.
+impl PartialEq for Attribute {
+	fn eq(&self, other: &Self) -> bool {
+		self.name == other.name && self.args == other.args
+	}
+}
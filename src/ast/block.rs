@@ -1,5 +1,11 @@
-use super::Expr;
-#[derive(Debug, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+use super::{ExprId, StmtId};
+
+/// A braced sequence of statements, optionally ending in an expression
+/// without a trailing semicolon which becomes the value of the block.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Block {
-	pub statements: Vec<Expr>,
+	pub stmts: Vec<StmtId>,
+	pub expr: Option<ExprId>,
 }
@@ -1,6 +0,0 @@
-use super::Stmt;
-
-#[derive(Debug, PartialEq)]
-pub struct BlockDecl {
-	pub stmts: Vec<Stmt>,
-}
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Attribute, ExprId};
+use crate::intern::Symbol;
+
+/// `const NAME = expr;`, whose value is evaluated while compiling, so it may
+/// only use literals, operators, other constants and calls to `const fn`s
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConstDecl {
+	pub attributes: Vec<Attribute>,
+	pub name: Symbol,
+	pub value: ExprId,
+}
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+use super::TypedefField;
+use crate::intern::Symbol;
+
+/// One alternative of an `enum`, like `Rect(w, h)`
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct EnumVariant {
+	pub name: Symbol,
+	/// The payload, declared like the fields of a typedef
+	pub fields: Vec<TypedefField>,
+}
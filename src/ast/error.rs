@@ -0,0 +1,19 @@
+use std::{error::Error, fmt};
+
+/// An ID read back from a dump that doesn't refer to what it should, which is a
+/// node of its kind added before the node it's in, or one of the file's macro
+/// invocations.
+#[derive(Debug, PartialEq)]
+pub struct DanglingIdError {
+	/// What the ID should refer to, e.g. "expression"
+	pub expected: &'static str,
+	pub id: u32,
+}
+
+impl Error for DanglingIdError {}
+
+impl fmt::Display for DanglingIdError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "Dangling {} ID {}", self.expected, self.id)
+	}
+}
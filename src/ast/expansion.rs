@@ -0,0 +1,238 @@
+use std::fmt;
+
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use super::{
+	walk_attribute, walk_expr, walk_pattern, walk_stmt, walk_type_expr, Arena, Attribute,
+	DanglingIdError, ExprId, ExprKind, PatternId, PatternKind, Span, StmtId, TypeExprId, Visitor,
+};
+
+/// How many invocations a backtrace shows at each end before leaving out the
+/// ones in between, which are usually a macro invoking itself over and over
+const BACKTRACE_ENDS: usize = 3;
+
+/// Identifies a macro invocation within the `Expansions` of its file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ExpansionId(pub u32);
+
+/// A macro invocation, which the spans of everything expanded from it refer to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Expansion {
+	pub macro_name: String,
+	pub call_site: Span,
+}
+
+/// The macro invocations expanded while parsing a file, owned by the file so
+/// that they are dropped along with the spans referring to them
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct Expansions(Vec<Expansion>);
+
+/// Checks that each invocation read back is in one expanded before it, if in
+/// any, so that backtraces end
+impl<'de> Deserialize<'de> for Expansions {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let expansions = Vec::<Expansion>::deserialize(deserializer)?;
+
+		for (i, expansion) in expansions.iter().enumerate() {
+			if let Some(id) = expansion
+				.call_site
+				.expansion
+				.filter(|id| id.0 as usize >= i)
+			{
+				return Err(de::Error::custom(DanglingIdError {
+					expected: "macro invocation",
+					id: id.0,
+				}));
+			}
+		}
+
+		Ok(Self(expansions))
+	}
+}
+
+impl Expansions {
+	pub fn push(&mut self, expansion: Expansion) -> ExpansionId {
+		let id = ExpansionId(
+			self.0
+				.len()
+				.try_into()
+				.expect("fewer than 2^32 invocations are expanded"),
+		);
+		self.0.push(expansion);
+		id
+	}
+
+	pub fn get(&self, id: ExpansionId) -> &Expansion {
+		&self.0[id.0 as usize]
+	}
+
+	/// Checks that the spans of a tree read back from a dump only refer to
+	/// invocations in the table
+	pub fn check_spans(&self, arena: &Arena, stmts: &[StmtId]) -> Result<(), DanglingIdError> {
+		let mut check = CheckSpans {
+			arena,
+			expansions: self.0.len(),
+			error: None,
+		};
+		stmts.iter().for_each(|&stmt| check.visit_stmt(stmt));
+
+		match check.error {
+			Some(error) => Err(error),
+			None => Ok(()),
+		}
+	}
+
+	/// How many invocations deep `span` is nested
+	pub fn depth(&self, span: Span) -> usize {
+		std::iter::successors(span.expansion, |&id| self.get(id).call_site.expansion).count()
+	}
+
+	/// The invocations `span` was expanded through, innermost first
+	pub fn backtrace(&self, span: Span) -> Backtrace {
+		Backtrace(
+			std::iter::successors(span.expansion, |&id| self.get(id).call_site.expansion)
+				.map(|id| self.get(id).clone())
+				.collect(),
+		)
+	}
+}
+
+/// The invocations a span was expanded through, innermost first, which errors
+/// show after the span like `, in the expansion of `twice!` at 8:1`.
+///
+/// Only the outermost and innermost few are shown when there are many.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Backtrace(pub Vec<Expansion>);
+
+impl fmt::Display for Backtrace {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let frame = |f: &mut fmt::Formatter, expansion: &Expansion| {
+			write!(
+				f,
+				", in the expansion of `{}!` at {}",
+				expansion.macro_name, expansion.call_site
+			)
+		};
+
+		if self.0.len() <= 2 * BACKTRACE_ENDS + 1 {
+			return self.0.iter().try_for_each(|expansion| frame(f, expansion));
+		}
+
+		let (inner, rest) = self.0.split_at(BACKTRACE_ENDS);
+		let (skipped, outer) = rest.split_at(rest.len() - BACKTRACE_ENDS);
+
+		inner.iter().try_for_each(|expansion| frame(f, expansion))?;
+		write!(f, ", and {} more expansions", skipped.len())?;
+		outer.iter().try_for_each(|expansion| frame(f, expansion))
+	}
+}
+
+/// Finds the first span of a tree that refers to an invocation past the end of
+/// its file's table
+struct CheckSpans<'ast> {
+	arena: &'ast Arena,
+	expansions: usize,
+	error: Option<DanglingIdError>,
+}
+
+impl CheckSpans<'_> {
+	fn check(&mut self, span: Span) {
+		if let Some(id) = span.expansion.filter(|id| id.0 as usize >= self.expansions) {
+			self.error.get_or_insert(DanglingIdError {
+				expected: "macro invocation",
+				id: id.0,
+			});
+		}
+	}
+}
+
+impl<'ast> Visitor<'ast> for CheckSpans<'ast> {
+	fn arena(&self) -> &'ast Arena {
+		self.arena
+	}
+
+	fn visit_stmt(&mut self, id: StmtId) {
+		self.check(self.arena[id].span);
+		walk_stmt(self, id);
+	}
+
+	fn visit_expr(&mut self, id: ExprId) {
+		let expr = &self.arena[id];
+		self.check(expr.span);
+		if let ExprKind::Record(record) = &expr.kind {
+			record
+				.fields
+				.iter()
+				.for_each(|field| self.check(field.span));
+		}
+		walk_expr(self, id);
+	}
+
+	fn visit_pattern(&mut self, id: PatternId) {
+		let pattern = &self.arena[id];
+		self.check(pattern.span);
+		if let PatternKind::Record { fields, .. } = &pattern.kind {
+			fields.iter().for_each(|field| self.check(field.span));
+		}
+		walk_pattern(self, id);
+	}
+
+	fn visit_type_expr(&mut self, id: TypeExprId) {
+		self.check(self.arena[id].span);
+		walk_type_expr(self, id);
+	}
+
+	fn visit_attribute(&mut self, attribute: &'ast Attribute) {
+		self.check(attribute.span);
+		walk_attribute(self, attribute);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn nested(depth: usize) -> (Expansions, Span) {
+		let mut expansions = Expansions::default();
+		let mut span = Span::new(1, 1);
+
+		for i in 0..depth {
+			let id = expansions.push(Expansion {
+				macro_name: "m".to_string(),
+				call_site: Span { row: i + 2, ..span },
+			});
+			span = Span {
+				expansion: Some(id),
+				..Span::new(1, 1)
+			};
+		}
+
+		(expansions, span)
+	}
+
+	#[test]
+	fn shows_every_invocation_of_short_backtraces() {
+		let (expansions, span) = nested(2);
+
+		assert_eq!(expansions.depth(span), 2);
+		assert_eq!(
+			expansions.backtrace(span).to_string(),
+			", in the expansion of `m!` at 3:1, in the expansion of `m!` at 2:1"
+		);
+	}
+
+	#[test]
+	fn leaves_out_the_middle_of_long_backtraces() {
+		let (expansions, span) = nested(10);
+
+		assert_eq!(expansions.depth(span), 10);
+		assert_eq!(
+			expansions.backtrace(span).to_string(),
+			", in the expansion of `m!` at 11:1, in the expansion of `m!` at 10:1, \
+			in the expansion of `m!` at 9:1, and 4 more expansions, \
+			in the expansion of `m!` at 4:1, in the expansion of `m!` at 3:1, \
+			in the expansion of `m!` at 2:1"
+		);
+	}
+}
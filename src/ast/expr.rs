@@ -1,6 +1,16 @@
-use super::ExprKind;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq)]
+use super::{ExprKind, Span};
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Expr {
 	pub kind: ExprKind,
+	pub span: Span,
+}
+
+/// Spans are ignored so that trees can be compared by shape alone.
+impl PartialEq for Expr {
+	fn eq(&self, other: &Self) -> bool {
+		self.kind == other.kind
+	}
 }
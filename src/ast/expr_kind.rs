@@ -1,17 +1,79 @@
-use super::{BinaryOp, Block, Expr, TypedefField};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq)]
+use super::{
+	BinaryOp, Block, EnumVariant, ExprId, MatchArm, PatternId, RecordLit, TypedefField, UnaryOp,
+};
+use crate::intern::Symbol;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum ExprKind {
-	Binary(BinaryOp, Box<Expr>, Box<Expr>),
-	FnInvoke(Box<Expr>, Vec<Expr>),
-	Typedef(Vec<TypedefField>),
-	While(Block),
+	Binary(BinaryOp, ExprId, ExprId),
+	Unary(UnaryOp, ExprId),
+	FnInvoke(ExprId, Vec<ExprId>),
+	/// A record type, after the names of its type parameters
+	Typedef(Vec<Symbol>, Vec<TypedefField>),
+	/// A tagged union, whose variants are in the same scope as the enum itself,
+	/// after the names of its type parameters
+	Enum(Vec<Symbol>, Vec<EnumVariant>),
+	Block(Block),
+	// The else branch is either a `Block` or another `If`.
+	If(ExprId, Block, Option<ExprId>),
+	While(ExprId, Block),
+	/// `for pattern in iterable { ... }`, which runs the body once for each item,
+	/// destructured by the pattern.
+	///
+	/// Ranges, lists and strings yield their items and characters in order, and
+	/// maps yield `(key, value)` tuples. Any other record is iterated by calling
+	/// its `next` field with no arguments until it returns `nil`.
+	For(PatternId, ExprId, Block),
+	/// The arms are tried in order, and the first whose pattern matches and whose
+	/// guard holds is evaluated
+	Match(ExprId, Vec<MatchArm>),
+	Lambda(Vec<Symbol>, Block),
 	NumLit(f64),
 	StrLit(String),
-	Group(Box<Expr>),
-	Reference(String),
-	// term → factor ( ( "-" | "+" ) factor )* ;
-	Term(Box<Expr>),
-	// factor → unary ( ( "/" | "*" ) unary )* ;
-	Factor(Box<Expr>),
+	BoolLit(bool),
+	/// `()`, the value of expressions evaluated only for their effects
+	UnitLit,
+	/// `nil`, an explicitly absent value
+	NilLit,
+	Group(ExprId),
+	List(Vec<ExprId>),
+	/// Key-value pairs in source order
+	Map(Vec<(ExprId, ExprId)>),
+	Tuple(Vec<ExprId>),
+	/// `xs[i]`, which can be overloaded like a binary operator through
+	/// `Overload::INDEX`
+	Index(ExprId, ExprId),
+	/// `xs[start..end]`, where either bound may be omitted
+	Slice(ExprId, Option<ExprId>, Option<ExprId>),
+	Record(RecordLit),
+	/// `start..end`, which includes `end` as well when the flag is set, written
+	/// `start..=end`
+	Range(ExprId, ExprId, bool),
+	/// `x?`, which unwraps an `Ok` or `Some`, and otherwise returns `x` from the
+	/// enclosing function right away
+	Try(ExprId),
+	/// `p.x`
+	Field(ExprId, Symbol),
+	/// `target = value`, where the target is a variable, field or list element
+	Assign(ExprId, ExprId),
+	/// `target += value` and the like, which combine the target's value with
+	/// `value` using the operator before storing it
+	CompoundAssign(BinaryOp, ExprId, ExprId),
+	Reference(Symbol),
+	/// `_` as an argument of a call on the right of a pipe, where the piped value
+	/// is passed
+	Placeholder,
+}
+
+impl ExprKind {
+	/// Whether the expression ends in a block, and so can be used as a statement
+	/// without a trailing semicolon.
+	pub fn is_block_like(&self) -> bool {
+		matches!(
+			self,
+			Self::Block(_) | Self::If(..) | Self::While(..) | Self::For(..) | Self::Match(..)
+		)
+	}
 }
@@ -1,6 +1,12 @@
-#[derive(Debug, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+
+use super::{ExprId, TypeExprId};
+use crate::intern::Symbol;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct TypedefField {
-	pub name: String,
-	pub type_name: Option<String>,
-	pub default_value: Option<String>,
+	pub name: Symbol,
+	pub type_expr: Option<TypeExprId>,
+	/// The value used when a record literal omits this field
+	pub default_value: Option<ExprId>,
 }
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Attribute, Block, TypeExprId, TypedefField};
+use crate::intern::Symbol;
+
+/// `fn name<T>(a: T, b: Str = "x") -> [T] { ... }`, which can be called from
+/// anywhere in the scope it is declared in, including before the declaration
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct FnDecl {
+	pub attributes: Vec<Attribute>,
+	/// Whether it is a `const fn`, which constants can call
+	pub constant: bool,
+	pub name: Symbol,
+	/// The names of the type parameters, like `T` in `fn first<T>(xs: [T])`
+	pub type_params: Vec<Symbol>,
+	/// Declared like the fields of a typedef, where the defaults are used for
+	/// arguments left off the end of a call
+	pub params: Vec<TypedefField>,
+	pub return_type: Option<TypeExprId>,
+	pub body: Block,
+}
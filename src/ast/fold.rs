@@ -0,0 +1,466 @@
+use super::{
+	Arena, Attribute, Block, ConstDecl, EnumVariant, ExprId, ExprKind, FieldInit, FieldPattern,
+	FnDecl, ImplDecl, InterfaceDecl, InterfaceMethod, LetDecl, MatchArm, PatternId, PatternKind,
+	RecordLit, StmtId, StmtKind, TypeExprId, TypeExprKind, TypedefField,
+};
+
+/// Rebuilds a tree in another arena, for passes that replace nodes with
+/// different ones.
+///
+/// Like `Visitor`, each method defaults to the `fold_*` function of the same
+/// name, which adds the node to the target arena with its children folded,
+/// keeping its span. Folding with the defaults alone copies the tree.
+pub trait Fold<'ast>: Sized {
+	/// The arena of the tree being folded
+	fn source(&self) -> &'ast Arena;
+
+	/// The arena the folded tree is built in
+	fn target(&mut self) -> &mut Arena;
+
+	fn fold_stmt(&mut self, id: StmtId) -> StmtId {
+		fold_stmt(self, id)
+	}
+
+	fn fold_expr(&mut self, id: ExprId) -> ExprId {
+		fold_expr(self, id)
+	}
+
+	fn fold_block(&mut self, block: &'ast Block) -> Block {
+		fold_block(self, block)
+	}
+
+	fn fold_fn_decl(&mut self, decl: &'ast FnDecl) -> FnDecl {
+		fold_fn_decl(self, decl)
+	}
+
+	fn fold_typedef_field(&mut self, field: &'ast TypedefField) -> TypedefField {
+		fold_typedef_field(self, field)
+	}
+
+	fn fold_pattern(&mut self, id: PatternId) -> PatternId {
+		fold_pattern(self, id)
+	}
+
+	fn fold_match_arm(&mut self, arm: &'ast MatchArm) -> MatchArm {
+		fold_match_arm(self, arm)
+	}
+
+	fn fold_type_expr(&mut self, id: TypeExprId) -> TypeExprId {
+		fold_type_expr(self, id)
+	}
+
+	fn fold_attribute(&mut self, attribute: &'ast Attribute) -> Attribute {
+		fold_attribute(self, attribute)
+	}
+}
+
+/// Copies trees out of one arena into a new one, leaving behind the nodes that
+/// aren't in them.
+///
+/// The copies are numbered in the order they're finished, so trees copied into
+/// new arenas compare equal exactly when they are written the same.
+pub struct Copier<'ast> {
+	source: &'ast Arena,
+	target: Arena,
+}
+
+impl<'ast> Copier<'ast> {
+	pub fn new(source: &'ast Arena) -> Self {
+		Self {
+			source,
+			target: Arena::default(),
+		}
+	}
+
+	/// The arena the copies were made in
+	pub fn finish(self) -> Arena {
+		self.target
+	}
+}
+
+impl<'ast> Fold<'ast> for Copier<'ast> {
+	fn source(&self) -> &'ast Arena {
+		self.source
+	}
+
+	fn target(&mut self) -> &mut Arena {
+		&mut self.target
+	}
+}
+
+fn fold_exprs<'ast, F: Fold<'ast>>(folder: &mut F, exprs: &[ExprId]) -> Vec<ExprId> {
+	exprs.iter().map(|&expr| folder.fold_expr(expr)).collect()
+}
+
+fn fold_fields<'ast, F: Fold<'ast>>(
+	folder: &mut F,
+	fields: &'ast [TypedefField],
+) -> Vec<TypedefField> {
+	fields
+		.iter()
+		.map(|field| folder.fold_typedef_field(field))
+		.collect()
+}
+
+fn fold_attributes<'ast, F: Fold<'ast>>(
+	folder: &mut F,
+	attributes: &'ast [Attribute],
+) -> Vec<Attribute> {
+	attributes
+		.iter()
+		.map(|attribute| folder.fold_attribute(attribute))
+		.collect()
+}
+
+pub fn fold_stmt<'ast, F: Fold<'ast>>(folder: &mut F, id: StmtId) -> StmtId {
+	let stmt = &folder.source()[id];
+
+	let kind = match &stmt.kind {
+		StmtKind::LetDecl(decl) => StmtKind::LetDecl(Box::new(LetDecl {
+			attributes: fold_attributes(folder, &decl.attributes),
+			mutable: decl.mutable,
+			pattern: folder.fold_pattern(decl.pattern),
+			value: folder.fold_expr(decl.value),
+		})),
+		StmtKind::ConstDecl(decl) => StmtKind::ConstDecl(Box::new(ConstDecl {
+			attributes: fold_attributes(folder, &decl.attributes),
+			name: decl.name,
+			value: folder.fold_expr(decl.value),
+		})),
+		StmtKind::FnDecl(decl) => StmtKind::FnDecl(Box::new(folder.fold_fn_decl(decl))),
+		StmtKind::InterfaceDecl(decl) => {
+			let methods = decl
+				.methods
+				.iter()
+				.map(|method| InterfaceMethod {
+					name: method.name,
+					type_params: method.type_params.clone(),
+					params: fold_fields(folder, &method.params),
+					return_type: method
+						.return_type
+						.map(|return_type| folder.fold_type_expr(return_type)),
+					default: method
+						.default
+						.as_ref()
+						.map(|default| folder.fold_block(default)),
+				})
+				.collect();
+
+			StmtKind::InterfaceDecl(Box::new(InterfaceDecl {
+				name: decl.name,
+				methods,
+			}))
+		}
+		StmtKind::ImplDecl(decl) => StmtKind::ImplDecl(Box::new(ImplDecl {
+			interface: decl.interface,
+			type_name: decl.type_name,
+			methods: decl
+				.methods
+				.iter()
+				.map(|method| folder.fold_fn_decl(method))
+				.collect(),
+		})),
+		StmtKind::Import(import) => StmtKind::Import(import.clone()),
+		StmtKind::Export(exported) => StmtKind::Export(folder.fold_stmt(*exported)),
+		StmtKind::Expr(expr) => StmtKind::Expr(folder.fold_expr(*expr)),
+	};
+
+	folder.target().alloc_stmt(kind, stmt.span)
+}
+
+pub fn fold_expr<'ast, F: Fold<'ast>>(folder: &mut F, id: ExprId) -> ExprId {
+	let expr = &folder.source()[id];
+
+	let kind = match &expr.kind {
+		ExprKind::Binary(op, left, right) => {
+			ExprKind::Binary(*op, folder.fold_expr(*left), folder.fold_expr(*right))
+		}
+		ExprKind::Unary(op, operand) => ExprKind::Unary(*op, folder.fold_expr(*operand)),
+		ExprKind::FnInvoke(callee, args) => {
+			ExprKind::FnInvoke(folder.fold_expr(*callee), fold_exprs(folder, args))
+		}
+		ExprKind::Typedef(type_params, fields) => {
+			ExprKind::Typedef(type_params.clone(), fold_fields(folder, fields))
+		}
+		ExprKind::Enum(type_params, variants) => ExprKind::Enum(
+			type_params.clone(),
+			variants
+				.iter()
+				.map(|variant| EnumVariant {
+					name: variant.name,
+					fields: fold_fields(folder, &variant.fields),
+				})
+				.collect(),
+		),
+		ExprKind::Block(block) => ExprKind::Block(folder.fold_block(block)),
+		ExprKind::If(condition, then_branch, else_branch) => ExprKind::If(
+			folder.fold_expr(*condition),
+			folder.fold_block(then_branch),
+			else_branch.map(|else_branch| folder.fold_expr(else_branch)),
+		),
+		ExprKind::While(condition, body) => {
+			ExprKind::While(folder.fold_expr(*condition), folder.fold_block(body))
+		}
+		ExprKind::For(pattern, iterable, body) => ExprKind::For(
+			folder.fold_pattern(*pattern),
+			folder.fold_expr(*iterable),
+			folder.fold_block(body),
+		),
+		ExprKind::Match(value, arms) => ExprKind::Match(
+			folder.fold_expr(*value),
+			arms.iter().map(|arm| folder.fold_match_arm(arm)).collect(),
+		),
+		ExprKind::Lambda(params, body) => ExprKind::Lambda(params.clone(), folder.fold_block(body)),
+		ExprKind::Group(inner) => ExprKind::Group(folder.fold_expr(*inner)),
+		ExprKind::List(items) => ExprKind::List(fold_exprs(folder, items)),
+		ExprKind::Tuple(items) => ExprKind::Tuple(fold_exprs(folder, items)),
+		ExprKind::Map(entries) => ExprKind::Map(
+			entries
+				.iter()
+				.map(|&(key, value)| (folder.fold_expr(key), folder.fold_expr(value)))
+				.collect(),
+		),
+		ExprKind::Index(target, index) => {
+			ExprKind::Index(folder.fold_expr(*target), folder.fold_expr(*index))
+		}
+		ExprKind::Slice(target, start, end) => ExprKind::Slice(
+			folder.fold_expr(*target),
+			start.map(|start| folder.fold_expr(start)),
+			end.map(|end| folder.fold_expr(end)),
+		),
+		ExprKind::Record(record) => ExprKind::Record(RecordLit {
+			type_name: record.type_name,
+			base: record.base.map(|base| folder.fold_expr(base)),
+			fields: record
+				.fields
+				.iter()
+				.map(|field| FieldInit {
+					name: field.name,
+					value: folder.fold_expr(field.value),
+					span: field.span,
+				})
+				.collect(),
+		}),
+		ExprKind::Range(start, end, inclusive) => {
+			ExprKind::Range(folder.fold_expr(*start), folder.fold_expr(*end), *inclusive)
+		}
+		ExprKind::Try(operand) => ExprKind::Try(folder.fold_expr(*operand)),
+		ExprKind::Field(target, field) => ExprKind::Field(folder.fold_expr(*target), *field),
+		ExprKind::Assign(target, value) => {
+			ExprKind::Assign(folder.fold_expr(*target), folder.fold_expr(*value))
+		}
+		ExprKind::CompoundAssign(op, target, value) => {
+			ExprKind::CompoundAssign(*op, folder.fold_expr(*target), folder.fold_expr(*value))
+		}
+		ExprKind::NumLit(num) => ExprKind::NumLit(*num),
+		ExprKind::StrLit(str) => ExprKind::StrLit(str.clone()),
+		ExprKind::BoolLit(bool) => ExprKind::BoolLit(*bool),
+		ExprKind::UnitLit => ExprKind::UnitLit,
+		ExprKind::NilLit => ExprKind::NilLit,
+		ExprKind::Reference(name) => ExprKind::Reference(*name),
+		ExprKind::Placeholder => ExprKind::Placeholder,
+	};
+
+	folder.target().alloc_expr(kind, expr.span)
+}
+
+pub fn fold_block<'ast, F: Fold<'ast>>(folder: &mut F, block: &'ast Block) -> Block {
+	Block {
+		stmts: block
+			.stmts
+			.iter()
+			.map(|&stmt| folder.fold_stmt(stmt))
+			.collect(),
+		expr: block.expr.map(|expr| folder.fold_expr(expr)),
+	}
+}
+
+pub fn fold_fn_decl<'ast, F: Fold<'ast>>(folder: &mut F, decl: &'ast FnDecl) -> FnDecl {
+	FnDecl {
+		attributes: fold_attributes(folder, &decl.attributes),
+		constant: decl.constant,
+		name: decl.name,
+		type_params: decl.type_params.clone(),
+		params: fold_fields(folder, &decl.params),
+		return_type: decl
+			.return_type
+			.map(|return_type| folder.fold_type_expr(return_type)),
+		body: folder.fold_block(&decl.body),
+	}
+}
+
+pub fn fold_typedef_field<'ast, F: Fold<'ast>>(
+	folder: &mut F,
+	field: &'ast TypedefField,
+) -> TypedefField {
+	TypedefField {
+		name: field.name,
+		type_expr: field
+			.type_expr
+			.map(|type_expr| folder.fold_type_expr(type_expr)),
+		default_value: field.default_value.map(|value| folder.fold_expr(value)),
+	}
+}
+
+pub fn fold_pattern<'ast, F: Fold<'ast>>(folder: &mut F, id: PatternId) -> PatternId {
+	let pattern = &folder.source()[id];
+
+	let fold_patterns = |folder: &mut F, patterns: &[PatternId]| {
+		patterns
+			.iter()
+			.map(|&pattern| folder.fold_pattern(pattern))
+			.collect()
+	};
+
+	let kind = match &pattern.kind {
+		PatternKind::Literal(literal) => PatternKind::Literal(folder.fold_expr(*literal)),
+		PatternKind::Tuple(items) => PatternKind::Tuple(fold_patterns(folder, items)),
+		PatternKind::List(items) => PatternKind::List(fold_patterns(folder, items)),
+		PatternKind::Or(alternatives) => PatternKind::Or(fold_patterns(folder, alternatives)),
+		PatternKind::Variant {
+			enum_name,
+			variant,
+			fields,
+		} => PatternKind::Variant {
+			enum_name: *enum_name,
+			variant: *variant,
+			fields: fold_patterns(folder, fields),
+		},
+		PatternKind::Record { type_name, fields } => PatternKind::Record {
+			type_name: *type_name,
+			fields: fields
+				.iter()
+				.map(|field| FieldPattern {
+					name: field.name,
+					pattern: folder.fold_pattern(field.pattern),
+					span: field.span,
+				})
+				.collect(),
+		},
+		PatternKind::Wildcard => PatternKind::Wildcard,
+		PatternKind::Binding(name) => PatternKind::Binding(*name),
+		PatternKind::Rest(name) => PatternKind::Rest(*name),
+	};
+
+	folder.target().alloc_pattern(kind, pattern.span)
+}
+
+pub fn fold_match_arm<'ast, F: Fold<'ast>>(folder: &mut F, arm: &'ast MatchArm) -> MatchArm {
+	MatchArm {
+		pattern: folder.fold_pattern(arm.pattern),
+		guard: arm.guard.map(|guard| folder.fold_expr(guard)),
+		body: folder.fold_expr(arm.body),
+	}
+}
+
+pub fn fold_type_expr<'ast, F: Fold<'ast>>(folder: &mut F, id: TypeExprId) -> TypeExprId {
+	let type_expr = &folder.source()[id];
+
+	let fold_types = |folder: &mut F, types: &[TypeExprId]| {
+		types
+			.iter()
+			.map(|&type_expr| folder.fold_type_expr(type_expr))
+			.collect()
+	};
+
+	let kind = match &type_expr.kind {
+		TypeExprKind::List(inner) => TypeExprKind::List(folder.fold_type_expr(*inner)),
+		TypeExprKind::Optional(inner) => TypeExprKind::Optional(folder.fold_type_expr(*inner)),
+		TypeExprKind::Map(key, value) => {
+			TypeExprKind::Map(folder.fold_type_expr(*key), folder.fold_type_expr(*value))
+		}
+		TypeExprKind::Named(name, args) => TypeExprKind::Named(*name, fold_types(folder, args)),
+		TypeExprKind::Tuple(items) => TypeExprKind::Tuple(fold_types(folder, items)),
+		TypeExprKind::Function(params, result) => {
+			TypeExprKind::Function(fold_types(folder, params), folder.fold_type_expr(*result))
+		}
+	};
+
+	folder.target().alloc_type_expr(kind, type_expr.span)
+}
+
+pub fn fold_attribute<'ast, F: Fold<'ast>>(
+	folder: &mut F,
+	attribute: &'ast Attribute,
+) -> Attribute {
+	Attribute {
+		name: attribute.name,
+		args: fold_exprs(folder, &attribute.args),
+		span: attribute.span,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		lower::lower,
+		parse::{parse, SourceFile},
+	};
+
+	/// Replaces every number literal with its double
+	struct Doubler<'ast> {
+		source: &'ast Arena,
+		target: Arena,
+	}
+
+	impl<'ast> Fold<'ast> for Doubler<'ast> {
+		fn source(&self) -> &'ast Arena {
+			self.source
+		}
+
+		fn target(&mut self) -> &mut Arena {
+			&mut self.target
+		}
+
+		fn fold_expr(&mut self, id: ExprId) -> ExprId {
+			let expr = &self.source[id];
+
+			match expr.kind {
+				ExprKind::NumLit(n) => self.target.alloc_expr(ExprKind::NumLit(n * 2.0), expr.span),
+				_ => fold_expr(self, id),
+			}
+		}
+	}
+
+	#[test]
+	fn folds_every_expression() {
+		let source = |a, b, c, d, e| {
+			format!(
+				"typedef P = {{ x: [Num] = [{a}] }}
+				fn f(y = {b}) -> Num {{ match y {{ {c} => P {{ x: [{d}] }}, _ => -{e} }} }}"
+			)
+		};
+		let parse_str = |source: String| parse(&mut source.as_bytes()).unwrap();
+
+		let file = parse_str(source(1, 2, 3, 4, 5));
+		let mut doubler = Doubler {
+			source: &file.arena,
+			target: Arena::default(),
+		};
+		let stmts = file
+			.stmts
+			.iter()
+			.map(|&stmt| doubler.fold_stmt(stmt))
+			.collect();
+		let folded = SourceFile {
+			arena: doubler.target,
+			stmts,
+			..Default::default()
+		};
+
+		assert_eq!(folded, parse_str(source(2, 4, 6, 8, 10)));
+	}
+
+	#[test]
+	fn copies_leave_behind_nodes_no_longer_in_the_tree() {
+		let file = lower(parse(&mut "a >> f(b);".as_bytes()).unwrap()).unwrap();
+
+		let mut copier = Copier::new(&file.arena);
+		copier.fold_stmt(file.stmts[0]);
+
+		// The call `f(b)` the pipe was lowered from is left behind
+		assert_eq!(file.arena.len(), 6);
+		assert_eq!(copier.finish().len(), 5);
+	}
+}
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+use super::FnDecl;
+use crate::intern::Symbol;
+
+/// `impl Interface for Type { fn method(self) { ... } ... }`.
+///
+/// A call `value.method(args)` on a value of the type calls its field `method`
+/// if it has one, and otherwise the method from the impl, or the interface's
+/// default if the impl leaves it out, with `value` passed as `self`.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ImplDecl {
+	pub interface: Symbol,
+	pub type_name: Symbol,
+	pub methods: Vec<FnDecl>,
+}
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+use crate::intern::Symbol;
+
+/// `import "path" as m;` or `import { a, b } from "path";`, where the path is
+/// relative to the importing file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Import {
+	pub path: String,
+	pub names: ImportNames,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ImportNames {
+	/// Every export of the module, accessed as fields of the given name
+	Module(Symbol),
+	/// Only the listed exports, brought into scope under their own names
+	Only(Vec<Symbol>),
+}
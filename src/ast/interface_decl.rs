@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Block, TypeExprId, TypedefField};
+use crate::intern::Symbol;
+
+/// `interface Name { fn method(self, x: Num) -> Num; ... }`, the methods a type
+/// has to provide to implement it
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct InterfaceDecl {
+	pub name: Symbol,
+	pub methods: Vec<InterfaceMethod>,
+}
+
+/// The signature of a method, which takes the value it is called on as its
+/// first parameter `self`
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct InterfaceMethod {
+	pub name: Symbol,
+	pub type_params: Vec<Symbol>,
+	pub params: Vec<TypedefField>,
+	pub return_type: Option<TypeExprId>,
+	/// The body used by implementations that leave the method out
+	pub default: Option<Block>,
+}
@@ -1,7 +1,12 @@
-use super::Expr;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq)]
+use super::{Attribute, ExprId, PatternId};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct LetDecl {
-	pub name: String,
-	pub value: Box<Expr>,
+	pub attributes: Vec<Attribute>,
+	/// Whether the names bound by the pattern can be assigned to, from `let mut`
+	pub mutable: bool,
+	pub pattern: PatternId,
+	pub value: ExprId,
 }
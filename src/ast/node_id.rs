@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+/// Identifies a statement, expression, pattern or type expression within the
+/// arena of its file, so that passes can keep what they learn about nodes in
+/// side tables like `HashMap<NodeId, Type>` instead of in the tree itself.
+///
+/// Nodes are numbered in the order they're added to the arena, which is after
+/// their children, and keep their ID for as long as the arena lives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct NodeId(pub u32);
+
+impl NodeId {
+	pub fn index(self) -> usize {
+		self.0 as usize
+	}
+}
+
+/// Declares IDs that only refer to one kind of node, which the arena can be
+/// indexed with to get that node
+macro_rules! typed_ids {
+	($($(#[$doc:meta])* $name:ident,)*) => {$(
+		$(#[$doc])*
+		#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+		pub struct $name(pub(super) NodeId);
+
+		impl From<$name> for NodeId {
+			fn from(id: $name) -> NodeId {
+				id.0
+			}
+		}
+	)*};
+}
+
+typed_ids! {
+	/// The ID of a `Stmt`
+	StmtId,
+	/// The ID of an `Expr`
+	ExprId,
+	/// The ID of a `Pattern`
+	PatternId,
+	/// The ID of a `TypeExpr`
+	TypeExprId,
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+use super::{ExprId, PatternId, PatternKind, Span};
+use crate::intern::Symbol;
+
+/// The shape a value is matched against in a `match` arm or destructured into
+/// by a `let`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Pattern {
+	pub kind: PatternKind,
+	pub span: Span,
+}
+
+/// Spans are ignored so that trees can be compared by shape alone.
+impl PartialEq for Pattern {
+	fn eq(&self, other: &Self) -> bool {
+		self.kind == other.kind
+	}
+}
+
+/// A `name: pattern` pair in a record pattern, where `name` alone is short for
+/// `name: name`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldPattern {
+	pub name: Symbol,
+	pub pattern: PatternId,
+	pub span: Span,
+}
+
+/// Spans are ignored so that trees can be compared by shape alone.
+impl PartialEq for FieldPattern {
+	fn eq(&self, other: &Self) -> bool {
+		self.name == other.name && self.pattern == other.pattern
+	}
+}
+
+/// `pattern if guard => body`
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct MatchArm {
+	pub pattern: PatternId,
+	pub guard: Option<ExprId>,
+	pub body: ExprId,
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+use super::{ExprId, FieldPattern, PatternId};
+use crate::intern::Symbol;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum PatternKind {
+	/// `_`, which matches anything without binding it
+	Wildcard,
+	/// A bare name, which matches a variant without fields if one of that name is
+	/// in scope and otherwise matches anything and binds it
+	Binding(Symbol),
+	/// A number, string, boolean, `nil` or `()`, matched by equality
+	Literal(ExprId),
+	Tuple(Vec<PatternId>),
+	/// `[first, ..rest]`, with at most one `Rest` among the items
+	List(Vec<PatternId>),
+	/// `..` or `..name` in a list pattern, matching the items no other pattern
+	/// in the list does
+	Rest(Option<Symbol>),
+	/// `Point { x, y: 0 }`, or `{ x, y }` for any record. Fields left out are
+	/// not matched against.
+	Record {
+		type_name: Option<Symbol>,
+		fields: Vec<FieldPattern>,
+	},
+	/// `Circle(r)` or `Shape.Circle(r)`
+	Variant {
+		enum_name: Option<Symbol>,
+		variant: Symbol,
+		fields: Vec<PatternId>,
+	},
+	/// `a | b`, matching if any of the alternatives does
+	Or(Vec<PatternId>),
+}
@@ -1,6 +0,0 @@
-use super::Expr;
-
-pub struct PipeExpr {
-	pub left: Box<Expr>,
-	pub right: Box<Expr>,
-}
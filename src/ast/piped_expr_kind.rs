@@ -1,6 +0,0 @@
-use super::{expr::Expr, pipe_expr::PipeExpr};
-
-pub enum PipedExprKind {
-	Expr(Expr),
-	PipeExpr(PipeExpr),
-}
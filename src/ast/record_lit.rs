@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+use super::{ExprId, Span};
+use crate::intern::Symbol;
+
+/// A record literal, `Point { x: 1, y: 2 }`, or a functional update of an
+/// existing record, `{ ..p, x: 3 }`
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordLit {
+	/// The typedef being constructed, which is the type of `base` when absent
+	pub type_name: Option<Symbol>,
+	/// The record that fields missing from `fields` are copied from
+	pub base: Option<ExprId>,
+	pub fields: Vec<FieldInit>,
+}
+
+/// A `name: value` pair in a record literal
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldInit {
+	pub name: Symbol,
+	pub value: ExprId,
+	pub span: Span,
+}
+
+/// Spans are ignored so that trees can be compared by shape alone.
+impl PartialEq for FieldInit {
+	fn eq(&self, other: &Self) -> bool {
+		self.name == other.name && self.value == other.value
+	}
+}
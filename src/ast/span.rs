@@ -0,0 +1,34 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::ExpansionId;
+
+/// The position in the source of the first token of a node.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+	pub row: usize,
+	pub col: usize,
+	/// The macro invocation the node was expanded from, if any, in which case
+	/// the position is in the macro's definition
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub expansion: Option<ExpansionId>,
+}
+
+impl Span {
+	pub fn new(row: usize, col: usize) -> Self {
+		Self {
+			row,
+			col,
+			expansion: None,
+		}
+	}
+}
+
+/// Shows the position alone, the invocations it was expanded through are shown
+/// by the `Backtrace` of the file's `Expansions`
+impl fmt::Display for Span {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}:{}", self.row, self.col)
+	}
+}
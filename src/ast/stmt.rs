@@ -1,6 +1,16 @@
-use super::StmtKind;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq)]
+use super::{Span, StmtKind};
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Stmt {
 	pub kind: StmtKind,
+	pub span: Span,
+}
+
+/// Spans are ignored so that trees can be compared by shape alone.
+impl PartialEq for Stmt {
+	fn eq(&self, other: &Self) -> bool {
+		self.kind == other.kind
+	}
 }
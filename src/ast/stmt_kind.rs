@@ -1,6 +1,17 @@
-use super::{BlockDecl, LetDecl};
-#[derive(Debug, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+use super::{ConstDecl, ExprId, FnDecl, ImplDecl, Import, InterfaceDecl, LetDecl, StmtId};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum StmtKind {
 	LetDecl(Box<LetDecl>),
-	BlockDecl(Box<BlockDecl>),
+	FnDecl(Box<FnDecl>),
+	ConstDecl(Box<ConstDecl>),
+	Import(Box<Import>),
+	InterfaceDecl(Box<InterfaceDecl>),
+	ImplDecl(Box<ImplDecl>),
+	/// A `let`, `const`, `fn`, `typedef`, `enum` or `interface` marked with `export`, which other
+	/// modules can import
+	Export(StmtId),
+	Expr(ExprId),
 }
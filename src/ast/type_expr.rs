@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Span, TypeExprId};
+use crate::intern::Symbol;
+
+/// A type as written in an annotation, like `Num`, `[Pair<A, B>]` or `(A) -> B?`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TypeExpr {
+	pub kind: TypeExprKind,
+	pub span: Span,
+}
+
+impl TypeExpr {
+	/// The name of a named type, without its arguments
+	pub fn name(&self) -> Option<Symbol> {
+		match &self.kind {
+			TypeExprKind::Named(name, _) => Some(*name),
+			_ => None,
+		}
+	}
+}
+
+/// Spans are ignored so that trees can be compared by shape alone.
+impl PartialEq for TypeExpr {
+	fn eq(&self, other: &Self) -> bool {
+		self.kind == other.kind
+	}
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum TypeExprKind {
+	/// `Num` or `Pair<A, B>`, naming a builtin, a typedef, an enum or a type
+	/// parameter
+	Named(Symbol, Vec<TypeExprId>),
+	/// `(A, B) -> C`
+	Function(Vec<TypeExprId>, TypeExprId),
+	/// `[T]`
+	List(TypeExprId),
+	/// `{K: V}`
+	Map(TypeExprId, TypeExprId),
+	/// `(A, B)`, where `()` is the unit type
+	Tuple(Vec<TypeExprId>),
+	/// `T?`, which is either a `T` or `nil`
+	Optional(TypeExprId),
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnaryOp {
+	/// `-x`
+	Negate,
+	/// `+x`, which leaves the operand unchanged
+	Plus,
+	/// `!x`
+	Not,
+	/// `~x`
+	BitNot,
+}
+
+impl UnaryOp {
+	/// The operator as it is written in source
+	pub fn symbol(&self) -> &'static str {
+		match self {
+			Self::Negate => "-",
+			Self::Plus => "+",
+			Self::Not => "!",
+			Self::BitNot => "~",
+		}
+	}
+}
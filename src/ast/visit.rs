@@ -0,0 +1,316 @@
+use super::{
+	Arena, Attribute, Block, ExprId, ExprKind, FnDecl, MatchArm, PatternId, PatternKind, StmtId,
+	StmtKind, TypeExprId, TypeExprKind, TypedefField,
+};
+
+/// Walks a tree in its arena by reference.
+///
+/// Each method visits one kind of node and by default calls the `walk_*`
+/// function of the same name, which visits the node's children in source
+/// order. A pass overrides the methods for the nodes it cares about, calling
+/// the `walk_*` function itself to keep descending, and looks nodes up by ID in
+/// `arena`.
+pub trait Visitor<'ast>: Sized {
+	/// The arena of the tree being walked
+	fn arena(&self) -> &'ast Arena;
+
+	fn visit_stmt(&mut self, id: StmtId) {
+		walk_stmt(self, id);
+	}
+
+	fn visit_expr(&mut self, id: ExprId) {
+		walk_expr(self, id);
+	}
+
+	fn visit_block(&mut self, block: &'ast Block) {
+		walk_block(self, block);
+	}
+
+	fn visit_fn_decl(&mut self, decl: &'ast FnDecl) {
+		walk_fn_decl(self, decl);
+	}
+
+	fn visit_typedef_field(&mut self, field: &'ast TypedefField) {
+		walk_typedef_field(self, field);
+	}
+
+	fn visit_pattern(&mut self, id: PatternId) {
+		walk_pattern(self, id);
+	}
+
+	fn visit_match_arm(&mut self, arm: &'ast MatchArm) {
+		walk_match_arm(self, arm);
+	}
+
+	fn visit_type_expr(&mut self, id: TypeExprId) {
+		walk_type_expr(self, id);
+	}
+
+	fn visit_attribute(&mut self, attribute: &'ast Attribute) {
+		walk_attribute(self, attribute);
+	}
+}
+
+pub fn walk_stmt<'ast, V: Visitor<'ast>>(visitor: &mut V, id: StmtId) {
+	match &visitor.arena()[id].kind {
+		StmtKind::LetDecl(decl) => {
+			decl.attributes
+				.iter()
+				.for_each(|attribute| visitor.visit_attribute(attribute));
+			visitor.visit_pattern(decl.pattern);
+			visitor.visit_expr(decl.value);
+		}
+		StmtKind::ConstDecl(decl) => {
+			decl.attributes
+				.iter()
+				.for_each(|attribute| visitor.visit_attribute(attribute));
+			visitor.visit_expr(decl.value);
+		}
+		StmtKind::FnDecl(decl) => visitor.visit_fn_decl(decl),
+		StmtKind::InterfaceDecl(decl) => {
+			for method in &decl.methods {
+				method
+					.params
+					.iter()
+					.for_each(|param| visitor.visit_typedef_field(param));
+				if let Some(return_type) = method.return_type {
+					visitor.visit_type_expr(return_type);
+				}
+				if let Some(default) = &method.default {
+					visitor.visit_block(default);
+				}
+			}
+		}
+		StmtKind::ImplDecl(decl) => {
+			decl.methods
+				.iter()
+				.for_each(|method| visitor.visit_fn_decl(method));
+		}
+		StmtKind::Import(_) => {}
+		StmtKind::Export(exported) => visitor.visit_stmt(*exported),
+		StmtKind::Expr(expr) => visitor.visit_expr(*expr),
+	}
+}
+
+pub fn walk_expr<'ast, V: Visitor<'ast>>(visitor: &mut V, id: ExprId) {
+	match &visitor.arena()[id].kind {
+		ExprKind::Binary(_, left, right)
+		| ExprKind::Index(left, right)
+		| ExprKind::Range(left, right, _)
+		| ExprKind::Assign(left, right)
+		| ExprKind::CompoundAssign(_, left, right) => {
+			visitor.visit_expr(*left);
+			visitor.visit_expr(*right);
+		}
+		ExprKind::Unary(_, operand)
+		| ExprKind::Group(operand)
+		| ExprKind::Field(operand, _)
+		| ExprKind::Try(operand) => visitor.visit_expr(*operand),
+		ExprKind::FnInvoke(callee, args) => {
+			visitor.visit_expr(*callee);
+			args.iter().for_each(|&arg| visitor.visit_expr(arg));
+		}
+		ExprKind::Typedef(_, fields) => {
+			fields
+				.iter()
+				.for_each(|field| visitor.visit_typedef_field(field));
+		}
+		ExprKind::Enum(_, variants) => {
+			for variant in variants {
+				variant
+					.fields
+					.iter()
+					.for_each(|field| visitor.visit_typedef_field(field));
+			}
+		}
+		ExprKind::Block(block) | ExprKind::Lambda(_, block) => visitor.visit_block(block),
+		ExprKind::If(condition, then_branch, else_branch) => {
+			visitor.visit_expr(*condition);
+			visitor.visit_block(then_branch);
+			if let Some(else_branch) = else_branch {
+				visitor.visit_expr(*else_branch);
+			}
+		}
+		ExprKind::While(condition, body) => {
+			visitor.visit_expr(*condition);
+			visitor.visit_block(body);
+		}
+		ExprKind::For(pattern, iterable, body) => {
+			visitor.visit_pattern(*pattern);
+			visitor.visit_expr(*iterable);
+			visitor.visit_block(body);
+		}
+		ExprKind::Match(value, arms) => {
+			visitor.visit_expr(*value);
+			arms.iter().for_each(|arm| visitor.visit_match_arm(arm));
+		}
+		ExprKind::List(items) | ExprKind::Tuple(items) => {
+			items.iter().for_each(|&item| visitor.visit_expr(item));
+		}
+		ExprKind::Map(entries) => {
+			for (key, value) in entries {
+				visitor.visit_expr(*key);
+				visitor.visit_expr(*value);
+			}
+		}
+		ExprKind::Slice(target, start, end) => {
+			visitor.visit_expr(*target);
+			start
+				.iter()
+				.chain(end)
+				.for_each(|&bound| visitor.visit_expr(bound));
+		}
+		ExprKind::Record(record) => {
+			if let Some(base) = record.base {
+				visitor.visit_expr(base);
+			}
+			for field in &record.fields {
+				visitor.visit_expr(field.value);
+			}
+		}
+		ExprKind::NumLit(_)
+		| ExprKind::StrLit(_)
+		| ExprKind::BoolLit(_)
+		| ExprKind::UnitLit
+		| ExprKind::NilLit
+		| ExprKind::Reference(_)
+		| ExprKind::Placeholder => {}
+	}
+}
+
+pub fn walk_block<'ast, V: Visitor<'ast>>(visitor: &mut V, block: &'ast Block) {
+	block
+		.stmts
+		.iter()
+		.for_each(|&stmt| visitor.visit_stmt(stmt));
+
+	if let Some(expr) = block.expr {
+		visitor.visit_expr(expr);
+	}
+}
+
+pub fn walk_fn_decl<'ast, V: Visitor<'ast>>(visitor: &mut V, decl: &'ast FnDecl) {
+	decl.attributes
+		.iter()
+		.for_each(|attribute| visitor.visit_attribute(attribute));
+	decl.params
+		.iter()
+		.for_each(|param| visitor.visit_typedef_field(param));
+	if let Some(return_type) = decl.return_type {
+		visitor.visit_type_expr(return_type);
+	}
+	visitor.visit_block(&decl.body);
+}
+
+pub fn walk_typedef_field<'ast, V: Visitor<'ast>>(visitor: &mut V, field: &'ast TypedefField) {
+	if let Some(type_expr) = field.type_expr {
+		visitor.visit_type_expr(type_expr);
+	}
+	if let Some(default) = field.default_value {
+		visitor.visit_expr(default);
+	}
+}
+
+pub fn walk_pattern<'ast, V: Visitor<'ast>>(visitor: &mut V, id: PatternId) {
+	match &visitor.arena()[id].kind {
+		PatternKind::Literal(literal) => visitor.visit_expr(*literal),
+		PatternKind::Tuple(items)
+		| PatternKind::List(items)
+		| PatternKind::Variant { fields: items, .. }
+		| PatternKind::Or(items) => items.iter().for_each(|&item| visitor.visit_pattern(item)),
+		PatternKind::Record { fields, .. } => {
+			for field in fields {
+				visitor.visit_pattern(field.pattern);
+			}
+		}
+		PatternKind::Wildcard | PatternKind::Binding(_) | PatternKind::Rest(_) => {}
+	}
+}
+
+pub fn walk_match_arm<'ast, V: Visitor<'ast>>(visitor: &mut V, arm: &'ast MatchArm) {
+	visitor.visit_pattern(arm.pattern);
+	if let Some(guard) = arm.guard {
+		visitor.visit_expr(guard);
+	}
+	visitor.visit_expr(arm.body);
+}
+
+pub fn walk_type_expr<'ast, V: Visitor<'ast>>(visitor: &mut V, id: TypeExprId) {
+	match &visitor.arena()[id].kind {
+		TypeExprKind::Named(_, args) | TypeExprKind::Tuple(args) => {
+			args.iter().for_each(|&arg| visitor.visit_type_expr(arg));
+		}
+		TypeExprKind::Function(params, result) => {
+			params
+				.iter()
+				.for_each(|&param| visitor.visit_type_expr(param));
+			visitor.visit_type_expr(*result);
+		}
+		TypeExprKind::List(inner) | TypeExprKind::Optional(inner) => {
+			visitor.visit_type_expr(*inner);
+		}
+		TypeExprKind::Map(key, value) => {
+			visitor.visit_type_expr(*key);
+			visitor.visit_type_expr(*value);
+		}
+	}
+}
+
+pub fn walk_attribute<'ast, V: Visitor<'ast>>(visitor: &mut V, attribute: &'ast Attribute) {
+	attribute
+		.args
+		.iter()
+		.for_each(|&arg| visitor.visit_expr(arg));
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::parse::parse;
+
+	/// Collects every name referenced outside of typedefs
+	struct References<'ast> {
+		arena: &'ast Arena,
+		names: Vec<&'ast str>,
+	}
+
+	impl<'ast> Visitor<'ast> for References<'ast> {
+		fn arena(&self) -> &'ast Arena {
+			self.arena
+		}
+
+		fn visit_expr(&mut self, id: ExprId) {
+			match &self.arena[id].kind {
+				ExprKind::Reference(name) => self.names.push(name.as_str()),
+				ExprKind::Typedef(..) => return,
+				_ => {}
+			}
+
+			walk_expr(self, id);
+		}
+	}
+
+	#[test]
+	fn visits_every_reference_in_order() {
+		let file = parse(
+			&mut "typedef P = { x: Num = skipped }
+			@doc(a) fn f(y = b) { let [c, ..] = d; match e { 1 if g => h, _ => { i } } }
+			let z = j >> k(_, l) + P { x: m }.x;"
+				.as_bytes(),
+		)
+		.unwrap();
+
+		let mut references = References {
+			arena: &file.arena,
+			names: Vec::new(),
+		};
+		file.stmts
+			.iter()
+			.for_each(|&stmt| references.visit_stmt(stmt));
+
+		assert_eq!(
+			references.names,
+			vec!["a", "b", "d", "e", "g", "h", "i", "j", "k", "l", "m"]
+		);
+	}
+}
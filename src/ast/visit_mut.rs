@@ -0,0 +1,106 @@
+use super::{Arena, ExprId, Node, NodeId, PatternId, StmtId, TypeExprId};
+
+/// Walks a tree with its arena borrowed mutably, for passes that rewrite it in
+/// place.
+///
+/// Only nodes with IDs are visited, as it's through their IDs that they are
+/// rewritten with `Arena::update_expr` and the like. Like `Visitor`, each method
+/// defaults to the `walk_*_mut` function of the same name, which visits the
+/// node's children as they are once the node has been visited.
+pub trait VisitorMut: Sized {
+	/// The arena of the tree being walked
+	fn arena_mut(&mut self) -> &mut Arena;
+
+	fn visit_stmt_mut(&mut self, id: StmtId) {
+		walk_stmt_mut(self, id);
+	}
+
+	fn visit_expr_mut(&mut self, id: ExprId) {
+		walk_expr_mut(self, id);
+	}
+
+	fn visit_pattern_mut(&mut self, id: PatternId) {
+		walk_pattern_mut(self, id);
+	}
+
+	fn visit_type_expr_mut(&mut self, id: TypeExprId) {
+		walk_type_expr_mut(self, id);
+	}
+}
+
+pub fn walk_stmt_mut<V: VisitorMut>(visitor: &mut V, id: StmtId) {
+	walk_children_mut(visitor, id.into());
+}
+
+pub fn walk_expr_mut<V: VisitorMut>(visitor: &mut V, id: ExprId) {
+	walk_children_mut(visitor, id.into());
+}
+
+pub fn walk_pattern_mut<V: VisitorMut>(visitor: &mut V, id: PatternId) {
+	walk_children_mut(visitor, id.into());
+}
+
+pub fn walk_type_expr_mut<V: VisitorMut>(visitor: &mut V, id: TypeExprId) {
+	walk_children_mut(visitor, id.into());
+}
+
+fn walk_children_mut<V: VisitorMut>(visitor: &mut V, id: NodeId) {
+	for child in visitor.arena_mut().children(id) {
+		match visitor.arena_mut().get(child) {
+			Some(Node::Stmt(_)) => visitor.visit_stmt_mut(StmtId(child)),
+			Some(Node::Expr(_)) => visitor.visit_expr_mut(ExprId(child)),
+			Some(Node::Pattern(_)) => visitor.visit_pattern_mut(PatternId(child)),
+			Some(Node::TypeExpr(_)) => visitor.visit_type_expr_mut(TypeExprId(child)),
+			None => unreachable!("the children of a node are in its arena"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{ast::ExprKind, parse::parse};
+
+	/// Renames every reference to `old` into `new`
+	struct Renamer<'a>(&'a mut Arena);
+
+	impl VisitorMut for Renamer<'_> {
+		fn arena_mut(&mut self) -> &mut Arena {
+			self.0
+		}
+
+		fn visit_expr_mut(&mut self, id: ExprId) {
+			self.0.update_expr(id, |kind| {
+				if let ExprKind::Reference(name) = kind {
+					if name == "old" {
+						*name = "new".into();
+					}
+				}
+			});
+
+			walk_expr_mut(self, id);
+		}
+	}
+
+	#[test]
+	fn rewrites_nested_expressions() {
+		let parse_str = |source: &str| parse(&mut source.as_bytes()).unwrap();
+
+		let mut file = parse_str(
+			"fn f(x = old) { for y in old { if old { [old] } else { old[1] } } }
+			let z = { a: old, b: other };",
+		);
+		let mut renamer = Renamer(&mut file.arena);
+		file.stmts
+			.iter()
+			.for_each(|&stmt| renamer.visit_stmt_mut(stmt));
+
+		assert_eq!(
+			file,
+			parse_str(
+				"fn f(x = new) { for y in new { if new { [new] } else { new[1] } } }
+				let z = { a: new, b: other };",
+			)
+		);
+	}
+}
@@ -0,0 +1,7 @@
+mod checker;
+mod constant;
+mod diagnostic;
+
+pub use checker::*;
+pub use constant::*;
+pub use diagnostic::*;
@@ -0,0 +1,2352 @@
+use std::{
+	collections::{HashMap, HashSet},
+	ops::Deref,
+};
+
+use super::{ConstError, Constant, Diagnostic, DiagnosticKind};
+use crate::{
+	ast::{
+		Arena, BinaryOp, Block, Copier, EnumVariant, Expansions, ExprId, ExprKind, FieldPattern,
+		FnDecl, Fold, ImplDecl, Import, ImportNames, InterfaceDecl, InterfaceMethod, Overload,
+		PatternId, PatternKind, RecordLit, Span, StmtId, StmtKind, TypeExprId, TypedefField,
+	},
+	intern::Symbol,
+	module::{Module, Program},
+	parse::SourceFile,
+	prelude::prelude,
+};
+
+/// Checks a lowered file for mistakes that can be found without running it,
+/// without knowing anything about what it imports
+pub fn check(file: &SourceFile) -> Vec<Diagnostic> {
+	let mut checker = Checker::new(file, None);
+	checker.check_file(file);
+	checker.diagnostics
+}
+
+/// Checks one module of a program, knowing what its imports refer to
+pub fn check_module(program: &Program, module: &Module) -> Vec<Diagnostic> {
+	let mut checker = Checker::new(&module.file, Some((program, module)));
+	checker.check_file(&module.file);
+	checker.diagnostics
+}
+
+/// Evaluates the constants declared at the top level of a lowered file, giving
+/// the value of each one that could be evaluated
+pub fn evaluate_constants(file: &SourceFile) -> HashMap<String, Constant> {
+	let mut checker = Checker::new(file, None);
+	checker.check_file(file);
+
+	let file_scope = checker.scopes.last().expect("there is always a file scope");
+
+	file_scope
+		.iter()
+		.filter_map(|(name, binding)| match binding {
+			Binding::Const(index) => Some((name.to_string(), checker.constants[*index].clone()?)),
+			_ => None,
+		})
+		.collect()
+}
+
+/// The bindings a module makes available to others with `export`
+fn exports(file: &SourceFile) -> HashMap<Symbol, Binding<'_>> {
+	let arena = &file.arena;
+	let mut exports = HashMap::new();
+
+	for &stmt in &file.stmts {
+		let StmtKind::Export(exported) = arena[stmt].kind else {
+			continue;
+		};
+
+		match &arena[exported].kind {
+			StmtKind::FnDecl(decl) => {
+				exports.insert(decl.name, Binding::Function(Decl { arena, node: decl }));
+			}
+			StmtKind::InterfaceDecl(decl) => {
+				exports.insert(decl.name, Binding::Interface(Decl { arena, node: decl }));
+			}
+			// The values of another module's constants aren't known while checking
+			// this one
+			StmtKind::ConstDecl(decl) => {
+				let value = Binding::Value {
+					record_type: None,
+					mutable: false,
+				};
+				exports.insert(decl.name, value);
+			}
+			StmtKind::LetDecl(decl) => match (&arena[decl.pattern].kind, &arena[decl.value].kind) {
+				(PatternKind::Binding(name), ExprKind::Typedef(_, fields)) => {
+					exports.insert(
+						*name,
+						Binding::Typedef(Decl {
+							arena,
+							node: fields,
+						}),
+					);
+				}
+				// Exporting an enum exports its variants with it
+				(PatternKind::Binding(name), ExprKind::Enum(_, variants)) => {
+					exports.insert(
+						*name,
+						Binding::Enum(Decl {
+							arena,
+							node: variants,
+						}),
+					);
+					for variant in variants {
+						exports.insert(
+							variant.name,
+							Binding::Variant(Decl {
+								arena,
+								node: variant,
+							}),
+						);
+					}
+				}
+				_ => {
+					let mut names = Vec::new();
+					bound_names(arena, decl.pattern, &mut names);
+
+					for name in names {
+						let value = Binding::Value {
+							record_type: None,
+							mutable: false,
+						};
+						exports.insert(name, value);
+					}
+				}
+			},
+			_ => {}
+		}
+	}
+
+	exports
+}
+
+/// Collects the names a pattern may bind, without telling apart bare names that
+/// are variants
+fn bound_names(arena: &Arena, pattern: PatternId, names: &mut Vec<Symbol>) {
+	match &arena[pattern].kind {
+		PatternKind::Binding(name) | PatternKind::Rest(Some(name)) => names.push(*name),
+		PatternKind::Wildcard | PatternKind::Literal(_) | PatternKind::Rest(None) => {}
+		PatternKind::Tuple(items)
+		| PatternKind::List(items)
+		| PatternKind::Variant { fields: items, .. } => {
+			items
+				.iter()
+				.for_each(|&item| bound_names(arena, item, names));
+		}
+		PatternKind::Record { fields, .. } => {
+			for field in fields {
+				bound_names(arena, field.pattern, names);
+			}
+		}
+		// Every alternative binds the same names
+		PatternKind::Or(alternatives) => {
+			if let Some(&first) = alternatives.first() {
+				bound_names(arena, first, names);
+			}
+		}
+	}
+}
+
+/// Whether an impl's method can stand in for the one declared by its interface,
+/// taking as many parameters and type parameters, of the same types where both
+/// give one
+fn signature_matches(declared: Decl<'_, InterfaceMethod>, method: Decl<'_, FnDecl>) -> bool {
+	// Types from different arenas are compared by copies numbered by their shape
+	let copy = |arena, type_expr| {
+		let mut copier = Copier::new(arena);
+		copier.fold_type_expr(type_expr);
+		copier.finish()
+	};
+	let same = |a: Option<TypeExprId>, b: Option<TypeExprId>| match (a, b) {
+		(Some(a), Some(b)) => copy(declared.arena, a) == copy(method.arena, b),
+		_ => true,
+	};
+
+	declared.type_params.len() == method.type_params.len()
+		&& declared.params.len() == method.params.len()
+		&& declared
+			.params
+			.iter()
+			.zip(&method.params)
+			.all(|(a, b)| same(a.type_expr, b.type_expr))
+		&& same(declared.return_type, method.return_type)
+}
+
+/// How many calls of `const fn`s can be nested while evaluating a constant
+const CONST_CALL_LIMIT: usize = 128;
+
+/// A declaration along with the arena its nodes are in, which is the prelude's
+/// or another module's for the names they declare
+struct Decl<'ast, T: ?Sized> {
+	arena: &'ast Arena,
+	node: &'ast T,
+}
+
+impl<T: ?Sized> Clone for Decl<'_, T> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<T: ?Sized> Copy for Decl<'_, T> {}
+
+impl<T: ?Sized> Deref for Decl<'_, T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		self.node
+	}
+}
+
+/// What is statically known about a name in scope
+#[derive(Clone, Copy)]
+enum Binding<'ast> {
+	/// A typedef, with its fields
+	Typedef(Decl<'ast, [TypedefField]>),
+	/// An enum, with its variants
+	Enum(Decl<'ast, [EnumVariant]>),
+	/// The constructor of an enum variant
+	Variant(Decl<'ast, EnumVariant>),
+	/// A function declared with `fn`
+	Function(Decl<'ast, FnDecl>),
+	/// A constant, with the index of its value among those evaluated, which is
+	/// missing if it couldn't be evaluated
+	Const(usize),
+	/// An interface, with the methods it declares
+	Interface(Decl<'ast, InterfaceDecl>),
+	/// A module imported with `import "path" as m`, whose exports are its fields
+	Module {
+		path: &'ast str,
+		file: &'ast SourceFile,
+	},
+	/// A value, with the name of its record type if it is known
+	Value {
+		record_type: Option<Symbol>,
+		mutable: bool,
+	},
+}
+
+struct Checker<'ast> {
+	/// The arena of the statements being checked, which is the prelude's before
+	/// the file's
+	arena: &'ast Arena,
+	scopes: Vec<HashMap<Symbol, Binding<'ast>>>,
+	/// The program and module being checked, if imports can be resolved
+	program: Option<(&'ast Program, &'ast Module)>,
+	/// The impls of every module, as a method can be called on a value whose
+	/// type is declared somewhere else
+	impls: Vec<Decl<'ast, ImplDecl>>,
+	/// How many function bodies the expression being checked is nested in
+	fn_depth: usize,
+	/// The values of the constants declared so far
+	constants: Vec<Option<Constant>>,
+	/// The invocations the spans of the file being checked were expanded through
+	expansions: &'ast Expansions,
+	diagnostics: Vec<Diagnostic>,
+}
+
+impl<'ast> Checker<'ast> {
+	fn new(file: &'ast SourceFile, program: Option<(&'ast Program, &'ast Module)>) -> Self {
+		Self {
+			arena: &file.arena,
+			scopes: vec![HashMap::new()],
+			program,
+			impls: Vec::new(),
+			fn_depth: 0,
+			constants: Vec::new(),
+			expansions: &file.expansions,
+			diagnostics: Vec::new(),
+		}
+	}
+
+	fn check_file(&mut self, file: &'ast SourceFile) {
+		let files = match self.program {
+			Some((program, _)) => program.modules.iter().map(|module| &module.file).collect(),
+			None => vec![file],
+		};
+
+		for file in files {
+			for &stmt in &file.stmts {
+				if let StmtKind::ImplDecl(decl) = &file.arena[stmt].kind {
+					self.impls.push(Decl {
+						arena: &file.arena,
+						node: decl,
+					});
+				}
+			}
+		}
+
+		let prelude = prelude();
+		self.arena = &prelude.arena;
+		self.check_stmts(&prelude.stmts);
+
+		// The file has a scope of its own so that it can shadow the prelude
+		self.arena = &file.arena;
+		self.scopes.push(HashMap::new());
+		self.check_stmts(&file.stmts);
+	}
+
+	/// Reports a diagnostic, unless the same one was already reported, as
+	/// evaluating a constant can run into what checking a `const fn` found
+	fn report(&mut self, kind: DiagnosticKind, span: Span) {
+		let diagnostic = Diagnostic {
+			kind,
+			span,
+			backtrace: self.expansions.backtrace(span),
+		};
+
+		if !self.diagnostics.contains(&diagnostic) {
+			self.diagnostics.push(diagnostic);
+		}
+	}
+
+	fn bind(&mut self, name: Symbol, binding: Binding<'ast>) {
+		self.scopes
+			.last_mut()
+			.expect("there is always a file scope")
+			.insert(name, binding);
+	}
+
+	fn lookup(&self, name: Symbol) -> Option<Binding<'ast>> {
+		self.lookup_scope(name).map(|(_, binding)| binding)
+	}
+
+	/// Like `lookup`, along with the index of the scope the name is bound in
+	fn lookup_scope(&self, name: Symbol) -> Option<(usize, Binding<'ast>)> {
+		self.scopes
+			.iter()
+			.enumerate()
+			.rev()
+			.find_map(|(i, scope)| Some((i, *scope.get(&name)?)))
+	}
+
+	fn typedef_fields(&self, name: Symbol) -> Option<Decl<'ast, [TypedefField]>> {
+		match self.lookup(name) {
+			Some(Binding::Typedef(fields)) => Some(fields),
+			_ => None,
+		}
+	}
+
+	/// Whether `name` is a type values can be known to have, which is a typedef
+	/// or an interface
+	fn is_record_type(&self, name: Symbol) -> bool {
+		matches!(
+			self.lookup(name),
+			Some(Binding::Typedef(_) | Binding::Interface(_))
+		)
+	}
+
+	/// The name of the record type `expr` evaluates to, if it can be known
+	/// without running the program
+	fn record_type(&self, expr: ExprId) -> Option<Symbol> {
+		match &self.arena[expr].kind {
+			ExprKind::Record(record) => match (record.type_name, record.base) {
+				(Some(type_name), _) => Some(type_name),
+				(None, Some(base)) => self.record_type(base),
+				(None, None) => None,
+			},
+			ExprKind::Reference(name) => match self.lookup(*name) {
+				Some(Binding::Value { record_type, .. }) => record_type,
+				_ => None,
+			},
+			ExprKind::Group(inner) => self.record_type(*inner),
+			ExprKind::Binary(op, left, _) if !op.is_comparison() => {
+				self.overload_type(*left, op.overload()?)
+			}
+			ExprKind::Index(target, _) => self.overload_type(*target, Overload::INDEX),
+			ExprKind::Field(target, field) => {
+				let fields = self.typedef_fields(self.record_type(*target)?)?;
+				let type_expr = fields.iter().find(|f| &f.name == field)?.type_expr?;
+				let type_name = fields.arena[type_expr].name()?;
+				self.is_record_type(type_name).then_some(type_name)
+			}
+			_ => None,
+		}
+	}
+
+	/// The record type returned by the method overloading an operator applied to
+	/// `operand`, if it declares one
+	fn overload_type(&self, operand: ExprId, overload: Overload) -> Option<Symbol> {
+		let type_name = self.record_type(operand)?;
+		let decl = self.impl_of(type_name, overload.interface)?;
+		let method = decl
+			.methods
+			.iter()
+			.find(|method| method.name == overload.method)?;
+		let return_type = decl.arena[method.return_type?].name()?;
+
+		self.is_record_type(return_type).then_some(return_type)
+	}
+
+	fn impl_of(&self, type_name: Symbol, interface: Symbol) -> Option<Decl<'ast, ImplDecl>> {
+		self.impls
+			.iter()
+			.find(|decl| decl.type_name == type_name && decl.interface == interface)
+			.copied()
+	}
+
+	/// Checks the statements of a scope, in which functions can be called
+	/// before they are declared
+	fn check_stmts(&mut self, stmts: &'ast [StmtId]) {
+		let arena = self.arena;
+
+		for &stmt in stmts {
+			let declared = match arena[stmt].kind {
+				StmtKind::Export(exported) => exported,
+				_ => stmt,
+			};
+
+			match &arena[declared].kind {
+				StmtKind::FnDecl(decl) => {
+					self.bind(decl.name, Binding::Function(Decl { arena, node: decl }));
+				}
+				StmtKind::InterfaceDecl(decl) => {
+					self.bind(decl.name, Binding::Interface(Decl { arena, node: decl }));
+				}
+				_ => {}
+			}
+		}
+
+		for &stmt in stmts {
+			self.check_stmt(stmt);
+		}
+	}
+
+	fn check_stmt(&mut self, stmt: StmtId) {
+		let arena = self.arena;
+		let span = arena[stmt].span;
+
+		match &arena[stmt].kind {
+			StmtKind::LetDecl(decl) => {
+				self.check_expr(decl.value);
+
+				// A bare name always declares something new here, even if it is also the
+				// name of a variant
+				let PatternKind::Binding(name) = arena[decl.pattern].kind else {
+					if self.is_refutable(decl.pattern) {
+						let span = arena[decl.pattern].span;
+						self.report(DiagnosticKind::RefutablePattern("let"), span);
+					}
+					self.check_pattern(decl.pattern, decl.mutable);
+					return;
+				};
+
+				let value = &arena[decl.value];
+				let binding = match &value.kind {
+					ExprKind::Typedef(_, fields) => Binding::Typedef(Decl {
+						arena,
+						node: fields,
+					}),
+					ExprKind::Enum(_, variants) => {
+						self.bind_variants(name, variants, value.span);
+						Binding::Enum(Decl {
+							arena,
+							node: variants,
+						})
+					}
+					_ => Binding::Value {
+						record_type: self.record_type(decl.value),
+						mutable: decl.mutable,
+					},
+				};
+				self.bind(name, binding);
+			}
+			StmtKind::FnDecl(decl) => {
+				self.check_defaults(&decl.params);
+				self.bind(decl.name, Binding::Function(Decl { arena, node: decl }));
+				self.check_fn_body(&decl.type_params, &decl.params, &decl.body, None);
+
+				if decl.constant {
+					self.check_const_fn(decl, span);
+				}
+			}
+			StmtKind::ConstDecl(decl) => {
+				self.check_expr(decl.value);
+
+				let value = if self.check_constant(decl.value, &mut Vec::new()) {
+					self.evaluate(decl.value, &mut Vec::new(), 0)
+				} else {
+					None
+				};
+
+				self.constants.push(value);
+				self.bind(decl.name, Binding::Const(self.constants.len() - 1));
+			}
+			StmtKind::InterfaceDecl(decl) => self.check_interface(decl, span),
+			StmtKind::ImplDecl(decl) => self.check_impl(decl, span),
+			StmtKind::Import(import) => self.check_import(import, span),
+			StmtKind::Export(exported) => self.check_stmt(*exported),
+			StmtKind::Expr(expr) => self.check_expr(*expr),
+		}
+	}
+
+	/// Checks the body of a function or method with its parameters in scope,
+	/// where the `receiver` of a method is the type of its `self`
+	fn check_fn_body(
+		&mut self,
+		type_params: &[Symbol],
+		params: &'ast [TypedefField],
+		body: &'ast Block,
+		receiver: Option<Symbol>,
+	) {
+		self.scopes.push(HashMap::new());
+		self.fn_depth += 1;
+
+		for (i, param) in params.iter().enumerate() {
+			let record_type = match receiver {
+				Some(receiver) if i == 0 => Some(receiver),
+				_ => param
+					.type_expr
+					.and_then(|type_expr| self.arena[type_expr].name())
+					.filter(|type_name| !type_params.contains(type_name))
+					.filter(|type_name| self.is_record_type(*type_name)),
+			};
+
+			self.bind(
+				param.name,
+				Binding::Value {
+					record_type,
+					mutable: false,
+				},
+			);
+		}
+
+		self.check_block(body);
+		self.fn_depth -= 1;
+		self.scopes.pop();
+	}
+
+	fn check_interface(&mut self, decl: &'ast InterfaceDecl, span: Span) {
+		for method in &decl.methods {
+			self.check_receiver(method.name, &method.params, span);
+			self.check_defaults(&method.params);
+
+			if let Some(default) = &method.default {
+				self.check_fn_body(
+					&method.type_params,
+					&method.params,
+					default,
+					Some(decl.name),
+				);
+			}
+		}
+	}
+
+	/// Checks that an impl provides exactly the methods its interface declares,
+	/// apart from those with defaults which it may leave out
+	fn check_impl(&mut self, decl: &'ast ImplDecl, span: Span) {
+		let interface = match self.lookup(decl.interface) {
+			Some(Binding::Interface(interface)) => Some(interface),
+			_ => {
+				self.report(
+					DiagnosticKind::UnknownInterface(decl.interface.to_string()),
+					span,
+				);
+				None
+			}
+		};
+
+		if self.typedef_fields(decl.type_name).is_none() {
+			self.report(
+				DiagnosticKind::UnknownType(decl.type_name.to_string()),
+				span,
+			);
+		}
+
+		for method in &decl.methods {
+			self.check_receiver(method.name, &method.params, span);
+
+			if let Some(interface) = interface {
+				let method = Decl {
+					arena: self.arena,
+					node: method,
+				};
+				let declared = interface
+					.node
+					.methods
+					.iter()
+					.find(|m| m.name == method.name)
+					.map(|node| Decl {
+						arena: interface.arena,
+						node,
+					});
+
+				let kind = match declared {
+					None => Some(DiagnosticKind::NotInInterface {
+						interface: interface.name.to_string(),
+						method: method.name.to_string(),
+					}),
+					Some(declared) if !signature_matches(declared, method) => {
+						Some(DiagnosticKind::MismatchedMethod {
+							interface: interface.name.to_string(),
+							method: method.name.to_string(),
+						})
+					}
+					Some(_) => None,
+				};
+
+				if let Some(kind) = kind {
+					self.report(kind, span);
+				}
+			}
+
+			self.check_defaults(&method.params);
+			self.check_fn_body(
+				&method.type_params,
+				&method.params,
+				&method.body,
+				Some(decl.type_name),
+			);
+		}
+
+		let Some(interface) = interface else {
+			return;
+		};
+
+		for declared in &interface.methods {
+			let implemented = decl.methods.iter().any(|m| m.name == declared.name);
+
+			if declared.default.is_none() && !implemented {
+				self.report(
+					DiagnosticKind::MissingMethod {
+						interface: interface.name.to_string(),
+						type_name: decl.type_name.to_string(),
+						method: declared.name.to_string(),
+					},
+					span,
+				);
+			}
+		}
+	}
+
+	fn check_receiver(&mut self, method: Symbol, params: &[TypedefField], span: Span) {
+		if params
+			.first()
+			.is_none_or(|param| param.name != Symbol::SELF)
+		{
+			self.report(DiagnosticKind::MissingReceiver(method.to_string()), span);
+		}
+	}
+
+	/// Brings the names an import refers to into scope, which are only known
+	/// when checking a module of a program
+	fn check_import(&mut self, import: &'ast Import, span: Span) {
+		let imported = self
+			.program
+			.and_then(|(program, module)| program.module(module.imports.get(&import.path)?));
+
+		let unknown = Binding::Value {
+			record_type: None,
+			mutable: false,
+		};
+
+		match &import.names {
+			ImportNames::Module(name) => {
+				let binding = imported.map_or(unknown, |imported| Binding::Module {
+					path: &import.path,
+					file: &imported.file,
+				});
+				self.bind(*name, binding);
+			}
+			ImportNames::Only(names) => {
+				let exports = imported.map(|imported| exports(&imported.file));
+
+				for name in names {
+					let binding = match &exports {
+						Some(exports) => match exports.get(name) {
+							Some(binding) => *binding,
+							None => {
+								self.report(
+									DiagnosticKind::UnknownExport {
+										module: import.path.clone(),
+										name: name.to_string(),
+									},
+									span,
+								);
+								unknown
+							}
+						},
+						None => unknown,
+					};
+
+					self.bind(*name, binding);
+				}
+			}
+		}
+	}
+
+	fn check_block(&mut self, block: &'ast Block) {
+		self.scopes.push(HashMap::new());
+
+		self.check_stmts(&block.stmts);
+
+		if let Some(expr) = block.expr {
+			self.check_expr(expr);
+		}
+
+		self.scopes.pop();
+	}
+
+	fn check_expr(&mut self, expr: ExprId) {
+		let arena = self.arena;
+		let span = arena[expr].span;
+
+		match &arena[expr].kind {
+			&ExprKind::Binary(op, left, right) => {
+				self.check_expr(left);
+				self.check_expr(right);
+				self.check_operator(op, left, span);
+			}
+			ExprKind::Unary(_, operand) | ExprKind::Group(operand) => self.check_expr(*operand),
+			&ExprKind::FnInvoke(callee, ref args) => match self.method_call(callee) {
+				Some((target, type_name, method)) => {
+					self.check_expr(target);
+					args.iter().for_each(|&arg| self.check_expr(arg));
+					self.check_method_arity(type_name, method, args.len(), arena[callee].span);
+				}
+				None => {
+					self.check_expr(callee);
+					args.iter().for_each(|&arg| self.check_expr(arg));
+					self.check_arity(callee, args.len());
+				}
+			},
+			ExprKind::Typedef(_, fields) => self.check_defaults(fields),
+			ExprKind::Enum(_, variants) => {
+				for variant in variants {
+					self.check_defaults(&variant.fields);
+				}
+			}
+			ExprKind::Block(block) => self.check_block(block),
+			ExprKind::If(condition, then_branch, else_branch) => {
+				self.check_expr(*condition);
+				self.check_block(then_branch);
+				if let Some(else_branch) = *else_branch {
+					self.check_expr(else_branch);
+				}
+			}
+			ExprKind::While(condition, body) => {
+				self.check_expr(*condition);
+				self.check_block(body);
+			}
+			&ExprKind::For(pattern, iterable, ref body) => {
+				self.check_expr(iterable);
+				self.check_iterable(iterable);
+
+				self.scopes.push(HashMap::new());
+				if self.is_refutable(pattern) {
+					self.report(DiagnosticKind::RefutablePattern("for"), arena[pattern].span);
+				}
+				self.check_pattern(pattern, false);
+				self.check_block(body);
+				self.scopes.pop();
+			}
+			ExprKind::Try(operand) => {
+				self.check_expr(*operand);
+
+				if self.fn_depth == 0 {
+					self.report(DiagnosticKind::TryOutsideFunction, span);
+				}
+			}
+			ExprKind::Range(start, end, _) => {
+				self.check_expr(*start);
+				self.check_expr(*end);
+			}
+			ExprKind::Match(value, arms) => {
+				self.check_expr(*value);
+
+				for arm in arms {
+					self.scopes.push(HashMap::new());
+					self.check_pattern(arm.pattern, false);
+					if let Some(guard) = arm.guard {
+						self.check_expr(guard);
+					}
+					self.check_expr(arm.body);
+					self.scopes.pop();
+				}
+			}
+			&ExprKind::Assign(target, value) => {
+				self.check_expr(target);
+				self.check_expr(value);
+				self.check_assignable(target);
+			}
+			&ExprKind::CompoundAssign(op, target, value) => {
+				self.check_expr(target);
+				self.check_expr(value);
+				self.check_assignable(target);
+				self.check_operator(op, target, span);
+			}
+			ExprKind::Lambda(params, body) => {
+				self.scopes.push(HashMap::new());
+				self.fn_depth += 1;
+				for param in params {
+					self.bind(
+						*param,
+						Binding::Value {
+							record_type: None,
+							mutable: false,
+						},
+					);
+				}
+				self.check_block(body);
+				self.fn_depth -= 1;
+				self.scopes.pop();
+			}
+			ExprKind::NumLit(_)
+			| ExprKind::StrLit(_)
+			| ExprKind::BoolLit(_)
+			| ExprKind::UnitLit
+			| ExprKind::NilLit
+			| ExprKind::Reference(_)
+			| ExprKind::Placeholder => {}
+			ExprKind::List(items) | ExprKind::Tuple(items) => {
+				items.iter().for_each(|&item| self.check_expr(item));
+			}
+			ExprKind::Map(entries) => {
+				for &(key, value) in entries {
+					self.check_expr(key);
+					self.check_expr(value);
+				}
+			}
+			&ExprKind::Index(target, index) => {
+				self.check_expr(target);
+				self.check_expr(index);
+				self.check_overload("[]", Overload::INDEX, target, span);
+			}
+			ExprKind::Slice(target, start, end) => {
+				self.check_expr(*target);
+				start
+					.iter()
+					.chain(end)
+					.for_each(|&bound| self.check_expr(bound));
+			}
+			ExprKind::Record(record) => self.check_record(record, span),
+			&ExprKind::Field(target, field) => {
+				self.check_expr(target);
+
+				if let Some((_, Binding::Module { path, file })) = self.resolve(target) {
+					if !exports(file).contains_key(&field) {
+						self.report(
+							DiagnosticKind::UnknownExport {
+								module: path.to_string(),
+								name: field.to_string(),
+							},
+							span,
+						);
+					}
+					return;
+				}
+
+				if let Some((enum_name, variants)) = self.enum_of(target) {
+					if !variants.iter().any(|variant| variant.name == field) {
+						self.report(
+							DiagnosticKind::UnknownVariant {
+								enum_name: enum_name.to_string(),
+								variant: field.to_string(),
+							},
+							span,
+						);
+					}
+					return;
+				}
+
+				// The fields of a value only known to implement an interface aren't
+				let Some(type_name) = self.record_type(target) else {
+					return;
+				};
+				let Some(fields) = self.typedef_fields(type_name) else {
+					return;
+				};
+
+				if !fields.iter().any(|f| f.name == field) {
+					self.report(
+						DiagnosticKind::UnknownField {
+							type_name: type_name.to_string(),
+							field: field.to_string(),
+						},
+						span,
+					);
+				}
+			}
+		}
+	}
+
+	fn check_operator(&mut self, op: BinaryOp, left: ExprId, span: Span) {
+		// Records without an impl of `Equal` are compared field by field
+		if matches!(op, BinaryOp::Equal | BinaryOp::NotEqual) {
+			return;
+		}
+
+		if let Some(overload) = op.overload() {
+			self.check_overload(op.symbol(), overload, left, span);
+		}
+	}
+
+	/// Reports an operator applied to a record whose type is known not to
+	/// implement the interface that gives the operator a meaning for it
+	fn check_overload(&mut self, operator: &str, overload: Overload, operand: ExprId, span: Span) {
+		let Some(type_name) = self.record_type(operand) else {
+			return;
+		};
+
+		// A value only known to implement some interface may implement any other
+		if self.typedef_fields(type_name).is_none() {
+			return;
+		}
+
+		if self.impl_of(type_name, overload.interface).is_none() {
+			self.report(
+				DiagnosticKind::NoOverload {
+					operator: operator.to_string(),
+					type_name: type_name.to_string(),
+					interface: overload.interface.as_str(),
+				},
+				span,
+			);
+		}
+	}
+
+	/// Reports iterating over a record whose typedef has no `next` field, which
+	/// is how records take part in `for` loops
+	fn check_iterable(&mut self, iterable: ExprId) {
+		let Some(type_name) = self.record_type(iterable) else {
+			return;
+		};
+
+		let Some(fields) = self.typedef_fields(type_name) else {
+			return;
+		};
+
+		if !fields.iter().any(|field| field.name == Symbol::NEXT) {
+			self.report(
+				DiagnosticKind::NotIterable(type_name.to_string()),
+				self.arena[iterable].span,
+			);
+		}
+	}
+
+	/// Reports assigning to a name that wasn't declared with `let mut`, which
+	/// includes assigning to its fields and elements
+	fn check_assignable(&mut self, target: ExprId) {
+		let mut root = target;
+		while let ExprKind::Field(inner, _) | ExprKind::Index(inner, _) = self.arena[root].kind {
+			root = inner;
+		}
+
+		let ExprKind::Reference(name) = self.arena[root].kind else {
+			return;
+		};
+
+		match self.lookup(name) {
+			None | Some(Binding::Value { mutable: true, .. }) => {}
+			Some(_) => self.report(
+				DiagnosticKind::ImmutableAssignment(name.to_string()),
+				self.arena[target].span,
+			),
+		}
+	}
+
+	/// Reports what the body and defaults of a `const fn` do that can't be
+	/// evaluated while compiling
+	fn check_const_fn(&mut self, decl: &'ast FnDecl, span: Span) {
+		let mut locals = Vec::new();
+
+		for param in &decl.params {
+			if let Some(default) = param.default_value {
+				self.check_constant(default, &mut locals);
+			}
+			locals.push(param.name);
+		}
+
+		self.check_const_block(&decl.body, &mut locals, span);
+	}
+
+	/// Reports the parts of an expression that can't be evaluated while
+	/// compiling, where `locals` are the names bound inside a `const fn`,
+	/// returning whether there were none
+	fn check_constant(&mut self, expr: ExprId, locals: &mut Vec<Symbol>) -> bool {
+		let arena = self.arena;
+		let span = arena[expr].span;
+
+		let constant = match &arena[expr].kind {
+			ExprKind::NumLit(_) | ExprKind::StrLit(_) | ExprKind::BoolLit(_) | ExprKind::NilLit => {
+				true
+			}
+			ExprKind::Group(operand) | ExprKind::Unary(_, operand) => {
+				return self.check_constant(*operand, locals);
+			}
+			ExprKind::Binary(_, left, right) => {
+				let left = self.check_constant(*left, locals);
+				return self.check_constant(*right, locals) && left;
+			}
+			ExprKind::Reference(name) => {
+				locals.contains(name) || matches!(self.lookup(*name), Some(Binding::Const(_)))
+			}
+			ExprKind::FnInvoke(callee, args) => {
+				let callee_constant = match &arena[*callee].kind {
+					ExprKind::Reference(name) if !locals.contains(name) => {
+						matches!(self.lookup(*name), Some(Binding::Function(decl)) if decl.constant)
+					}
+					_ => false,
+				};
+
+				if !callee_constant {
+					self.report(DiagnosticKind::NotConstant, arena[*callee].span);
+				}
+
+				return args.iter().fold(callee_constant, |constant, &arg| {
+					self.check_constant(arg, locals) && constant
+				});
+			}
+			ExprKind::If(condition, then_branch, Some(else_branch)) => {
+				let condition = self.check_constant(*condition, locals);
+				let then_branch = self.check_const_block(then_branch, locals, span);
+				return self.check_constant(*else_branch, locals) && condition && then_branch;
+			}
+			ExprKind::Block(block) => return self.check_const_block(block, locals, span),
+			_ => false,
+		};
+
+		if !constant {
+			self.report(DiagnosticKind::NotConstant, span);
+		}
+
+		constant
+	}
+
+	/// Like `check_constant` for a block at `span`, which may only bind
+	/// constants with `let` before the expression it evaluates to
+	fn check_const_block(
+		&mut self,
+		block: &'ast Block,
+		locals: &mut Vec<Symbol>,
+		span: Span,
+	) -> bool {
+		let arena = self.arena;
+		let scope = locals.len();
+		let mut constant = true;
+
+		for &stmt in &block.stmts {
+			match &arena[stmt].kind {
+				StmtKind::LetDecl(decl) => {
+					if decl.mutable {
+						self.report(DiagnosticKind::NotConstant, arena[stmt].span);
+						constant = false;
+					}
+
+					constant = self.check_constant(decl.value, locals) && constant;
+
+					match arena[decl.pattern].kind {
+						PatternKind::Binding(name) => locals.push(name),
+						_ => {
+							self.report(DiagnosticKind::NotConstant, arena[decl.pattern].span);
+							constant = false;
+						}
+					}
+				}
+				_ => {
+					self.report(DiagnosticKind::NotConstant, arena[stmt].span);
+					constant = false;
+				}
+			}
+		}
+
+		// A block without a final expression evaluates to `()`, which isn't a constant
+		constant = match block.expr {
+			Some(expr) => self.check_constant(expr, locals) && constant,
+			None => {
+				self.report(DiagnosticKind::NotConstant, span);
+				false
+			}
+		};
+
+		locals.truncate(scope);
+		constant
+	}
+
+	/// Evaluates an expression `check_constant` accepted, `depth` calls of
+	/// `const fn`s deep where `locals` are the values of the names bound inside
+	/// the innermost, reporting anything that goes wrong
+	fn evaluate(
+		&mut self,
+		expr: ExprId,
+		locals: &mut Vec<(Symbol, Constant)>,
+		depth: usize,
+	) -> Option<Constant> {
+		let arena = self.arena;
+		let span = arena[expr].span;
+
+		let (result, operator, operands) = match &arena[expr].kind {
+			ExprKind::NumLit(n) => return Some(Constant::Num(*n)),
+			ExprKind::StrLit(s) => return Some(Constant::Str(s.clone())),
+			ExprKind::BoolLit(b) => return Some(Constant::Bool(*b)),
+			ExprKind::NilLit => return Some(Constant::Nil),
+			ExprKind::Group(inner) => return self.evaluate(*inner, locals, depth),
+			ExprKind::Reference(name) => {
+				if let Some((_, value)) = locals.iter().rev().find(|(local, _)| local == name) {
+					return Some(value.clone());
+				}
+
+				return match self.lookup(*name) {
+					Some(Binding::Const(index)) => self.constants[index].clone(),
+					_ => None,
+				};
+			}
+			ExprKind::Unary(op, operand) => {
+				let operand = self.evaluate(*operand, locals, depth)?;
+				(Constant::unary(op, &operand), op.symbol(), vec![operand])
+			}
+			ExprKind::Binary(op, left, right) => {
+				let left = self.evaluate(*left, locals, depth)?;
+
+				// `&&` and `||` only evaluate their right operand when it decides the result
+				match (op, &left) {
+					(BinaryOp::And, Constant::Bool(false))
+					| (BinaryOp::Or, Constant::Bool(true)) => {
+						return Some(left);
+					}
+					_ => {}
+				}
+
+				let right = self.evaluate(*right, locals, depth)?;
+				(
+					Constant::binary(*op, &left, &right),
+					op.symbol(),
+					vec![left, right],
+				)
+			}
+			ExprKind::FnInvoke(callee, args) => {
+				let ExprKind::Reference(name) = arena[*callee].kind else {
+					return None;
+				};
+				let Some((scope, Binding::Function(decl))) = self.lookup_scope(name) else {
+					return None;
+				};
+
+				if !decl.constant {
+					self.report(DiagnosticKind::NotConstant, arena[*callee].span);
+					return None;
+				}
+
+				let args = args
+					.iter()
+					.map(|&arg| self.evaluate(arg, locals, depth))
+					.collect::<Option<Vec<_>>>()?;
+
+				if depth >= CONST_CALL_LIMIT {
+					self.report(DiagnosticKind::ConstRecursion(CONST_CALL_LIMIT), span);
+					return None;
+				}
+
+				// The function's defaults and body are in the arena it was declared in,
+				// and the names they don't bind refer to what is in scope there. A
+				// function imported from another module only sees the prelude and that
+				// module's exports.
+				let (outer, module_scope) = match self.declaring_module(decl) {
+					Some(file) => (1, Some(exports(file))),
+					None => (scope + 1, None),
+				};
+				let inner = self.scopes.split_off(outer);
+				self.scopes.extend(module_scope);
+				let caller = std::mem::replace(&mut self.arena, decl.arena);
+
+				let value = self.evaluate_call(decl.node, &args, depth + 1);
+
+				self.arena = caller;
+				self.scopes.truncate(outer);
+				self.scopes.extend(inner);
+				return value;
+			}
+			ExprKind::If(condition, then_branch, Some(else_branch)) => {
+				return match self.evaluate(*condition, locals, depth)? {
+					Constant::Bool(true) => self.evaluate_block(then_branch, locals, depth),
+					Constant::Bool(false) => self.evaluate(*else_branch, locals, depth),
+					other => {
+						self.report(
+							DiagnosticKind::ConstCondition(other.type_name()),
+							arena[*condition].span,
+						);
+						None
+					}
+				};
+			}
+			ExprKind::Block(block) => return self.evaluate_block(block, locals, depth),
+			_ => return None,
+		};
+
+		let error = match result {
+			Ok(value) => return Some(value),
+			Err(error) => error,
+		};
+
+		let kind = match error {
+			ConstError::Overflow => DiagnosticKind::ConstOverflow(operator),
+			ConstError::NotANumber => DiagnosticKind::ConstNotANumber(operator),
+			ConstError::DivisionByZero => DiagnosticKind::DivisionByZero(operator),
+			ConstError::NotInteger => DiagnosticKind::ConstNotInteger(operator),
+			ConstError::Operands => DiagnosticKind::ConstOperands {
+				operator,
+				types: operands.iter().map(Constant::type_name).collect(),
+			},
+		};
+
+		self.report(kind, span);
+		None
+	}
+
+	/// Evaluates the body of a `const fn` called with `args`, `depth` calls deep,
+	/// evaluating the defaults of the parameters they leave off
+	fn evaluate_call(
+		&mut self,
+		decl: &'ast FnDecl,
+		args: &[Constant],
+		depth: usize,
+	) -> Option<Constant> {
+		let mut params = Vec::new();
+
+		for (i, param) in decl.params.iter().enumerate() {
+			let value = match args.get(i) {
+				Some(arg) => arg.clone(),
+				None => self.evaluate(param.default_value?, &mut params, depth)?,
+			};
+			params.push((param.name, value));
+		}
+
+		self.evaluate_block(&decl.body, &mut params, depth)
+	}
+
+	fn evaluate_block(
+		&mut self,
+		block: &'ast Block,
+		locals: &mut Vec<(Symbol, Constant)>,
+		depth: usize,
+	) -> Option<Constant> {
+		let arena = self.arena;
+		let scope = locals.len();
+
+		// The names the block binds go out of scope however it is left
+		let value = (|| {
+			for &stmt in &block.stmts {
+				let StmtKind::LetDecl(decl) = &arena[stmt].kind else {
+					self.report(DiagnosticKind::NotConstant, arena[stmt].span);
+					return None;
+				};
+				let PatternKind::Binding(name) = arena[decl.pattern].kind else {
+					self.report(DiagnosticKind::NotConstant, arena[decl.pattern].span);
+					return None;
+				};
+				let value = self.evaluate(decl.value, locals, depth)?;
+				locals.push((name, value));
+			}
+
+			self.evaluate(block.expr?, locals, depth)
+		})();
+
+		locals.truncate(scope);
+		value
+	}
+
+	/// The file of the module other than the one being checked that a function
+	/// was declared in, if any
+	fn declaring_module(&self, decl: Decl<'ast, FnDecl>) -> Option<&'ast SourceFile> {
+		let (program, current) = self.program?;
+
+		program
+			.modules
+			.iter()
+			.map(|module| &module.file)
+			.find(|file| std::ptr::eq(&file.arena, decl.arena))
+			.filter(|file| !std::ptr::eq(*file, &current.file))
+	}
+
+	/// Brings the constructors of an enum's variants into scope
+	fn bind_variants(&mut self, enum_name: Symbol, variants: &'ast [EnumVariant], span: Span) {
+		let mut seen = HashSet::new();
+
+		for variant in variants {
+			if !seen.insert(variant.name) {
+				self.report(
+					DiagnosticKind::DuplicateVariant {
+						enum_name: enum_name.to_string(),
+						variant: variant.name.to_string(),
+					},
+					span,
+				);
+			}
+
+			let variant = Decl {
+				arena: self.arena,
+				node: variant,
+			};
+			self.bind(variant.name, Binding::Variant(variant));
+		}
+	}
+
+	fn check_defaults(&mut self, fields: &'ast [TypedefField]) {
+		fields
+			.iter()
+			.filter_map(|field| field.default_value)
+			.for_each(|value| self.check_expr(value));
+	}
+
+	/// The name and variants of the enum `expr` refers to, if it does
+	fn enum_of(&self, expr: ExprId) -> Option<(Symbol, Decl<'ast, [EnumVariant]>)> {
+		match self.resolve(expr)? {
+			(name, Binding::Enum(variants)) => Some((name, variants)),
+			_ => None,
+		}
+	}
+
+	/// What `expr` refers to along with its name, if it is a name in scope or an
+	/// export of an imported module like `m.x`
+	fn resolve(&self, expr: ExprId) -> Option<(Symbol, Binding<'ast>)> {
+		match &self.arena[expr].kind {
+			ExprKind::Reference(name) => Some((*name, self.lookup(*name)?)),
+			ExprKind::Field(target, name) => match self.resolve(*target)? {
+				(_, Binding::Module { file, .. }) => Some((*name, *exports(file).get(name)?)),
+				_ => None,
+			},
+			_ => None,
+		}
+	}
+
+	/// The name and parameters of the function or variant constructor `expr`
+	/// refers to, where a variant may be qualified by its enum like `Shape.Circle`
+	fn callee(&self, expr: ExprId) -> Option<(Symbol, &'ast [TypedefField])> {
+		match self.resolve(expr) {
+			Some((_, Binding::Variant(variant))) => {
+				return Some((variant.name, &variant.node.fields));
+			}
+			Some((_, Binding::Function(decl))) => return Some((decl.name, &decl.node.params)),
+			_ => {}
+		}
+
+		let ExprKind::Field(target, name) = self.arena[expr].kind else {
+			return None;
+		};
+
+		let (_, variants) = self.enum_of(target)?;
+		let variant = variants.node.iter().find(|variant| variant.name == name)?;
+		Some((variant.name, &variant.fields))
+	}
+
+	/// The target, its type and the method called by `target.method(...)`, if
+	/// that is a method call, which it isn't if the record has a field of the
+	/// same name
+	fn method_call(&self, callee: ExprId) -> Option<(ExprId, Symbol, Symbol)> {
+		let ExprKind::Field(target, name) = self.arena[callee].kind else {
+			return None;
+		};
+
+		let type_name = self.record_type(target)?;
+		let is_field = self
+			.typedef_fields(type_name)
+			.is_some_and(|fields| fields.iter().any(|f| f.name == name));
+
+		(!is_field).then_some((target, type_name, name))
+	}
+
+	/// The parameters, starting with `self`, of the method `name` of values of
+	/// type `type_name`, which is either an interface or a type with impls
+	fn method(&self, type_name: Symbol, name: Symbol) -> Option<&'ast [TypedefField]> {
+		let declared = |interface: Symbol| match self.lookup(interface) {
+			Some(Binding::Interface(decl)) => decl
+				.node
+				.methods
+				.iter()
+				.find(|method| method.name == name)
+				.map(|method| method.params.as_slice()),
+			_ => None,
+		};
+
+		if let Some(Binding::Interface(_)) = self.lookup(type_name) {
+			return declared(type_name);
+		}
+
+		self.impls
+			.iter()
+			.filter(|decl| decl.type_name == type_name)
+			.find_map(|decl| {
+				decl.node
+					.methods
+					.iter()
+					.find(|method| method.name == name)
+					.map(|method| method.params.as_slice())
+					.or_else(|| declared(decl.interface))
+			})
+	}
+
+	fn check_method_arity(&mut self, type_name: Symbol, method: Symbol, found: usize, span: Span) {
+		let Some(params) = self.method(type_name, method) else {
+			self.report(
+				DiagnosticKind::UnknownMethod {
+					type_name: type_name.to_string(),
+					method: method.to_string(),
+				},
+				span,
+			);
+			return;
+		};
+
+		// `self` is passed by calling the method on it
+		self.check_args(method, params.get(1..).unwrap_or_default(), found, span);
+	}
+
+	fn check_arity(&mut self, callee: ExprId, found: usize) {
+		let Some((name, params)) = self.callee(callee) else {
+			return;
+		};
+
+		self.check_args(name, params, found, self.arena[callee].span);
+	}
+
+	fn check_args(&mut self, name: Symbol, params: &[TypedefField], found: usize, span: Span) {
+		// Parameters with defaults can only be left off after the last required one
+		let max = params.len();
+		let min = params
+			.iter()
+			.rposition(|param| param.default_value.is_none())
+			.map_or(0, |last_required| last_required + 1);
+
+		if found < min || found > max {
+			self.report(
+				DiagnosticKind::Arity {
+					callee: name.to_string(),
+					min,
+					max,
+					found,
+				},
+				span,
+			);
+		}
+	}
+
+	/// Checks the variants and records a pattern names, and brings the names it
+	/// binds into scope
+	fn check_pattern(&mut self, pattern: PatternId, mutable: bool) {
+		let arena = self.arena;
+		let span = arena[pattern].span;
+		let value = Binding::Value {
+			record_type: None,
+			mutable,
+		};
+
+		match &arena[pattern].kind {
+			PatternKind::Wildcard | PatternKind::Literal(_) | PatternKind::Rest(None) => {}
+			PatternKind::Binding(name) => match self.lookup(*name) {
+				Some(Binding::Variant(variant)) => self.check_pattern_arity(&variant, 0, span),
+				_ => self.bind(*name, value),
+			},
+			PatternKind::Rest(Some(name)) => self.bind(*name, value),
+			PatternKind::Tuple(items) | PatternKind::List(items) => {
+				items
+					.iter()
+					.for_each(|&item| self.check_pattern(item, mutable));
+			}
+			PatternKind::Record { type_name, fields } => {
+				self.check_record_pattern(*type_name, fields, mutable, span)
+			}
+			PatternKind::Variant {
+				enum_name,
+				variant,
+				fields,
+			} => {
+				if let Some(found) = self.pattern_variant(*enum_name, *variant, span) {
+					self.check_pattern_arity(found, fields.len(), span);
+				}
+
+				fields
+					.iter()
+					.for_each(|&field| self.check_pattern(field, mutable));
+			}
+			PatternKind::Or(alternatives) => self.check_or_pattern(alternatives, value),
+		}
+	}
+
+	/// Whether there are values of the right shape `pattern` doesn't match
+	fn is_refutable(&self, pattern: PatternId) -> bool {
+		match &self.arena[pattern].kind {
+			PatternKind::Wildcard | PatternKind::Rest(_) => false,
+			PatternKind::Binding(name) => matches!(self.lookup(*name), Some(Binding::Variant(_))),
+			PatternKind::Literal(_) | PatternKind::Variant { .. } => true,
+			PatternKind::Tuple(items) => items.iter().any(|&item| self.is_refutable(item)),
+			// Only a lone rest matches lists of any length
+			PatternKind::List(items) => !matches!(
+				items.as_slice(),
+				[item] if matches!(self.arena[*item].kind, PatternKind::Rest(_))
+			),
+			PatternKind::Record { fields, .. } => {
+				fields.iter().any(|field| self.is_refutable(field.pattern))
+			}
+			PatternKind::Or(alternatives) => alternatives
+				.iter()
+				.all(|&alternative| self.is_refutable(alternative)),
+		}
+	}
+
+	/// Looks up the variant matched by a variant pattern, reporting it if there
+	/// is no such variant
+	fn pattern_variant(
+		&mut self,
+		enum_name: Option<Symbol>,
+		name: Symbol,
+		span: Span,
+	) -> Option<&'ast EnumVariant> {
+		let Some(enum_name) = enum_name else {
+			if let Some(Binding::Variant(variant)) = self.lookup(name) {
+				return Some(variant.node);
+			}
+
+			self.report(DiagnosticKind::NotAVariant(name.to_string()), span);
+			return None;
+		};
+
+		let Some(Binding::Enum(variants)) = self.lookup(enum_name) else {
+			self.report(
+				DiagnosticKind::NotAVariant(format!("{enum_name}.{name}")),
+				span,
+			);
+			return None;
+		};
+
+		let found = variants.node.iter().find(|variant| variant.name == name);
+
+		if found.is_none() {
+			self.report(
+				DiagnosticKind::UnknownVariant {
+					enum_name: enum_name.to_string(),
+					variant: name.to_string(),
+				},
+				span,
+			);
+		}
+
+		found
+	}
+
+	/// Unlike a constructor call, a pattern has to match every field of the
+	/// variant, including those with defaults
+	fn check_pattern_arity(&mut self, variant: &EnumVariant, found: usize, span: Span) {
+		if found != variant.fields.len() {
+			self.report(
+				DiagnosticKind::PatternArity {
+					variant: variant.name.to_string(),
+					expected: variant.fields.len(),
+					found,
+				},
+				span,
+			);
+		}
+	}
+
+	fn check_record_pattern(
+		&mut self,
+		type_name: Option<Symbol>,
+		fields: &'ast [FieldPattern],
+		mutable: bool,
+		span: Span,
+	) {
+		for field in fields {
+			self.check_pattern(field.pattern, mutable);
+		}
+
+		// Without a type name any record with the fields matches
+		let Some(type_name) = type_name else {
+			return;
+		};
+
+		let Some(typedef_fields) = self.typedef_fields(type_name) else {
+			self.report(DiagnosticKind::UnknownType(type_name.to_string()), span);
+			return;
+		};
+
+		let mut seen = HashSet::new();
+
+		for field in fields {
+			let kind = if !seen.insert(field.name) {
+				DiagnosticKind::DuplicateField {
+					type_name: type_name.to_string(),
+					field: field.name.to_string(),
+				}
+			} else if !typedef_fields.iter().any(|f| f.name == field.name) {
+				DiagnosticKind::UnknownField {
+					type_name: type_name.to_string(),
+					field: field.name.to_string(),
+				}
+			} else {
+				continue;
+			};
+
+			self.report(kind, field.span);
+		}
+	}
+
+	/// Checks each alternative of an or-pattern, which all have to bind the same
+	/// names so that the arm's body can use them whichever one matched
+	fn check_or_pattern(&mut self, alternatives: &[PatternId], value: Binding<'ast>) {
+		let mut bound = Vec::new();
+
+		for &alternative in alternatives {
+			self.scopes.push(HashMap::new());
+			self.check_pattern(alternative, false);
+			let names = self.scopes.pop().expect("the scope was just pushed");
+			bound.push((self.arena[alternative].span, names));
+		}
+
+		let all_names = bound
+			.iter()
+			.flat_map(|(_, names)| names.keys().copied())
+			.collect::<HashSet<_>>();
+
+		for (span, names) in &bound {
+			let mut missing = all_names
+				.iter()
+				.filter(|name| !names.contains_key(*name))
+				.collect::<Vec<_>>();
+			missing.sort();
+
+			for name in missing {
+				self.report(DiagnosticKind::InconsistentBinding(name.to_string()), *span);
+			}
+		}
+
+		for name in all_names {
+			self.bind(name, value);
+		}
+	}
+
+	fn check_record(&mut self, record: &'ast RecordLit, span: Span) {
+		if let Some(base) = record.base {
+			self.check_expr(base);
+		}
+
+		for field in &record.fields {
+			self.check_expr(field.value);
+		}
+
+		let type_name = match &record.type_name {
+			Some(type_name) => *type_name,
+			// The type of an update is only known if its base's is
+			None => match record.base.and_then(|base| self.record_type(base)) {
+				Some(type_name) => type_name,
+				None => return,
+			},
+		};
+
+		let Some(typedef_fields) = self.typedef_fields(type_name) else {
+			self.report(DiagnosticKind::UnknownType(type_name.to_string()), span);
+			return;
+		};
+
+		let mut seen = HashSet::new();
+
+		for field in &record.fields {
+			let kind = if !seen.insert(field.name) {
+				DiagnosticKind::DuplicateField {
+					type_name: type_name.to_string(),
+					field: field.name.to_string(),
+				}
+			} else if !typedef_fields.iter().any(|f| f.name == field.name) {
+				DiagnosticKind::UnknownField {
+					type_name: type_name.to_string(),
+					field: field.name.to_string(),
+				}
+			} else {
+				continue;
+			};
+
+			self.report(kind, field.span);
+		}
+
+		// Fields left out of an update are copied from its base
+		if record.base.is_some() {
+			return;
+		}
+
+		for field in typedef_fields.iter() {
+			if field.default_value.is_none() && !seen.contains(&field.name) {
+				self.report(
+					DiagnosticKind::MissingField {
+						type_name: type_name.to_string(),
+						field: field.name.to_string(),
+					},
+					span,
+				);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{ast::Backtrace, lower::lower, parse::parse};
+
+	fn check_source(source: &str) -> Vec<Diagnostic> {
+		check(&lower(parse(&mut source.as_bytes()).unwrap()).unwrap())
+	}
+
+	#[test]
+	fn valid_records() {
+		let diagnostics = check_source(
+			"typedef Point = { x: Num, y: Num = 0 }
+			let p = Point { x: 1 };
+			let q = { ..p, y: 2 };
+			let r = q.x + Point { x: 1, y: 2 }.y;",
+		);
+
+		assert_eq!(diagnostics, vec![]);
+	}
+
+	#[test]
+	fn unknown_and_missing_fields() {
+		let diagnostics = check_source(
+			"typedef Point = { x: Num, y: Num = 0 }
+			let p = Point { y: 1, z: 2, y: 3 };",
+		);
+
+		assert_eq!(
+			diagnostics,
+			vec![
+				Diagnostic {
+					kind: DiagnosticKind::UnknownField {
+						type_name: "Point".to_string(),
+						field: "z".to_string(),
+					},
+					span: Span::new(2, 26),
+					backtrace: Backtrace::default(),
+				},
+				Diagnostic {
+					kind: DiagnosticKind::DuplicateField {
+						type_name: "Point".to_string(),
+						field: "y".to_string(),
+					},
+					span: Span::new(2, 32),
+					backtrace: Backtrace::default(),
+				},
+				Diagnostic {
+					kind: DiagnosticKind::MissingField {
+						type_name: "Point".to_string(),
+						field: "x".to_string(),
+					},
+					span: Span::new(2, 12),
+					backtrace: Backtrace::default(),
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn unknown_type() {
+		let diagnostics = check_source("let x = 1; let p = x { y: 1 };");
+
+		assert_eq!(
+			diagnostics,
+			vec![Diagnostic {
+				kind: DiagnosticKind::UnknownType("x".to_string()),
+				span: Span::new(1, 20),
+				backtrace: Backtrace::default(),
+			}]
+		);
+	}
+
+	#[test]
+	fn unknown_field_access() {
+		let diagnostics = check_source(
+			"typedef Point = { x: Num, y: Num }
+			typedef Line = { from: Point, to: Point }
+			let l = Line { from: Point { x: 1, y: 2 }, to: Point { x: 3, y: 4 } };
+			let u = { ..l, to: l.from };
+			let a = u.from.x + l.to.z;",
+		);
+
+		assert_eq!(
+			diagnostics
+				.iter()
+				.map(|diagnostic| diagnostic.to_string())
+				.collect::<Vec<_>>(),
+			vec!["`Point` has no field `z` at 5:23"]
+		);
+	}
+
+	#[test]
+	fn shadowed_typedef() {
+		let diagnostics = check_source(
+			"typedef Point = { x: Num }
+			let f = (Point) => { Point { x: 1 } };",
+		);
+
+		assert_eq!(
+			diagnostics,
+			vec![Diagnostic {
+				kind: DiagnosticKind::UnknownType("Point".to_string()),
+				span: Span::new(2, 25),
+				backtrace: Backtrace::default(),
+			}]
+		);
+	}
+
+	#[test]
+	fn enum_constructors() {
+		let diagnostics = check_source(
+			"enum Shape = Circle(r) | Rect(w, h = 1) | Empty
+			let a = Circle(1);
+			let b = Rect(1) >> f;
+			let c = 2 >> Shape.Circle;
+			let d = [Empty, Shape.Empty];",
+		);
+
+		assert_eq!(diagnostics, vec![]);
+	}
+
+	#[test]
+	fn enum_diagnostics() {
+		let diagnostics = check_source(
+			"enum Shape = Circle(r) | Rect(w, h = 1) | Empty | Circle
+			let a = Rect();
+			let b = Shape.Rect(1, 2, 3);
+			let c = (1, 2) >> Empty;
+			let d = Shape.Square(1);",
+		);
+
+		assert_eq!(
+			diagnostics
+				.iter()
+				.map(|diagnostic| diagnostic.to_string())
+				.collect::<Vec<_>>(),
+			vec![
+				"Variant `Circle` of `Shape` is declared more than once at 1:1",
+				"`Rect` takes 1 to 2 arguments but 0 were given at 2:12",
+				"`Rect` takes 1 to 2 arguments but 3 were given at 3:12",
+				"`Empty` takes 0 arguments but 1 was given at 4:22",
+				"`Shape` has no variant `Square` at 5:12",
+			]
+		);
+	}
+
+	#[test]
+	fn valid_patterns() {
+		let diagnostics = check_source(
+			"enum Shape = Circle(r) | Rect(w, h = 1) | Empty
+			typedef Point = { x: Num, y: Num }
+			let { x, y } = Point { x: 1, y: 2 };
+			let area = (s) => {
+				match s {
+					Circle(r) if r > x => r * r,
+					Shape.Rect(w, h) | Rect(h, w) => w * h + y,
+					Empty => 0,
+					_ => area(Empty),
+				}
+			};
+			let Point { x: a, y: (b, [..rest]) } = p;
+			let sum = a + b + rest;",
+		);
+
+		assert_eq!(
+			diagnostics
+				.iter()
+				.map(|diagnostic| diagnostic.to_string())
+				.collect::<Vec<_>>(),
+			Vec::<String>::new()
+		);
+	}
+
+	#[test]
+	fn pattern_diagnostics() {
+		let diagnostics = check_source(
+			"enum Shape = Circle(r) | Rect(w, h = 1) | Empty
+			typedef Point = { x: Num, y: Num }
+			match s {
+				Rect(w) => w,
+				Shape.Square => 0,
+				Square(x) => x,
+				Empty(x) => x,
+				Point { z } => z,
+				Circle(a) | Rect(a, b) => a,
+			}",
+		);
+
+		assert_eq!(
+			diagnostics
+				.iter()
+				.map(|diagnostic| diagnostic.to_string())
+				.collect::<Vec<_>>(),
+			vec![
+				"`Rect` has 2 fields but the pattern matches 1 at 4:5",
+				"`Shape` has no variant `Square` at 5:5",
+				"`Square` is not an enum variant at 6:5",
+				"`Empty` has 0 fields but the pattern matches 1 at 7:5",
+				"`Point` has no field `z` at 8:13",
+				"`b` is not bound in every alternative of the pattern at 9:5",
+			]
+		);
+	}
+
+	#[test]
+	fn refutable_let() {
+		let diagnostics = check_source(
+			"enum Shape = Circle(r) | Empty
+			let true = 1;
+			let (a, Empty) = x;
+			let [b, c] = xs;
+			let [..d] | Circle(d) = ys;",
+		);
+
+		assert_eq!(
+			diagnostics
+				.iter()
+				.map(|diagnostic| diagnostic.to_string())
+				.collect::<Vec<_>>(),
+			vec![
+				"Pattern in `let` might not match, use `match` instead at 2:8",
+				"Pattern in `let` might not match, use `match` instead at 3:8",
+				"Pattern in `let` might not match, use `match` instead at 4:8",
+			]
+		);
+	}
+
+	#[test]
+	fn match_arm_scopes() {
+		let diagnostics = check_source(
+			"typedef Point = { x: Num }
+			match p {
+				Point => Point { x: 1 },
+				_ => Point { x: 1 },
+			}",
+		);
+
+		assert_eq!(
+			diagnostics,
+			vec![Diagnostic {
+				kind: DiagnosticKind::UnknownType("Point".to_string()),
+				span: Span::new(3, 14),
+				backtrace: Backtrace::default(),
+			}]
+		);
+	}
+
+	#[test]
+	fn fn_decls() {
+		let diagnostics = check_source(
+			"typedef Point = { x: Num }
+			let a = even(1, 2);
+			fn even(n: Num, p: Point = Point { x: 0 }) { n == 0 || odd(n - 1) + p.y }
+			fn odd(n) { !even(n - 1) }
+			let b = 1 >> odd;
+			let c = { fn inner() { outer() } inner(2) };
+			fn outer() { inner() }",
+		);
+
+		assert_eq!(
+			diagnostics
+				.iter()
+				.map(|diagnostic| diagnostic.to_string())
+				.collect::<Vec<_>>(),
+			vec![
+				"`Point` has no field `y` at 3:72",
+				"`inner` takes 0 arguments but 1 was given at 6:37",
+			]
+		);
+	}
+
+	#[test]
+	fn generic_fns() {
+		let diagnostics = check_source(
+			"typedef Point = { x: Num }
+			fn first<Point>(p: Point) { p.y }
+			fn second<T>(p: Point, f: (Point) -> T) -> T? { f(p).z + p.z }",
+		);
+
+		assert_eq!(
+			diagnostics
+				.iter()
+				.map(|diagnostic| diagnostic.to_string())
+				.collect::<Vec<_>>(),
+			vec!["`Point` has no field `z` at 3:61"]
+		);
+	}
+
+	#[test]
+	fn interfaces() {
+		let diagnostics = check_source(
+			"typedef Square = { side: Num, label: Str }
+			interface Shape {
+				fn area(self) -> Num;
+				fn scale(self, by: Num) -> Num { self.area() * by }
+			}
+			impl Shape for Square { fn area(self) -> Num { self.side * self.side } }
+			fn total(a: Shape, b: Square) { a.area() + b.scale(2) + a.scale() + b.label }
+			let s = Square { side: 1, label: \"s\" };
+			let t = s.area(1) + s.perimeter();",
+		);
+
+		assert_eq!(
+			diagnostics
+				.iter()
+				.map(|diagnostic| diagnostic.to_string())
+				.collect::<Vec<_>>(),
+			vec![
+				"`scale` takes 1 argument but 0 were given at 7:60",
+				"`area` takes 0 arguments but 1 was given at 9:12",
+				"`Square` has no field or method `perimeter` at 9:24",
+			]
+		);
+	}
+
+	#[test]
+	fn invalid_impls() {
+		let diagnostics = check_source(
+			"typedef Square = { side: Num }
+			interface Shape { fn area(self) -> Num; fn sides(self) -> Num; fn name(s) {} }
+			impl Shape for Square { fn area(self) -> Str { \"\" } fn corners(self) { 4 } }
+			impl Square for Shape {}",
+		);
+
+		assert_eq!(
+			diagnostics
+				.iter()
+				.map(|diagnostic| diagnostic.to_string())
+				.collect::<Vec<_>>(),
+			vec![
+				"Method `name` must take `self` as its first parameter at 2:4",
+				"`area` doesn't match its declaration in `Shape` at 3:4",
+				"`Shape` has no method `corners` at 3:4",
+				"`Square` doesn't implement `sides` of `Shape` at 3:4",
+				"`Square` is not an interface at 4:4",
+				"Unknown type `Shape` at 4:4",
+			]
+		);
+	}
+
+	#[test]
+	fn operator_overloading() {
+		let diagnostics = check_source(
+			"typedef Money = { cents: Num }
+			typedef Grid = { cells: [Num] }
+			impl Add for Money {
+				fn add(self, other) -> Money { Money { cents: self.cents + other.cents } }
+			}
+			impl Index for Grid { fn index(self, i) { self.cells[i] } }
+			fn f(a: Money, b: Money, g: Grid, s: Add) {
+				let mut c = a + b + a;
+				c += a;
+				c -= a;
+				let e = (a + b).cents + (a + b).dollars;
+				let d = a == b || a < b || g[0] > 1 || a[0] > 1 || s * 2;
+			}",
+		);
+
+		assert_eq!(
+			diagnostics
+				.iter()
+				.map(|diagnostic| diagnostic.to_string())
+				.collect::<Vec<_>>(),
+			vec![
+				"`Money` has no `-` operator, as it doesn't implement `Subtract` at 10:5",
+				"`Money` has no field `dollars` at 11:29",
+				"`Money` has no `<` operator, as it doesn't implement `Compare` at 12:23",
+				"`Money` has no `[]` operator, as it doesn't implement `Index` at 12:44",
+			]
+		);
+	}
+
+	#[test]
+	fn mismatched_operator_impl() {
+		let diagnostics = check_source(
+			"typedef Money = { cents: Num }
+			impl Compare for Money { fn compare(self, other) -> Bool { true } }",
+		);
+
+		assert_eq!(
+			diagnostics
+				.iter()
+				.map(|diagnostic| diagnostic.to_string())
+				.collect::<Vec<_>>(),
+			vec!["`compare` doesn't match its declaration in `Compare` at 2:4"]
+		);
+	}
+
+	#[test]
+	fn assignment() {
+		let diagnostics = check_source(
+			"typedef Point = { x: Num }
+			fn f() {}
+			let mut p = Point { x: 1 };
+			let q = p;
+			let mut (a, [..b]) = (1, []);
+			p.x += 1;
+			a = b[0] = 2;
+			q.x = 1;
+			f = 2;
+			let g = (n) => { n = 1 };
+			match p { Point { x } => x += 1 }",
+		);
+
+		assert_eq!(
+			diagnostics
+				.iter()
+				.map(|diagnostic| diagnostic.to_string())
+				.collect::<Vec<_>>(),
+			vec![
+				"Cannot assign to `q`, which is not declared with `let mut` at 8:4",
+				"Cannot assign to `f`, which is not declared with `let mut` at 9:4",
+				"Cannot assign to `n`, which is not declared with `let mut` at 10:21",
+				"Cannot assign to `x`, which is not declared with `let mut` at 11:29",
+			]
+		);
+	}
+
+	#[test]
+	fn for_loops() {
+		let diagnostics = check_source(
+			"typedef Point = { x: Num, y: Num }
+			typedef Countdown = { n: Num, next }
+			enum Shape = Circle(r) | Empty
+			let mut total = 0;
+			for (k, v) in { 1: 2 } { total += k + v }
+			for i in 0..=10 { i = 1 }
+			for n in (Countdown { n: 3, next: () => { nil } }) { total += n }
+			for p in (Point { x: 1, y: 2 }) {}
+			for Circle(r) in shapes {}",
+		);
+
+		assert_eq!(
+			diagnostics
+				.iter()
+				.map(|diagnostic| diagnostic.to_string())
+				.collect::<Vec<_>>(),
+			vec![
+				"Cannot assign to `i`, which is not declared with `let mut` at 6:22",
+				"`Point` can't be iterated over, as it has no `next` field at 8:13",
+				"Pattern in `for` might not match, use `match` instead at 9:8",
+			]
+		);
+	}
+
+	#[test]
+	fn results() {
+		let diagnostics = check_source(
+			"fn parse(s) { let n = int(s)?; if n > 0 { Ok(n) } else { Err(\"negative\") } }
+			let f = (s) => { parse(s)? + 1 };
+			let a = \"1\" ?>> parse ?>> Some(_, 2);
+			let b = match a { Ok(n) | Some(n) => n, Err(e) => e, None => 0 };
+			let c = parse(\"2\")?;",
+		);
+
+		assert_eq!(
+			diagnostics
+				.iter()
+				.map(|diagnostic| diagnostic.to_string())
+				.collect::<Vec<_>>(),
+			vec![
+				"`Some` takes 1 argument but 2 were given at 3:30",
+				"`?` can only be used inside a function at 5:12",
+			]
+		);
+	}
+
+	#[test]
+	fn shadowed_prelude() {
+		let diagnostics = check_source("enum Option = Some(a, b) | None let a = Some(1, 2);");
+
+		assert_eq!(diagnostics, vec![]);
+	}
+
+	#[test]
+	fn modules() {
+		let dir = std::env::temp_dir().join(format!("alkali-check-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		std::fs::write(
+			dir.join("shapes.alk"),
+			"export enum Shape = Circle(r) | Empty
+			export fn area(s) { 0 }
+			export let (unit, _) = (1, 2);
+			let hidden = 1;
+			const TWO = 2;
+			export const fn half(n: Num) -> Num { n / TWO }",
+		)
+		.unwrap();
+		std::fs::write(
+			dir.join("main.alk"),
+			"import \"shapes\" as shapes;
+			import { Circle, area, unit, hidden, half } from \"shapes\";
+			let area2 = (s) => { area(s) + shapes.area(s, 1) };
+			let a = shapes.Shape.Circle(unit) >> shapes.area;
+			let b = Circle(1, 2) + shapes.hidden;
+			unit = 2;
+			const TWO = 0;
+			const HALF = half(1);",
+		)
+		.unwrap();
+
+		let program = crate::module::load(&dir.join("main.alk")).unwrap();
+		let diagnostics = check_module(&program, program.root());
+
+		assert_eq!(
+			diagnostics
+				.iter()
+				.map(|diagnostic| diagnostic.to_string())
+				.collect::<Vec<_>>(),
+			vec![
+				"`shapes` has no export `hidden` at 2:4",
+				"`area` takes 1 argument but 2 were given at 3:35",
+				"`Circle` takes 1 argument but 2 were given at 5:12",
+				"`shapes` has no export `hidden` at 5:27",
+				"Cannot assign to `unit`, which is not declared with `let mut` at 6:4",
+			]
+		);
+	}
+
+	#[test]
+	fn macro_bindings_are_hygienic() {
+		let diagnostics = check_source(
+			"typedef Point = { x: Num, y: Num }
+			macro bump { ($name:ident) => { let mut count = 0; count = count + 1; $name = count; } }
+			macro origin { () => { Point { x: 0, y: 0 }.z } }
+			let count = 1;
+			bump!(count);
+			let z = origin!();",
+		);
+
+		assert_eq!(
+			diagnostics
+				.iter()
+				.map(|diagnostic| diagnostic.to_string())
+				.collect::<Vec<_>>(),
+			vec![
+				"Cannot assign to `count`, which is not declared with `let mut` at 5:10",
+				"`Point` has no field `z` at 3:27, in the expansion of `origin!` at 6:12",
+			]
+		);
+	}
+
+	#[test]
+	fn macro_bindings_in_patterns_are_hygienic() {
+		let diagnostics = check_source(
+			"macro destructure { ($name:ident) => { let (count, step) = (0, 1); $name = count + step; } }
+			macro arm { ($name:ident) => { match 0 { count => $name = count } } }
+			macro lambda { ($name:ident) => { let f = (count) => { $name = count }; } }
+			macro record { ($name:ident) => { let r = { count: 0 }; match r { { count } => $name = count } } }
+			let mut count = 1;
+			destructure!(count);
+			arm!(count);
+			lambda!(count);
+			record!(count);",
+		);
+
+		assert_eq!(diagnostics, vec![]);
+	}
+
+	#[test]
+	fn constants() {
+		let file = lower(
+			parse(
+				&mut "const fn square(x: Num) -> Num { x * x }
+				const fn factorial(n: Num) -> Num { if n <= 1 { 1 } else { n * factorial(n - 1) } }
+				const AREA = square(3) + 1;
+				const GREETING = \"hello, \" + \"world\";
+				const BIG = factorial(10) > 1000 && !false;
+				const MASK = (12 & 10) ~ 1;"
+					.as_bytes(),
+			)
+			.unwrap(),
+		)
+		.unwrap();
+
+		assert_eq!(check(&file), vec![]);
+		assert_eq!(
+			evaluate_constants(&file),
+			HashMap::from([
+				("AREA".to_string(), Constant::Num(10.0)),
+				(
+					"GREETING".to_string(),
+					Constant::Str("hello, world".to_string())
+				),
+				("BIG".to_string(), Constant::Bool(true)),
+				("MASK".to_string(), Constant::Num(9.0)),
+			])
+		);
+	}
+
+	#[test]
+	fn invalid_constants() {
+		let diagnostics = check_source(
+			"let x = 1;
+			fn f() { 1 }
+			const fn g(n: Num) { let mut m = n; m }
+			const fn forever(n: Num) { forever(n) }
+			const A = x + f();
+			const B = 10 ^ 300 * 10 ^ 300;
+			const C = 1 / (2 - 2);
+			const D = \"a\" - 1;
+			const E = 1 / 2 & 1;
+			const F = if 1 { 2 } else { 3 };
+			const G = forever(1);
+			const H = A + 1;",
+		);
+
+		assert_eq!(
+			diagnostics
+				.iter()
+				.map(|diagnostic| diagnostic.to_string())
+				.collect::<Vec<_>>(),
+			vec![
+				"Constants can only use literals, operators, other constants and calls to `const fn`s at 3:25",
+				"Constants can only use literals, operators, other constants and calls to `const fn`s at 5:14",
+				"Constants can only use literals, operators, other constants and calls to `const fn`s at 5:18",
+				"`*` overflows in a constant at 6:14",
+				"`/` divides by zero in a constant at 7:14",
+				"`-` can't be applied to `Str` and `Num` in a constant at 8:14",
+				"`&` needs integers in a constant at 9:14",
+				"The condition of an `if` in a constant must be a `Bool`, not `Num` at 10:17",
+				"Evaluating a constant nests more than 128 calls of `const fn`s at 4:31",
+			]
+		);
+	}
+
+	#[test]
+	fn constants_stop_at_what_const_fns_cant_do() {
+		let file = lower(
+			parse(
+				&mut "fn f() { 1 }
+				const fn g(n: Num) { f() + n }
+				const fn h(n: Num) { n; n }
+				const A = g(1);
+				const B = h(1);
+				const C = (0 - 8) ^ (1 / 2);"
+					.as_bytes(),
+			)
+			.unwrap(),
+		)
+		.unwrap();
+
+		assert_eq!(
+			check(&file)
+				.iter()
+				.map(|diagnostic| diagnostic.to_string())
+				.collect::<Vec<_>>(),
+			vec![
+				"Constants can only use literals, operators, other constants and calls to `const fn`s at 2:26",
+				"Constants can only use literals, operators, other constants and calls to `const fn`s at 3:26",
+				"`^` has no numeric result in a constant at 6:15",
+			]
+		);
+		assert_eq!(evaluate_constants(&file), HashMap::new());
+	}
+
+	#[test]
+	fn const_fns_see_names_where_they_are_declared() {
+		let diagnostics = check_source(
+			"const X = 1;
+			const fn f() -> Num { X }
+			fn g() {
+				const X = 0;
+				const Y = 1 / f();
+			}",
+		);
+
+		assert_eq!(diagnostics, vec![]);
+	}
+}
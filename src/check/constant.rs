@@ -0,0 +1,123 @@
+use std::fmt;
+
+use crate::ast::{BinaryOp, UnaryOp};
+
+/// A value known while compiling, which constants evaluate to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+	Num(f64),
+	Str(String),
+	Bool(bool),
+	Nil,
+}
+
+/// Why an operator can't be applied to constants
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ConstError {
+	/// The result is too large to be represented
+	Overflow,
+	/// The result isn't a number, like that of a negative number raised to a
+	/// fractional power
+	NotANumber,
+	DivisionByZero,
+	/// A bitwise operator applied to a number with a fractional part, or one
+	/// too large to be an integer
+	NotInteger,
+	/// The operator has no meaning for operands of these types
+	Operands,
+}
+
+impl Constant {
+	/// The name of the constant's type
+	pub fn type_name(&self) -> &'static str {
+		match self {
+			Self::Num(_) => "Num",
+			Self::Str(_) => "Str",
+			Self::Bool(_) => "Bool",
+			Self::Nil => "Nil",
+		}
+	}
+
+	/// Applies a binary operator. `&&` and `||` take booleans, but don't
+	/// short-circuit here as both operands are already evaluated.
+	pub fn binary(op: BinaryOp, left: &Self, right: &Self) -> Result<Self, ConstError> {
+		use Constant::*;
+
+		let result = match (op, left, right) {
+			(BinaryOp::Equal, _, _) => Bool(left == right),
+			(BinaryOp::NotEqual, _, _) => Bool(left != right),
+			(BinaryOp::Add, Str(a), Str(b)) => Str(format!("{a}{b}")),
+			(BinaryOp::LessThan, Str(a), Str(b)) => Bool(a < b),
+			(BinaryOp::LessThanOrEqual, Str(a), Str(b)) => Bool(a <= b),
+			(BinaryOp::GreaterThan, Str(a), Str(b)) => Bool(a > b),
+			(BinaryOp::GreaterThanOrEqual, Str(a), Str(b)) => Bool(a >= b),
+			(BinaryOp::And | BinaryOp::BitAnd, Bool(a), Bool(b)) => Bool(*a && *b),
+			(BinaryOp::Or | BinaryOp::BitOr, Bool(a), Bool(b)) => Bool(*a || *b),
+			(BinaryOp::BitXor, Bool(a), Bool(b)) => Bool(a != b),
+			(_, Num(a), Num(b)) => return numeric(op, *a, *b),
+			_ => return Err(ConstError::Operands),
+		};
+
+		Ok(result)
+	}
+
+	pub fn unary(op: &UnaryOp, operand: &Self) -> Result<Self, ConstError> {
+		match (op, operand) {
+			(UnaryOp::Negate, Self::Num(n)) => Ok(Self::Num(-n)),
+			(UnaryOp::Plus, Self::Num(n)) => Ok(Self::Num(*n)),
+			(UnaryOp::Not, Self::Bool(b)) => Ok(Self::Bool(!b)),
+			(UnaryOp::BitNot, Self::Num(n)) => Ok(Self::Num(!integer(*n)? as f64)),
+			_ => Err(ConstError::Operands),
+		}
+	}
+}
+
+fn numeric(op: BinaryOp, a: f64, b: f64) -> Result<Constant, ConstError> {
+	let result = match op {
+		BinaryOp::Add => a + b,
+		BinaryOp::Subtract => a - b,
+		BinaryOp::Multiply => a * b,
+		BinaryOp::Divide | BinaryOp::Modulo if b == 0.0 => {
+			return Err(ConstError::DivisionByZero);
+		}
+		BinaryOp::Divide => a / b,
+		BinaryOp::Modulo => a % b,
+		BinaryOp::Power => a.powf(b),
+		BinaryOp::BitAnd => (integer(a)? & integer(b)?) as f64,
+		BinaryOp::BitOr => (integer(a)? | integer(b)?) as f64,
+		BinaryOp::BitXor => (integer(a)? ^ integer(b)?) as f64,
+		BinaryOp::LessThan => return Ok(Constant::Bool(a < b)),
+		BinaryOp::LessThanOrEqual => return Ok(Constant::Bool(a <= b)),
+		BinaryOp::GreaterThan => return Ok(Constant::Bool(a > b)),
+		BinaryOp::GreaterThanOrEqual => return Ok(Constant::Bool(a >= b)),
+		_ => return Err(ConstError::Operands),
+	};
+
+	if result.is_nan() {
+		Err(ConstError::NotANumber)
+	} else if result.is_infinite() {
+		Err(ConstError::Overflow)
+	} else {
+		Ok(Constant::Num(result))
+	}
+}
+
+fn integer(n: f64) -> Result<i64, ConstError> {
+	if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+		Ok(n as i64)
+	} else {
+		Err(ConstError::NotInteger)
+	}
+}
+
+/// Shows the constant as a literal
+impl fmt::Display for Constant {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Num(n) => write!(f, "{n}"),
+			Self::Str(s) => write!(f, "{s:?}"),
+			Self::Bool(b) => write!(f, "{b}"),
+			Self::Nil => write!(f, "nil"),
+		}
+	}
+}
@@ -0,0 +1,263 @@
+use std::{error::Error, fmt};
+
+use crate::ast::{Backtrace, Span};
+
+/// A problem found in a parsed program, pointing at where it occurs
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+	pub kind: DiagnosticKind,
+	pub span: Span,
+	pub backtrace: Backtrace,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DiagnosticKind {
+	/// A record literal naming something that isn't a typedef
+	UnknownType(String),
+	UnknownField {
+		type_name: String,
+		field: String,
+	},
+	/// A field without a default value left out of a record literal
+	MissingField {
+		type_name: String,
+		field: String,
+	},
+	DuplicateField {
+		type_name: String,
+		field: String,
+	},
+	DuplicateVariant {
+		enum_name: String,
+		variant: String,
+	},
+	UnknownVariant {
+		enum_name: String,
+		variant: String,
+	},
+	/// A function or variant constructor called with the wrong number of
+	/// arguments, where those with defaults may be left off the end
+	Arity {
+		callee: String,
+		min: usize,
+		max: usize,
+		found: usize,
+	},
+	/// A variant pattern naming something that isn't a variant
+	NotAVariant(String),
+	/// A variant pattern that doesn't match exactly the variant's fields
+	PatternArity {
+		variant: String,
+		expected: usize,
+		found: usize,
+	},
+	/// A name bound by some alternatives of an or-pattern but not others
+	InconsistentBinding(String),
+	/// A `let` or `for` pattern that doesn't match every value, like a literal
+	/// or variant
+	RefutablePattern(&'static str),
+	/// An assignment to, or through, a name not declared with `let mut`
+	ImmutableAssignment(String),
+	/// A record without a `next` field iterated over by a `for` loop
+	NotIterable(String),
+	/// A `?` outside of a function, which it would return from
+	TryOutsideFunction,
+	/// An import of, or access to, a name a module doesn't export
+	UnknownExport {
+		module: String,
+		name: String,
+	},
+	/// An impl of something that isn't an interface
+	UnknownInterface(String),
+	/// A method without a default that an impl leaves out
+	MissingMethod {
+		interface: String,
+		type_name: String,
+		method: String,
+	},
+	/// A method in an impl that its interface doesn't declare
+	NotInInterface {
+		interface: String,
+		method: String,
+	},
+	/// A method in an impl whose signature differs from its interface's
+	MismatchedMethod {
+		interface: String,
+		method: String,
+	},
+	/// A method whose first parameter isn't `self`
+	MissingReceiver(String),
+	/// An operator applied to a record whose type doesn't implement the interface
+	/// overloading it
+	NoOverload {
+		operator: String,
+		type_name: String,
+		interface: &'static str,
+	},
+	/// A call of a method that neither the record's fields nor its impls have
+	UnknownMethod {
+		type_name: String,
+		method: String,
+	},
+	/// Something in a constant or `const fn` that can't be evaluated while
+	/// compiling
+	NotConstant,
+	/// An operator in a constant whose result is too large to represent
+	ConstOverflow(&'static str),
+	/// An operator in a constant whose result isn't a number
+	ConstNotANumber(&'static str),
+	/// A `/` or `%` by zero in a constant
+	DivisionByZero(&'static str),
+	/// A bitwise operator in a constant applied to a number that isn't an integer
+	ConstNotInteger(&'static str),
+	/// An operator in a constant applied to values of types it has no meaning for
+	ConstOperands {
+		operator: &'static str,
+		types: Vec<&'static str>,
+	},
+	/// An `if` in a constant whose condition isn't a boolean
+	ConstCondition(&'static str),
+	/// Calls of `const fn`s nested too deep while evaluating a constant, which is
+	/// almost always a recursion that never stops
+	ConstRecursion(usize),
+}
+
+impl Error for Diagnostic {}
+
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match &self.kind {
+			DiagnosticKind::UnknownType(name) => write!(f, "Unknown type `{name}`")?,
+			DiagnosticKind::UnknownField { type_name, field } => {
+				write!(f, "`{type_name}` has no field `{field}`")?
+			}
+			DiagnosticKind::MissingField { type_name, field } => {
+				write!(f, "Missing field `{field}` in `{type_name}`")?
+			}
+			DiagnosticKind::DuplicateField { type_name, field } => write!(
+				f,
+				"Field `{field}` of `{type_name}` is given more than once"
+			)?,
+			DiagnosticKind::DuplicateVariant { enum_name, variant } => write!(
+				f,
+				"Variant `{variant}` of `{enum_name}` is declared more than once"
+			)?,
+			DiagnosticKind::UnknownVariant { enum_name, variant } => {
+				write!(f, "`{enum_name}` has no variant `{variant}`")?
+			}
+			DiagnosticKind::Arity {
+				callee,
+				min,
+				max,
+				found,
+			} => {
+				let expected = match (min, max) {
+					(1, 1) => "1 argument".to_string(),
+					(min, max) if min == max => format!("{min} arguments"),
+					(min, max) => format!("{min} to {max} arguments"),
+				};
+				let given = if *found == 1 { "was" } else { "were" };
+				write!(f, "`{callee}` takes {expected} but {found} {given} given")?
+			}
+			DiagnosticKind::NotAVariant(name) => write!(f, "`{name}` is not an enum variant")?,
+			DiagnosticKind::PatternArity {
+				variant,
+				expected,
+				found,
+			} => {
+				let fields = if *expected == 1 { "field" } else { "fields" };
+				write!(
+					f,
+					"`{variant}` has {expected} {fields} but the pattern matches {found}"
+				)?
+			}
+			DiagnosticKind::InconsistentBinding(name) => write!(
+				f,
+				"`{name}` is not bound in every alternative of the pattern"
+			)?,
+			DiagnosticKind::RefutablePattern(construct) => write!(
+				f,
+				"Pattern in `{construct}` might not match, use `match` instead"
+			)?,
+			DiagnosticKind::NotIterable(type_name) => write!(
+				f,
+				"`{type_name}` can't be iterated over, as it has no `next` field"
+			)?,
+			DiagnosticKind::TryOutsideFunction => {
+				write!(f, "`?` can only be used inside a function")?
+			}
+			DiagnosticKind::UnknownExport { module, name } => {
+				write!(f, "`{module}` has no export `{name}`")?
+			}
+			DiagnosticKind::UnknownInterface(name) => write!(f, "`{name}` is not an interface")?,
+			DiagnosticKind::MissingMethod {
+				interface,
+				type_name,
+				method,
+			} => write!(
+				f,
+				"`{type_name}` doesn't implement `{method}` of `{interface}`"
+			)?,
+			DiagnosticKind::NotInInterface { interface, method } => {
+				write!(f, "`{interface}` has no method `{method}`")?
+			}
+			DiagnosticKind::MismatchedMethod { interface, method } => write!(
+				f,
+				"`{method}` doesn't match its declaration in `{interface}`"
+			)?,
+			DiagnosticKind::MissingReceiver(method) => write!(
+				f,
+				"Method `{method}` must take `self` as its first parameter"
+			)?,
+			DiagnosticKind::NoOverload {
+				operator,
+				type_name,
+				interface,
+			} => write!(
+				f,
+				"`{type_name}` has no `{operator}` operator, as it doesn't implement `{interface}`"
+			)?,
+			DiagnosticKind::UnknownMethod { type_name, method } => {
+				write!(f, "`{type_name}` has no field or method `{method}`")?
+			}
+			DiagnosticKind::NotConstant => write!(
+				f,
+				"Constants can only use literals, operators, other constants and calls to `const fn`s"
+			)?,
+			DiagnosticKind::ConstOverflow(operator) => {
+				write!(f, "`{operator}` overflows in a constant")?
+			}
+			DiagnosticKind::ConstNotANumber(operator) => {
+				write!(f, "`{operator}` has no numeric result in a constant")?
+			}
+			DiagnosticKind::DivisionByZero(operator) => {
+				write!(f, "`{operator}` divides by zero in a constant")?
+			}
+			DiagnosticKind::ConstNotInteger(operator) => {
+				write!(f, "`{operator}` needs integers in a constant")?
+			}
+			DiagnosticKind::ConstOperands { operator, types } => {
+				let types = types
+					.iter()
+					.map(|type_name| format!("`{type_name}`"))
+					.collect::<Vec<_>>()
+					.join(" and ");
+				write!(f, "`{operator}` can't be applied to {types} in a constant")?
+			}
+			DiagnosticKind::ConstCondition(type_name) => write!(
+				f,
+				"The condition of an `if` in a constant must be a `Bool`, not `{type_name}`"
+			)?,
+			DiagnosticKind::ConstRecursion(limit) => write!(
+				f,
+				"Evaluating a constant nests more than {limit} calls of `const fn`s"
+			)?,
+			DiagnosticKind::ImmutableAssignment(name) => write!(
+				f,
+				"Cannot assign to `{name}`, which is not declared with `let mut`"
+			)?,
+		}
+
+		write!(f, " at {}{}", self.span, self.backtrace)
+	}
+}
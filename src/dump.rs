@@ -0,0 +1,5 @@
+mod json;
+mod sexpr;
+
+pub use json::*;
+pub use sexpr::*;
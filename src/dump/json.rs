@@ -0,0 +1,122 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::shared::Result;
+
+/// Writes tokens or a tree as indented JSON, spans and IDs included.
+///
+/// Fields appear in declaration order, variants with data as
+/// `{"Variant": data}` and variants without as `"Variant"`, so the output only
+/// changes when the types themselves do.
+pub fn to_json<T: Serialize + ?Sized>(value: &T) -> String {
+	serde_json::to_string_pretty(value).expect("tokens and trees can always be written as JSON")
+}
+
+/// Reads back tokens or a tree written by `to_json`.
+///
+/// As the JSON may have been written by hand, the IDs of a tree are checked to
+/// refer to nodes and invocations that exist, and its parents are worked out
+/// again from the nodes rather than read.
+pub fn from_json<T: DeserializeOwned>(json: &str) -> Result<T> {
+	Ok(serde_json::from_str(json)?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		ast::{Arena, Expr, ExprKind, NodeId, Span},
+		lex::{Lexer, Token},
+		parse::{parse, SourceFile},
+	};
+
+	#[test]
+	fn writes_spans_ids_and_variants() {
+		let mut arena = Arena::default();
+		let p = arena.alloc_expr(ExprKind::Reference("p".into()), Span::new(1, 1));
+		arena.alloc_expr(ExprKind::Field(p, "x".into()), Span::new(1, 1));
+
+		assert_eq!(
+			serde_json::from_str::<serde_json::Value>(&to_json(&arena)).unwrap(),
+			serde_json::json!({
+				"nodes": [
+					{ "Expr": { "kind": { "Reference": "p" }, "span": { "row": 1, "col": 1 } } },
+					{ "Expr": { "kind": { "Field": [0, "x"] }, "span": { "row": 1, "col": 1 } } }
+				],
+				"parents": [1, null]
+			})
+		);
+	}
+
+	#[test]
+	fn round_trips_tokens() {
+		let tokens = Lexer::new(&mut "let x = \"a\" >> f(_);".as_bytes()).collect::<Vec<_>>();
+
+		assert_eq!(from_json::<Vec<Token>>(&to_json(&tokens)).unwrap(), tokens);
+	}
+
+	#[test]
+	fn round_trips_files_with_spans() {
+		let file = parse(
+			&mut "macro twice { ($x:expr) => { $x + $x } }
+			@doc(\"a\") fn f<T>(a: [T]?, b = 2) -> (T) -> Num { twice!(a[1..]) }
+			let mut { x, y: [z, ..rest] } = P { ..p, x: -1 };
+			match e { A(n) | B if n => {}, _ => ((), nil, true) }"
+				.as_bytes(),
+		)
+		.unwrap();
+
+		let read = from_json::<SourceFile>(&to_json(&file)).unwrap();
+
+		// Trees compare equal regardless of spans, which do show up in debug output
+		assert_eq!(format!("{read:?}"), format!("{file:?}"));
+	}
+
+	#[test]
+	fn works_out_parents_again() {
+		let arena = from_json::<Arena>(
+			r#"{
+				"nodes": [
+					{ "Expr": { "kind": { "Reference": "p" }, "span": { "row": 1, "col": 1 } } },
+					{ "Expr": { "kind": { "Field": [0, "x"] }, "span": { "row": 1, "col": 1 } } }
+				],
+				"parents": [null, 0]
+			}"#,
+		)
+		.unwrap();
+
+		assert_eq!(arena.parent(NodeId(0)), Some(NodeId(1)));
+		assert_eq!(arena.parent(NodeId(1)), None);
+	}
+
+	#[test]
+	fn rejects_dangling_ids() {
+		let file = parse(&mut "p.x;".as_bytes()).unwrap();
+		let read = |change: fn(&mut serde_json::Value)| {
+			let mut json = serde_json::from_str(&to_json(&file)).unwrap();
+			change(&mut json);
+			from_json::<SourceFile>(&json.to_string())
+				.unwrap_err()
+				.to_string()
+		};
+
+		assert!(read(|json| json["stmts"][0] = 99.into()).starts_with("Dangling statement ID 99"));
+		// `p` is node 0, which is an expression rather than a statement
+		assert!(read(|json| json["stmts"][0] = 0.into()).starts_with("Dangling statement ID 0"));
+		// A node can only refer to nodes added before it, so not to itself
+		assert!(
+			read(|json| json["arena"]["nodes"][1]["Expr"]["kind"]["Field"][0] = 1.into())
+				.starts_with("Dangling expression ID 1")
+		);
+		assert!(
+			read(|json| json["arena"]["nodes"][0]["Expr"]["span"]["expansion"] = 3.into())
+				.starts_with("Dangling macro invocation ID 3")
+		);
+	}
+
+	#[test]
+	fn rejects_unknown_variants() {
+		assert!(
+			from_json::<Expr>(r#"{ "kind": "Nothing", "span": { "row": 1, "col": 1 } }"#).is_err()
+		);
+	}
+}
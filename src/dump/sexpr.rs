@@ -0,0 +1,526 @@
+use std::fmt;
+
+use serde::{ser, Serialize};
+
+use crate::ast::{self, Arena, NodeId};
+
+/// Writes tokens or a tree on one line as an S-expression for snapshot tests,
+/// like `(Binary Add (Reference "a") (NumLit 1))`.
+///
+/// Spans and IDs are left out, and so are struct fields that are absent, empty
+/// or false. Structs are written as `(Name (field value) ...)`, except that
+/// those made of nothing but a `kind` are written as the kind alone, and a
+/// variant holding a struct takes the struct's fields as its own, as in
+/// `(LetDecl (pattern (Binding "x")) (value (NumLit 1)))`.
+///
+/// The IDs of nodes are written as numbers, so trees are written with
+/// `to_sexpr_in`.
+pub fn to_sexpr<T: Serialize + ?Sized>(value: &T) -> String {
+	write(value, NodeSerializer { arena: None })
+}
+
+/// Writes a tree like `to_sexpr`, with each ID written as the node it refers
+/// to in `arena`
+pub fn to_sexpr_in<T: Serialize + ?Sized>(arena: &Arena, value: &T) -> String {
+	write(value, NodeSerializer { arena: Some(arena) })
+}
+
+fn write<T: Serialize + ?Sized>(value: &T, serializer: NodeSerializer) -> String {
+	value
+		.serialize(serializer)
+		.expect("tokens and trees can always be written as S-expressions")
+		.to_string()
+}
+
+enum Node {
+	Atom(String),
+	/// Tuples and variants with data, `(a b c)`
+	List(Vec<Node>),
+	/// `[a b c]`
+	Seq(Vec<Node>),
+	/// A struct's name and fields, whose fields are spliced into a variant
+	/// holding it
+	Struct(&'static str, Vec<Node>),
+	/// `None`, written `nil` where it can't be left out
+	Absent,
+}
+
+impl Node {
+	/// Whether the node is left out when it's the value of a struct field
+	fn is_empty(&self) -> bool {
+		match self {
+			Node::Atom(atom) => atom == "false",
+			Node::Seq(items) => items.is_empty(),
+			Node::Absent => true,
+			Node::List(_) | Node::Struct(..) => false,
+		}
+	}
+}
+
+impl fmt::Display for Node {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let (open, close, items) = match self {
+			Node::Atom(atom) => return f.write_str(atom),
+			Node::Absent => return f.write_str("nil"),
+			Node::List(items) => ("(", ")", items),
+			Node::Seq(items) => ("[", "]", items),
+			Node::Struct(name, fields) => {
+				write!(f, "({name}")?;
+				for field in fields {
+					write!(f, " {field}")?;
+				}
+				return f.write_str(")");
+			}
+		};
+
+		f.write_str(open)?;
+		for (i, item) in items.iter().enumerate() {
+			if i > 0 {
+				f.write_str(" ")?;
+			}
+			write!(f, "{item}")?;
+		}
+		f.write_str(close)
+	}
+}
+
+/// Serialises values into nodes, which can't fail
+#[derive(Clone, Copy)]
+struct NodeSerializer<'a> {
+	/// The arena IDs are looked up in, if they're written as their nodes
+	arena: Option<&'a Arena>,
+}
+
+impl NodeSerializer<'_> {
+	fn atom(value: impl fmt::Display) -> Result<Node, fmt::Error> {
+		Ok(Node::Atom(value.to_string()))
+	}
+
+	/// Writes the node an ID refers to, given the ID written as a number
+	fn resolve(self, id: Node) -> Result<Node, fmt::Error> {
+		let (Some(arena), Node::Atom(id)) = (self.arena, id) else {
+			return Err(fmt::Error);
+		};
+		let id = NodeId(id.parse().map_err(|_| fmt::Error)?);
+
+		match arena.get(id).ok_or(fmt::Error)? {
+			ast::Node::Stmt(stmt) => stmt.serialize(self),
+			ast::Node::Expr(expr) => expr.serialize(self),
+			ast::Node::Pattern(pattern) => pattern.serialize(self),
+			ast::Node::TypeExpr(type_expr) => type_expr.serialize(self),
+		}
+	}
+}
+
+impl<'a> ser::Serializer for NodeSerializer<'a> {
+	type Ok = Node;
+	type Error = fmt::Error;
+	type SerializeSeq = Items<'a>;
+	type SerializeTuple = Items<'a>;
+	type SerializeTupleStruct = Items<'a>;
+	type SerializeTupleVariant = Items<'a>;
+	type SerializeMap = Items<'a>;
+	type SerializeStruct = Fields<'a>;
+	type SerializeStructVariant = Fields<'a>;
+
+	fn serialize_bool(self, v: bool) -> Result<Node, fmt::Error> {
+		Self::atom(v)
+	}
+
+	fn serialize_i8(self, v: i8) -> Result<Node, fmt::Error> {
+		Self::atom(v)
+	}
+
+	fn serialize_i16(self, v: i16) -> Result<Node, fmt::Error> {
+		Self::atom(v)
+	}
+
+	fn serialize_i32(self, v: i32) -> Result<Node, fmt::Error> {
+		Self::atom(v)
+	}
+
+	fn serialize_i64(self, v: i64) -> Result<Node, fmt::Error> {
+		Self::atom(v)
+	}
+
+	fn serialize_u8(self, v: u8) -> Result<Node, fmt::Error> {
+		Self::atom(v)
+	}
+
+	fn serialize_u16(self, v: u16) -> Result<Node, fmt::Error> {
+		Self::atom(v)
+	}
+
+	fn serialize_u32(self, v: u32) -> Result<Node, fmt::Error> {
+		Self::atom(v)
+	}
+
+	fn serialize_u64(self, v: u64) -> Result<Node, fmt::Error> {
+		Self::atom(v)
+	}
+
+	fn serialize_f32(self, v: f32) -> Result<Node, fmt::Error> {
+		Self::atom(v)
+	}
+
+	fn serialize_f64(self, v: f64) -> Result<Node, fmt::Error> {
+		Self::atom(v)
+	}
+
+	fn serialize_char(self, v: char) -> Result<Node, fmt::Error> {
+		Self::atom(format_args!("{:?}", v.to_string()))
+	}
+
+	fn serialize_str(self, v: &str) -> Result<Node, fmt::Error> {
+		Self::atom(format_args!("{v:?}"))
+	}
+
+	fn serialize_bytes(self, v: &[u8]) -> Result<Node, fmt::Error> {
+		Ok(Node::Seq(
+			v.iter().map(|byte| Node::Atom(byte.to_string())).collect(),
+		))
+	}
+
+	fn serialize_none(self) -> Result<Node, fmt::Error> {
+		Ok(Node::Absent)
+	}
+
+	fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Node, fmt::Error> {
+		value.serialize(self)
+	}
+
+	fn serialize_unit(self) -> Result<Node, fmt::Error> {
+		Self::atom("()")
+	}
+
+	fn serialize_unit_struct(self, name: &'static str) -> Result<Node, fmt::Error> {
+		Self::atom(name)
+	}
+
+	fn serialize_unit_variant(
+		self,
+		_name: &'static str,
+		_index: u32,
+		variant: &'static str,
+	) -> Result<Node, fmt::Error> {
+		Self::atom(variant)
+	}
+
+	fn serialize_newtype_struct<T: Serialize + ?Sized>(
+		self,
+		name: &'static str,
+		value: &T,
+	) -> Result<Node, fmt::Error> {
+		match name {
+			"StmtId" | "ExprId" | "PatternId" | "TypeExprId" if self.arena.is_some() => {
+				self.resolve(value.serialize(self)?)
+			}
+			_ => value.serialize(self),
+		}
+	}
+
+	fn serialize_newtype_variant<T: Serialize + ?Sized>(
+		self,
+		_name: &'static str,
+		_index: u32,
+		variant: &'static str,
+		value: &T,
+	) -> Result<Node, fmt::Error> {
+		let mut items = vec![Node::Atom(variant.to_string())];
+
+		match value.serialize(self)? {
+			Node::Struct(_, fields) => items.extend(fields),
+			value => items.push(value),
+		}
+
+		Ok(Node::List(items))
+	}
+
+	fn serialize_seq(self, len: Option<usize>) -> Result<Items<'a>, fmt::Error> {
+		Ok(Items::new(self, len.unwrap_or(0), Node::Seq))
+	}
+
+	fn serialize_tuple(self, len: usize) -> Result<Items<'a>, fmt::Error> {
+		Ok(Items::new(self, len, Node::List))
+	}
+
+	fn serialize_tuple_struct(
+		self,
+		_name: &'static str,
+		len: usize,
+	) -> Result<Items<'a>, fmt::Error> {
+		Ok(Items::new(self, len, Node::List))
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		_name: &'static str,
+		_index: u32,
+		variant: &'static str,
+		len: usize,
+	) -> Result<Items<'a>, fmt::Error> {
+		let mut items = Items::new(self, len + 1, Node::List);
+		items.items.push(Node::Atom(variant.to_string()));
+
+		Ok(items)
+	}
+
+	fn serialize_map(self, len: Option<usize>) -> Result<Items<'a>, fmt::Error> {
+		Ok(Items::new(self, len.unwrap_or(0), Node::Seq))
+	}
+
+	fn serialize_struct(self, name: &'static str, len: usize) -> Result<Fields<'a>, fmt::Error> {
+		Ok(Fields::new(self, name, len))
+	}
+
+	fn serialize_struct_variant(
+		self,
+		_name: &'static str,
+		_index: u32,
+		variant: &'static str,
+		len: usize,
+	) -> Result<Fields<'a>, fmt::Error> {
+		Ok(Fields::new(self, variant, len))
+	}
+}
+
+/// The items of a sequence, tuple or map, where each map entry is written as a
+/// `(key value)` pair
+struct Items<'a> {
+	serializer: NodeSerializer<'a>,
+	items: Vec<Node>,
+	key: Option<Node>,
+	finish: fn(Vec<Node>) -> Node,
+}
+
+impl<'a> Items<'a> {
+	fn new(serializer: NodeSerializer<'a>, len: usize, finish: fn(Vec<Node>) -> Node) -> Self {
+		Self {
+			serializer,
+			items: Vec::with_capacity(len),
+			key: None,
+			finish,
+		}
+	}
+
+	fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), fmt::Error> {
+		self.items.push(value.serialize(self.serializer)?);
+		Ok(())
+	}
+
+	fn end(self) -> Result<Node, fmt::Error> {
+		Ok((self.finish)(self.items))
+	}
+}
+
+impl ser::SerializeSeq for Items<'_> {
+	type Ok = Node;
+	type Error = fmt::Error;
+
+	fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), fmt::Error> {
+		self.push(value)
+	}
+
+	fn end(self) -> Result<Node, fmt::Error> {
+		Items::end(self)
+	}
+}
+
+impl ser::SerializeTuple for Items<'_> {
+	type Ok = Node;
+	type Error = fmt::Error;
+
+	fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), fmt::Error> {
+		self.push(value)
+	}
+
+	fn end(self) -> Result<Node, fmt::Error> {
+		Items::end(self)
+	}
+}
+
+impl ser::SerializeTupleStruct for Items<'_> {
+	type Ok = Node;
+	type Error = fmt::Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), fmt::Error> {
+		self.push(value)
+	}
+
+	fn end(self) -> Result<Node, fmt::Error> {
+		Items::end(self)
+	}
+}
+
+impl ser::SerializeTupleVariant for Items<'_> {
+	type Ok = Node;
+	type Error = fmt::Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), fmt::Error> {
+		self.push(value)
+	}
+
+	fn end(self) -> Result<Node, fmt::Error> {
+		Items::end(self)
+	}
+}
+
+impl ser::SerializeMap for Items<'_> {
+	type Ok = Node;
+	type Error = fmt::Error;
+
+	fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), fmt::Error> {
+		self.key = Some(key.serialize(self.serializer)?);
+		Ok(())
+	}
+
+	fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), fmt::Error> {
+		let key = self.key.take().ok_or(fmt::Error)?;
+		self.items
+			.push(Node::List(vec![key, value.serialize(self.serializer)?]));
+		Ok(())
+	}
+
+	fn end(self) -> Result<Node, fmt::Error> {
+		Items::end(self)
+	}
+}
+
+/// The fields of a struct, or of a variant written like one
+struct Fields<'a> {
+	serializer: NodeSerializer<'a>,
+	name: &'static str,
+	kind: Option<Node>,
+	fields: Vec<Node>,
+}
+
+impl<'a> Fields<'a> {
+	fn new(serializer: NodeSerializer<'a>, name: &'static str, len: usize) -> Self {
+		Self {
+			serializer,
+			name,
+			kind: None,
+			fields: Vec::with_capacity(len),
+		}
+	}
+
+	fn push<T: Serialize + ?Sized>(
+		&mut self,
+		key: &'static str,
+		value: &T,
+	) -> Result<(), fmt::Error> {
+		if key == "span" || key == "id" {
+			return Ok(());
+		}
+
+		let value = value.serialize(self.serializer)?;
+		if key == "kind" {
+			self.kind = Some(value);
+		} else if !value.is_empty() {
+			self.fields
+				.push(Node::List(vec![Node::Atom(key.to_string()), value]));
+		}
+
+		Ok(())
+	}
+
+	fn end(self) -> Result<Node, fmt::Error> {
+		Ok(match self.kind {
+			Some(kind) if self.fields.is_empty() => kind,
+			kind => Node::Struct(self.name, kind.into_iter().chain(self.fields).collect()),
+		})
+	}
+}
+
+impl ser::SerializeStruct for Fields<'_> {
+	type Ok = Node;
+	type Error = fmt::Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(
+		&mut self,
+		key: &'static str,
+		value: &T,
+	) -> Result<(), fmt::Error> {
+		self.push(key, value)
+	}
+
+	fn end(self) -> Result<Node, fmt::Error> {
+		Fields::end(self)
+	}
+}
+
+impl ser::SerializeStructVariant for Fields<'_> {
+	type Ok = Node;
+	type Error = fmt::Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(
+		&mut self,
+		key: &'static str,
+		value: &T,
+	) -> Result<(), fmt::Error> {
+		self.push(key, value)
+	}
+
+	fn end(self) -> Result<Node, fmt::Error> {
+		Fields::end(self)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{lex::Lexer, parse::parse};
+
+	fn sexpr(source: &str) -> String {
+		let file = parse(&mut source.as_bytes()).unwrap();
+		to_sexpr_in(&file.arena, &file.stmts)
+	}
+
+	#[test]
+	fn writes_expressions() {
+		assert_eq!(
+			sexpr("-a + b.c[1..] * f(\"s\", 0..=n);"),
+			"[(Expr (Binary Add (Unary Negate (Reference \"a\")) \
+			(Binary Multiply (Slice (Field (Reference \"b\") \"c\") (NumLit 1) nil) \
+			(FnInvoke (Reference \"f\") [(StrLit \"s\") (Range (NumLit 0) (Reference \"n\") true)]))))]"
+		);
+	}
+
+	#[test]
+	fn splices_declarations_into_statements() {
+		assert_eq!(
+			sexpr("let mut x = P { y: 1 }; fn f(a) { a }"),
+			"[(LetDecl (mutable true) (pattern (Binding \"x\")) \
+			(value (Record (type_name \"P\") (fields [(FieldInit (name \"y\") (value (NumLit 1)))])))) \
+			(FnDecl (name \"f\") (params [(TypedefField (name \"a\"))]) (body (Block (expr (Reference \"a\")))))]"
+		);
+	}
+
+	#[test]
+	fn writes_patterns() {
+		assert_eq!(
+			sexpr("match x { [a, ..] | (1, _) if a => () }"),
+			"[(Expr (Match (Reference \"x\") [(MatchArm \
+			(pattern (Or [(List [(Binding \"a\") (Rest nil)]) (Tuple [(Literal (NumLit 1)) Wildcard])])) \
+			(guard (Reference \"a\")) (body UnitLit))]))]"
+		);
+	}
+
+	#[test]
+	fn writes_ids_as_numbers_without_an_arena() {
+		let file = parse(&mut "x; y;".as_bytes()).unwrap();
+
+		assert_eq!(to_sexpr(&file.stmts), "[1 3]");
+	}
+
+	#[test]
+	fn writes_tokens() {
+		let tokens = Lexer::new(&mut "x >> 1".as_bytes()).collect::<Vec<_>>();
+
+		assert_eq!(
+			to_sexpr(&tokens),
+			"[(Token (Identifier \"x\") (row 1) (col 1)) (Token Pipe (row 1) (col 3)) \
+			(Token (NumberLiteral \"1\") (row 1) (col 6))]"
+		);
+	}
+}
@@ -0,0 +1,5 @@
+mod interner;
+mod symbol;
+
+pub use interner::*;
+pub use symbol::*;
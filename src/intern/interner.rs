@@ -0,0 +1,101 @@
+use std::{
+	collections::HashMap,
+	sync::{LazyLock, Mutex},
+};
+
+use super::Symbol;
+use crate::lex::Keyword;
+
+/// The names of every symbol interned so far, shared by the whole session so
+/// that symbols from different files, and from the prelude, can be compared.
+///
+/// Names are leaked, as the symbols standing for them can be kept for the rest
+/// of the program. Keywords are interned first, so that the symbol of a keyword
+/// is its index in `Keyword::ALL`, followed by the predefined symbols.
+pub struct Interner {
+	symbols: HashMap<&'static str, Symbol>,
+	names: Vec<&'static str>,
+}
+
+static INTERNER: LazyLock<Mutex<Interner>> = LazyLock::new(|| Mutex::new(Interner::new()));
+
+impl Interner {
+	fn new() -> Self {
+		let mut interner = Self {
+			symbols: HashMap::new(),
+			names: Vec::new(),
+		};
+
+		for keyword in Keyword::ALL {
+			interner.intern(keyword.as_str());
+		}
+
+		for name in Symbol::PREDEFINED {
+			interner.intern(name);
+		}
+
+		interner
+	}
+
+	/// Runs `f` with the session's interner, which is locked meanwhile
+	pub fn with<T>(f: impl FnOnce(&mut Interner) -> T) -> T {
+		let mut interner = INTERNER.lock().unwrap_or_else(|error| error.into_inner());
+		f(&mut interner)
+	}
+
+	/// The symbol for `name`, which is the same each time `name` is interned
+	pub fn intern(&mut self, name: &str) -> Symbol {
+		if let Some(&symbol) = self.symbols.get(name) {
+			return symbol;
+		}
+
+		let symbol = Symbol::from_index(self.names.len());
+		let name: &'static str = Box::leak(name.into());
+		self.names.push(name);
+		self.symbols.insert(name, symbol);
+
+		symbol
+	}
+
+	pub fn name(&self, symbol: Symbol) -> &'static str {
+		self.names[symbol.index()]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn interns_each_name_once() {
+		let a = Symbol::intern("interned_name");
+		let b = Symbol::intern(&String::from("interned_name"));
+
+		assert_eq!(a, b);
+		assert_ne!(a, Symbol::intern("another_name"));
+		assert_eq!(a.as_str(), "interned_name");
+	}
+
+	#[test]
+	fn keywords_are_interned_first() {
+		for (index, keyword) in Keyword::ALL.iter().enumerate() {
+			let symbol = Symbol::intern(keyword.as_str());
+
+			assert_eq!(symbol.index(), index);
+			assert_eq!(symbol.keyword(), Some(*keyword));
+		}
+
+		assert_eq!(Symbol::intern("lets").keyword(), None);
+	}
+
+	#[test]
+	fn predefined_symbols_are_interned_next() {
+		for (index, name) in Symbol::PREDEFINED.iter().enumerate() {
+			assert_eq!(Symbol::intern(name).index(), Keyword::ALL.len() + index);
+		}
+
+		assert_eq!(Symbol::intern("self"), Symbol::SELF);
+		assert_eq!(Symbol::intern("bit_xor"), Symbol::BIT_XOR_METHOD);
+		assert_eq!(Symbol::SELF.keyword(), None);
+	}
+}
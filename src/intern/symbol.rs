@@ -0,0 +1,160 @@
+use std::{cmp::Ordering, fmt};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::Interner;
+use crate::lex::Keyword;
+
+/// An interned name, which is compared and hashed as a number rather than as
+/// the string it stands for
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+	pub fn intern(name: &str) -> Self {
+		Interner::with(|interner| interner.intern(name))
+	}
+
+	pub fn as_str(self) -> &'static str {
+		Interner::with(|interner| interner.name(self))
+	}
+
+	/// The keyword spelled by the symbol, if any
+	pub fn keyword(self) -> Option<Keyword> {
+		Keyword::ALL.get(self.index()).copied()
+	}
+
+	pub(super) fn from_index(index: usize) -> Self {
+		Self(
+			index
+				.try_into()
+				.expect("fewer than 2^32 names are interned"),
+		)
+	}
+
+	pub(super) fn index(self) -> usize {
+		self.0 as usize
+	}
+}
+
+/// Declares symbols for the names the compiler looks for itself, which are
+/// interned right after the keywords so that they can be compared without
+/// interning them again
+macro_rules! predefined {
+	($($(#[$doc:meta])* $constant:ident = $name:literal,)*) => {
+		#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+		enum Predefined {
+			$($constant,)*
+		}
+
+		impl Symbol {
+			$(
+				$(#[$doc])*
+				pub const $constant: Self = Self((Keyword::ALL.len() + Predefined::$constant as usize) as u32);
+			)*
+
+			/// The names of the predefined symbols, in the order they are interned
+			pub(super) const PREDEFINED: &'static [&'static str] = &[$($name,)*];
+		}
+	};
+}
+
+predefined! {
+	/// The wildcard pattern and placeholder
+	UNDERSCORE = "_",
+	/// The first parameter of every method, which is the value it is called on
+	SELF = "self",
+	/// The field a record iterated over by a `for` loop is called through to get
+	/// each item, until it returns `nil`
+	NEXT = "next",
+	ADD = "Add",
+	ADD_METHOD = "add",
+	SUBTRACT = "Subtract",
+	SUBTRACT_METHOD = "subtract",
+	MULTIPLY = "Multiply",
+	MULTIPLY_METHOD = "multiply",
+	DIVIDE = "Divide",
+	DIVIDE_METHOD = "divide",
+	MODULO = "Modulo",
+	MODULO_METHOD = "modulo",
+	POWER = "Power",
+	POWER_METHOD = "power",
+	BIT_AND = "BitAnd",
+	BIT_AND_METHOD = "bit_and",
+	BIT_OR = "BitOr",
+	BIT_OR_METHOD = "bit_or",
+	BIT_XOR = "BitXor",
+	BIT_XOR_METHOD = "bit_xor",
+	EQUAL = "Equal",
+	EQUALS_METHOD = "equals",
+	COMPARE = "Compare",
+	COMPARE_METHOD = "compare",
+	INDEX = "Index",
+	INDEX_METHOD = "index",
+}
+
+impl From<&str> for Symbol {
+	fn from(name: &str) -> Self {
+		Self::intern(name)
+	}
+}
+
+/// Compares the name, which locks the interner, so names that are compared
+/// often are better predefined
+impl PartialEq<str> for Symbol {
+	fn eq(&self, other: &str) -> bool {
+		self.as_str() == other
+	}
+}
+
+impl PartialEq<&str> for Symbol {
+	fn eq(&self, other: &&str) -> bool {
+		self.as_str() == *other
+	}
+}
+
+/// Shows the name rather than the number, so that trees read the same as
+/// before interning
+impl fmt::Debug for Symbol {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		fmt::Debug::fmt(self.as_str(), f)
+	}
+}
+
+/// Symbols are ordered by name, so that sorting them is alphabetical and the
+/// same from one session to the next. This locks the interner for each
+/// comparison, unlike equality and hashing.
+impl Ord for Symbol {
+	fn cmp(&self, other: &Self) -> Ordering {
+		if self == other {
+			return Ordering::Equal;
+		}
+
+		Interner::with(|interner| interner.name(*self).cmp(interner.name(*other)))
+	}
+}
+
+impl PartialOrd for Symbol {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl fmt::Display for Symbol {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+/// Written as the name, as the numbers differ from one session to the next
+impl Serialize for Symbol {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(self.as_str())
+	}
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		Ok(Self::intern(&String::deserialize(deserializer)?))
+	}
+}
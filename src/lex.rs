@@ -1,8 +1,10 @@
 mod buffered_char_reader;
+mod keyword;
 mod lexer;
 
 mod token;
 
 pub use buffered_char_reader::*;
+pub use keyword::*;
 pub use lexer::*;
 pub use token::*;
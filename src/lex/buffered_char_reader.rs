@@ -69,6 +69,9 @@ impl BufCharacterReader<'_> {
 				i += 1;
 			}
 
+			// Drop the placeholder slots that weren't filled before the end of input.
+			self.buffer.truncate(i);
+
 			// Update the buffer index
 			self.buf_index += i;
 		}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+/// Words reserved by the language, which can't be used as identifiers
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum Keyword {
+	Let,
+	If,
+	Else,
+	While,
+	True,
+	False,
+	Nil,
+	Typedef,
+	Enum,
+	Match,
+	Fn,
+	Mut,
+	For,
+	In,
+	Import,
+	Export,
+	Interface,
+	Impl,
+	Macro,
+	Const,
+}
+
+impl Keyword {
+	/// Every keyword, in declaration order
+	pub const ALL: &'static [Keyword] = &[
+		Self::Let,
+		Self::If,
+		Self::Else,
+		Self::While,
+		Self::True,
+		Self::False,
+		Self::Nil,
+		Self::Typedef,
+		Self::Enum,
+		Self::Match,
+		Self::Fn,
+		Self::Mut,
+		Self::For,
+		Self::In,
+		Self::Import,
+		Self::Export,
+		Self::Interface,
+		Self::Impl,
+		Self::Macro,
+		Self::Const,
+	];
+
+	/// The keyword as it is written in source
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::Let => "let",
+			Self::If => "if",
+			Self::Else => "else",
+			Self::While => "while",
+			Self::True => "true",
+			Self::False => "false",
+			Self::Nil => "nil",
+			Self::Typedef => "typedef",
+			Self::Enum => "enum",
+			Self::Match => "match",
+			Self::Fn => "fn",
+			Self::Mut => "mut",
+			Self::For => "for",
+			Self::In => "in",
+			Self::Import => "import",
+			Self::Export => "export",
+			Self::Interface => "interface",
+			Self::Impl => "impl",
+			Self::Macro => "macro",
+			Self::Const => "const",
+		}
+	}
+}
@@ -1,4 +1,5 @@
 use super::{BufCharacterReader, Token, TokenKind};
+use crate::intern::Symbol;
 
 pub struct Lexer<'a> {
 	reader: BufCharacterReader<'a>,
@@ -11,7 +12,7 @@ pub struct Lexer<'a> {
 /// Currently this only allows looking ahead one token
 impl<'a> Lexer<'a> {
 	/// Creates a new `Lexer` from given an object implementing [`std::io::Read`]
-	pub fn new(src: &'a mut dyn std::io::Read) -> Lexer {
+	pub fn new(src: &'a mut dyn std::io::Read) -> Lexer<'a> {
 		let reader = BufCharacterReader::new(src, 10);
 		Lexer {
 			reader,
@@ -29,27 +30,57 @@ impl<'a> Lexer<'a> {
 
 		loop {
 			let Some(ch) = self.reader.peek() else {
-				return self.return_end_of_input_token()
+				return self.return_end_of_input_token();
 			};
 
 			let token = match ch {
-				'=' => self.lex_symbol(TokenKind::Equals),
-				'+' => self.lex_symbol(TokenKind::Plus),
-				'-' => self.lex_symbol(TokenKind::Minus),
-				'*' => self.lex_symbol(TokenKind::Star),
-				'/' => self.lex_symbol(TokenKind::Slash),
-				'%' => self.lex_symbol(TokenKind::Percent),
-				'^' => self.lex_symbol(TokenKind::Caret),
-				'&' => self.lex_symbol(TokenKind::Ampersand),
+				'=' => self.lex_compound_symbol(
+					TokenKind::Equals,
+					&[('=', TokenKind::DoubleEquals), ('>', TokenKind::FatArrow)],
+				),
+				'+' => self.lex_compound_symbol(TokenKind::Plus, &[('=', TokenKind::PlusEquals)]),
+				'-' => self.lex_compound_symbol(
+					TokenKind::Minus,
+					&[('>', TokenKind::Arrow), ('=', TokenKind::MinusEquals)],
+				),
+				'*' => self.lex_compound_symbol(TokenKind::Star, &[('=', TokenKind::StarEquals)]),
+				'/' => self.lex_compound_symbol(TokenKind::Slash, &[('=', TokenKind::SlashEquals)]),
+				'%' => {
+					self.lex_compound_symbol(TokenKind::Percent, &[('=', TokenKind::PercentEquals)])
+				}
+				'^' => self.lex_compound_symbol(TokenKind::Caret, &[('=', TokenKind::CaretEquals)]),
+				'&' => self.lex_compound_symbol(
+					TokenKind::Ampersand,
+					&[
+						('&', TokenKind::DoubleAmpersand),
+						('=', TokenKind::AmpersandEquals),
+					],
+				),
+				'|' => self.lex_compound_symbol(
+					TokenKind::Bar,
+					&[('|', TokenKind::DoubleBar), ('=', TokenKind::BarEquals)],
+				),
+				'!' => self.lex_compound_symbol(TokenKind::Bang, &[('=', TokenKind::NotEquals)]),
+				'~' => self.lex_compound_symbol(TokenKind::Tilde, &[('=', TokenKind::TildeEquals)]),
 				';' => self.lex_symbol(TokenKind::Semicolon),
+				',' => self.lex_symbol(TokenKind::Comma),
 				'{' => self.lex_symbol(TokenKind::LBrace),
 				'}' => self.lex_symbol(TokenKind::RBrace),
 				'(' => self.lex_symbol(TokenKind::LParen),
 				')' => self.lex_symbol(TokenKind::RParen),
-				'<' => self.lex_symbol(TokenKind::LessThan),
+				'[' => self.lex_symbol(TokenKind::LBracket),
+				']' => self.lex_symbol(TokenKind::RBracket),
+				':' => self.lex_symbol(TokenKind::Colon),
+				'.' => self.lex_dots(),
+				'$' => self.lex_symbol(TokenKind::Dollar),
+				'@' => self.lex_symbol(TokenKind::At),
+				'#' => self.lex_symbol(TokenKind::Hash),
+				'<' => self
+					.lex_compound_symbol(TokenKind::LessThan, &[('=', TokenKind::LessThanEquals)]),
 				'>' => self.lex_greater_than_or_pipe(),
+				'?' => self.lex_question_or_try_pipe(),
 				'"' => self.lex_str(),
-				ch if ch.is_alphabetic() => self.lex_id(),
+				ch if ch.is_alphabetic() || *ch == '_' => self.lex_id(),
 				ch if ch.is_numeric() => self.lex_number(),
 				'\t' | ' ' | '\n' => {
 					self.advance();
@@ -78,21 +109,68 @@ impl<'a> Lexer<'a> {
 	}
 
 	fn lex_greater_than_or_pipe(&mut self) -> Token {
+		self.lex_compound_symbol(
+			TokenKind::GreaterThan,
+			&[('>', TokenKind::Pipe), ('=', TokenKind::GreaterThanEquals)],
+		)
+	}
+
+	/// Lexes `?`, or the `?>>` pipe which only forwards successful values
+	fn lex_question_or_try_pipe(&mut self) -> Token {
+		if !self.reader.peek_n(3).into_iter().eq(&['?', '>', '>']) {
+			return self.lex_symbol(TokenKind::Question);
+		}
+
 		self.advance();
-		if self.reader.peek() == Some(&'>') {
-			let start_col = self.col;
+		let start_col = self.col;
+		self.advance();
+		self.advance();
+
+		Token {
+			kind: TokenKind::TryPipe,
+			row: self.row,
+			col: start_col,
+		}
+	}
+
+	/// Lexes `.`, `..` or `..=`
+	fn lex_dots(&mut self) -> Token {
+		let token = self.lex_compound_symbol(TokenKind::Dot, &[('.', TokenKind::DotDot)]);
+
+		if token.kind == TokenKind::DotDot && self.reader.peek() == Some(&'=') {
 			self.advance();
-			Token {
-				kind: TokenKind::Pipe,
-				col: start_col,
-				row: self.row,
-			}
-		} else {
-			Token {
-				kind: TokenKind::GreaterThan,
-				row: self.row,
-				col: self.col,
+			return Token {
+				kind: TokenKind::DotDotEquals,
+				..token
+			};
+		}
+
+		token
+	}
+
+	/// Lexes a symbol which forms a different token when directly followed by
+	/// one of the characters in `compounds`, e.g. `<` and `<=`
+	fn lex_compound_symbol(&mut self, kind: TokenKind, compounds: &[(char, TokenKind)]) -> Token {
+		self.advance();
+		let start_col = self.col;
+
+		let compound = compounds
+			.iter()
+			.find(|(ch, _)| self.reader.peek() == Some(ch));
+
+		let kind = match compound {
+			Some((_, compound_kind)) => {
+				let compound_kind = compound_kind.clone();
+				self.advance();
+				compound_kind
 			}
+			None => kind,
+		};
+
+		Token {
+			kind,
+			row: self.row,
+			col: start_col,
 		}
 	}
 
@@ -110,7 +188,7 @@ impl<'a> Lexer<'a> {
 		let start_col = self.col;
 
 		while let Some(ch) = self.reader.peek() {
-			if ch.is_alphanumeric() {
+			if ch.is_alphanumeric() || *ch == '_' {
 				ident.push(*ch);
 				self.advance();
 			} else {
@@ -118,8 +196,14 @@ impl<'a> Lexer<'a> {
 			}
 		}
 
+		let symbol = Symbol::intern(&ident);
+		let kind = match symbol.keyword() {
+			Some(keyword) => TokenKind::Keyword(keyword),
+			None => TokenKind::Identifier(symbol),
+		};
+
 		Token {
-			kind: TokenKind::Identifier(ident),
+			kind,
 			row: self.row,
 			col: start_col,
 		}
@@ -208,6 +292,7 @@ impl<'a> Iterator for Lexer<'a> {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::lex::Keyword;
 
 	#[test]
 	fn number() {
@@ -232,7 +317,7 @@ mod tests {
 			lexer.collect::<Vec<_>>(),
 			vec![
 				Token {
-					kind: TokenKind::Identifier("test".to_string()),
+					kind: TokenKind::Identifier("test".into()),
 					row: 1,
 					col: 1
 				},
@@ -268,12 +353,12 @@ mod tests {
 			lexer.collect::<Vec<_>>(),
 			vec![
 				Token {
-					kind: TokenKind::Identifier("let".to_string()),
+					kind: TokenKind::Keyword(Keyword::Let),
 					row: 1,
 					col: 1
 				},
 				Token {
-					kind: TokenKind::Identifier("x".to_string()),
+					kind: TokenKind::Identifier("x".into()),
 					row: 1,
 					col: 5
 				},
@@ -299,7 +384,7 @@ mod tests {
 			lexer.collect::<Vec<_>>(),
 			vec![
 				Token {
-					kind: TokenKind::Identifier("test".to_string()),
+					kind: TokenKind::Identifier("test".into()),
 					row: 1,
 					col: 1
 				},
@@ -335,7 +420,7 @@ mod tests {
 			lexer.collect::<Vec<_>>(),
 			vec![
 				Token {
-					kind: TokenKind::Identifier("test".to_string()),
+					kind: TokenKind::Identifier("test".into()),
 					row: 1,
 					col: 1
 				},
@@ -386,7 +471,7 @@ mod tests {
 			lexer.collect::<Vec<_>>(),
 			vec![
 				Token {
-					kind: TokenKind::Identifier("test".to_string()),
+					kind: TokenKind::Identifier("test".into()),
 					row: 1,
 					col: 1
 				},
@@ -433,4 +518,241 @@ mod tests {
 			]
 		);
 	}
+
+	#[test]
+	fn lambda_test() {
+		let source = &mut "(a, b) => a".as_bytes();
+		let lexer = Lexer::new(source);
+		assert_eq!(
+			lexer.collect::<Vec<_>>(),
+			vec![
+				Token {
+					kind: TokenKind::LParen,
+					row: 1,
+					col: 1
+				},
+				Token {
+					kind: TokenKind::Identifier("a".into()),
+					row: 1,
+					col: 2
+				},
+				Token {
+					kind: TokenKind::Comma,
+					row: 1,
+					col: 3
+				},
+				Token {
+					kind: TokenKind::Identifier("b".into()),
+					row: 1,
+					col: 5
+				},
+				Token {
+					kind: TokenKind::RParen,
+					row: 1,
+					col: 6
+				},
+				Token {
+					kind: TokenKind::FatArrow,
+					row: 1,
+					col: 8
+				},
+				Token {
+					kind: TokenKind::Identifier("a".into()),
+					row: 1,
+					col: 11
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn compound_symbols() {
+		let source = &mut "== != <= >= && || < | &".as_bytes();
+		let lexer = Lexer::new(source);
+		assert_eq!(
+			lexer
+				.map(|token| (token.kind, token.col))
+				.collect::<Vec<_>>(),
+			vec![
+				(TokenKind::DoubleEquals, 1),
+				(TokenKind::NotEquals, 4),
+				(TokenKind::LessThanEquals, 7),
+				(TokenKind::GreaterThanEquals, 10),
+				(TokenKind::DoubleAmpersand, 13),
+				(TokenKind::DoubleBar, 16),
+				(TokenKind::LessThan, 19),
+				(TokenKind::Bar, 21),
+				(TokenKind::Ampersand, 23),
+			]
+		);
+	}
+
+	#[test]
+	fn keywords() {
+		let source = &mut "if truthy else true false nil match".as_bytes();
+		let lexer = Lexer::new(source);
+		assert_eq!(
+			lexer.map(|token| token.kind).collect::<Vec<_>>(),
+			vec![
+				TokenKind::Keyword(Keyword::If),
+				TokenKind::Identifier("truthy".into()),
+				TokenKind::Keyword(Keyword::Else),
+				TokenKind::Keyword(Keyword::True),
+				TokenKind::Keyword(Keyword::False),
+				TokenKind::Keyword(Keyword::Nil),
+				TokenKind::Keyword(Keyword::Match),
+			]
+		);
+	}
+
+	#[test]
+	fn identifiers_are_interned() {
+		let source = &mut "count + count".as_bytes();
+		let symbols = Lexer::new(source)
+			.filter_map(|token| match token.kind {
+				TokenKind::Identifier(symbol) => Some(symbol),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+
+		assert_eq!(symbols, vec![Symbol::intern("count"); 2]);
+		assert_eq!(symbols[0].as_str(), "count");
+	}
+
+	#[test]
+	fn underscores_in_identifiers() {
+		let source = &mut "_ _a b_2".as_bytes();
+		let lexer = Lexer::new(source);
+		assert_eq!(
+			lexer.map(|token| token.kind).collect::<Vec<_>>(),
+			vec![
+				TokenKind::Identifier("_".into()),
+				TokenKind::Identifier("_a".into()),
+				TokenKind::Identifier("b_2".into()),
+			]
+		);
+	}
+
+	#[test]
+	fn arrow() {
+		let source = &mut "a->b - c".as_bytes();
+		let lexer = Lexer::new(source);
+		assert_eq!(
+			lexer.map(|token| token.kind).collect::<Vec<_>>(),
+			vec![
+				TokenKind::Identifier("a".into()),
+				TokenKind::Arrow,
+				TokenKind::Identifier("b".into()),
+				TokenKind::Minus,
+				TokenKind::Identifier("c".into()),
+			]
+		);
+	}
+
+	#[test]
+	fn assignment_operators() {
+		let source = &mut "= += -= *= /= %= ^= &= |= ~= && ||".as_bytes();
+		let lexer = Lexer::new(source);
+		assert_eq!(
+			lexer.map(|token| token.kind).collect::<Vec<_>>(),
+			vec![
+				TokenKind::Equals,
+				TokenKind::PlusEquals,
+				TokenKind::MinusEquals,
+				TokenKind::StarEquals,
+				TokenKind::SlashEquals,
+				TokenKind::PercentEquals,
+				TokenKind::CaretEquals,
+				TokenKind::AmpersandEquals,
+				TokenKind::BarEquals,
+				TokenKind::TildeEquals,
+				TokenKind::DoubleAmpersand,
+				TokenKind::DoubleBar,
+			]
+		);
+	}
+
+	#[test]
+	fn dots() {
+		let source = &mut "a.b..c..=d".as_bytes();
+		let lexer = Lexer::new(source);
+		assert_eq!(
+			lexer
+				.map(|token| (token.kind, token.col))
+				.collect::<Vec<_>>(),
+			vec![
+				(TokenKind::Identifier("a".into()), 1),
+				(TokenKind::Dot, 2),
+				(TokenKind::Identifier("b".into()), 3),
+				(TokenKind::DotDot, 4),
+				(TokenKind::Identifier("c".into()), 6),
+				(TokenKind::DotDotEquals, 7),
+				(TokenKind::Identifier("d".into()), 10),
+			]
+		);
+	}
+
+	#[test]
+	fn try_pipe() {
+		let source = &mut "a? ?>> b ?> c?".as_bytes();
+		let lexer = Lexer::new(source);
+		assert_eq!(
+			lexer
+				.map(|token| (token.kind, token.col))
+				.collect::<Vec<_>>(),
+			vec![
+				(TokenKind::Identifier("a".into()), 1),
+				(TokenKind::Question, 2),
+				(TokenKind::TryPipe, 4),
+				(TokenKind::Identifier("b".into()), 8),
+				(TokenKind::Question, 10),
+				(TokenKind::GreaterThan, 11),
+				(TokenKind::Identifier("c".into()), 13),
+				(TokenKind::Question, 14),
+			]
+		);
+	}
+
+	#[test]
+	fn macro_tokens() {
+		let source = &mut "macro m { ($x:expr) => { $x } }".as_bytes();
+		let lexer = Lexer::new(source);
+		assert_eq!(
+			lexer.map(|token| token.kind).collect::<Vec<_>>(),
+			vec![
+				TokenKind::Keyword(Keyword::Macro),
+				TokenKind::Identifier("m".into()),
+				TokenKind::LBrace,
+				TokenKind::LParen,
+				TokenKind::Dollar,
+				TokenKind::Identifier("x".into()),
+				TokenKind::Colon,
+				TokenKind::Identifier("expr".into()),
+				TokenKind::RParen,
+				TokenKind::FatArrow,
+				TokenKind::LBrace,
+				TokenKind::Dollar,
+				TokenKind::Identifier("x".into()),
+				TokenKind::RBrace,
+				TokenKind::RBrace,
+			]
+		);
+	}
+
+	#[test]
+	fn attribute_tokens() {
+		let source = &mut "@a #[b]".as_bytes();
+		let lexer = Lexer::new(source);
+		assert_eq!(
+			lexer.map(|token| token.kind).collect::<Vec<_>>(),
+			vec![
+				TokenKind::At,
+				TokenKind::Identifier("a".into()),
+				TokenKind::Hash,
+				TokenKind::LBracket,
+				TokenKind::Identifier("b".into()),
+				TokenKind::RBracket,
+			]
+		);
+	}
 }
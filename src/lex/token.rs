@@ -1,8 +1,15 @@
 use std::fmt::Debug;
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+use serde::{Deserialize, Serialize};
+
+use super::Keyword;
+use crate::intern::Symbol;
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub enum TokenKind {
 	Equals,
+	DoubleEquals,
+	NotEquals,
 	Plus,
 	Minus,
 	Star,
@@ -10,21 +17,52 @@ pub enum TokenKind {
 	Percent,
 	Caret,
 	Ampersand,
-	Identifier(String),
+	DoubleAmpersand,
+	Bar,
+	DoubleBar,
+	Bang,
+	Tilde,
+	PlusEquals,
+	MinusEquals,
+	StarEquals,
+	SlashEquals,
+	PercentEquals,
+	CaretEquals,
+	AmpersandEquals,
+	BarEquals,
+	TildeEquals,
+	Identifier(Symbol),
+	Keyword(Keyword),
 	NumberLiteral(String),
 	StringLiteral(String),
 	End,
 	Semicolon,
+	Comma,
+	FatArrow,
+	Arrow,
 	LessThan,
+	LessThanEquals,
 	GreaterThan,
+	GreaterThanEquals,
 	Pipe,
+	TryPipe,
+	Question,
 	LBrace,
 	RBrace,
 	LParen,
 	RParen,
+	LBracket,
+	RBracket,
+	Colon,
+	Dot,
+	DotDot,
+	DotDotEquals,
+	Dollar,
+	At,
+	Hash,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Token {
 	pub kind: TokenKind,
 	pub row: usize,
@@ -0,0 +1,11 @@
+pub mod ast;
+pub mod check;
+pub mod dump;
+pub mod intern;
+pub mod lex;
+pub mod lower;
+pub mod module;
+pub mod parse;
+pub mod prelude;
+pub mod print;
+mod shared;
@@ -0,0 +1,5 @@
+mod error;
+mod lowerer;
+
+pub use error::*;
+pub use lowerer::*;
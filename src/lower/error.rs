@@ -0,0 +1,41 @@
+use crate::ast::{Backtrace, Span};
+use std::{error::Error, fmt};
+
+/// A `_` placeholder outside the arguments of a call on the right of a pipe
+#[derive(Debug, PartialEq)]
+pub struct StrayPlaceholderError {
+	pub span: Span,
+	pub backtrace: Backtrace,
+}
+
+impl Error for StrayPlaceholderError {}
+
+impl fmt::Display for StrayPlaceholderError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"`_` can only be used as an argument of a call on the right of `>>` at {}{}",
+			self.span, self.backtrace
+		)
+	}
+}
+
+/// A piped call with more than one `_` placeholder, which would need the piped
+/// value more than once
+#[derive(Debug, PartialEq)]
+pub struct MultiplePlaceholdersError {
+	pub span: Span,
+	pub backtrace: Backtrace,
+}
+
+impl Error for MultiplePlaceholdersError {}
+
+impl fmt::Display for MultiplePlaceholdersError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"A piped call can only have one `_` placeholder at {}{}",
+			self.span, self.backtrace
+		)
+	}
+}
@@ -0,0 +1,264 @@
+use super::{MultiplePlaceholdersError, StrayPlaceholderError};
+use crate::{
+	ast::{
+		walk_expr_mut, Arena, BinaryOp, Expansions, ExprId, ExprKind, MatchArm, PatternKind, Span,
+		VisitorMut,
+	},
+	parse::SourceFile,
+	shared::Result,
+};
+use std::error::Error;
+
+/// Names bound by lowered `?>>` pipes, which can't clash with the user's as they
+/// can't be written in source
+const PIPED: &str = "$piped";
+const FAILED: &str = "$failed";
+
+/// Rewrites a parsed file into the smaller language later passes work on,
+/// replacing pipes with the calls they stand for
+pub fn lower(mut file: SourceFile) -> Result<SourceFile> {
+	let mut lowerer = Lowerer {
+		arena: &mut file.arena,
+		expansions: &file.expansions,
+		error: None,
+	};
+
+	for &stmt in &file.stmts {
+		lowerer.visit_stmt_mut(stmt);
+	}
+
+	if let Some(error) = lowerer.error {
+		return Err(error);
+	}
+
+	Ok(file)
+}
+
+/// Rewrites pipes in place on the way down, so the calls they become are
+/// walked like any other
+struct Lowerer<'a> {
+	arena: &'a mut Arena,
+	/// The invocations the spans of errors may have been expanded through
+	expansions: &'a Expansions,
+	/// The first error found, after which the rest of the tree is skipped
+	error: Option<Box<dyn Error>>,
+}
+
+impl VisitorMut for Lowerer<'_> {
+	fn arena_mut(&mut self) -> &mut Arena {
+		self.arena
+	}
+
+	fn visit_expr_mut(&mut self, id: ExprId) {
+		if self.error.is_some() {
+			return;
+		}
+
+		let span = self.arena[id].span;
+		let lowered = match self.arena[id].kind {
+			ExprKind::Binary(BinaryOp::Pipe, input, target) => {
+				lower_pipe(self.arena, input, target, self.expansions)
+			}
+			ExprKind::Binary(BinaryOp::TryPipe, input, target) => {
+				Ok(lower_try_pipe(self.arena, input, target, span))
+			}
+			ExprKind::Placeholder => Err(StrayPlaceholderError {
+				span,
+				backtrace: self.expansions.backtrace(span),
+			}
+			.into()),
+			_ => {
+				walk_expr_mut(self, id);
+				return;
+			}
+		};
+
+		match lowered {
+			Ok(kind) => self.arena.update_expr(id, |old| *old = kind),
+			Err(error) => {
+				self.error = Some(error);
+				return;
+			}
+		}
+
+		walk_expr_mut(self, id);
+	}
+}
+
+/// Turns `input >> target` into a call of `target`, passing `input` in place of
+/// the `_` placeholder if `target` is a call with one, or as the first argument
+/// if it is a call without one
+fn lower_pipe(
+	arena: &Arena,
+	input: ExprId,
+	target: ExprId,
+	expansions: &Expansions,
+) -> Result<ExprKind> {
+	let ExprKind::FnInvoke(callee, args) = &arena[target].kind else {
+		return Ok(ExprKind::FnInvoke(target, vec![input]));
+	};
+
+	let placeholders = args
+		.iter()
+		.enumerate()
+		.filter(|&(_, &arg)| arena[arg].kind == ExprKind::Placeholder)
+		.map(|(i, _)| i)
+		.collect::<Vec<_>>();
+
+	if let Some(&second) = placeholders.get(1) {
+		let span = arena[args[second]].span;
+		return Err(MultiplePlaceholdersError {
+			span,
+			backtrace: expansions.backtrace(span),
+		}
+		.into());
+	}
+
+	let mut args = args.clone();
+
+	match placeholders.first() {
+		Some(&i) => args[i] = input,
+		None => args.insert(0, input),
+	}
+
+	Ok(ExprKind::FnInvoke(*callee, args))
+}
+
+/// Turns `input ?>> target` into
+/// `match input { Ok(piped) | Some(piped) => piped >> target, failed => failed }`,
+/// leaving the inner pipe to be lowered when the match is walked
+fn lower_try_pipe(arena: &mut Arena, input: ExprId, target: ExprId, span: Span) -> ExprKind {
+	let mut success = |variant: &str| {
+		let piped = arena.alloc_pattern(PatternKind::Binding(PIPED.into()), span);
+		let kind = PatternKind::Variant {
+			enum_name: None,
+			variant: variant.into(),
+			fields: vec![piped],
+		};
+		arena.alloc_pattern(kind, span)
+	};
+	let alternatives = vec![success("Ok"), success("Some")];
+
+	let piped = arena.alloc_expr(ExprKind::Reference(PIPED.into()), span);
+	let arms = vec![
+		MatchArm {
+			pattern: arena.alloc_pattern(PatternKind::Or(alternatives), span),
+			guard: None,
+			body: arena.alloc_expr(ExprKind::Binary(BinaryOp::Pipe, piped, target), span),
+		},
+		MatchArm {
+			pattern: arena.alloc_pattern(PatternKind::Binding(FAILED.into()), span),
+			guard: None,
+			body: arena.alloc_expr(ExprKind::Reference(FAILED.into()), span),
+		},
+	];
+
+	ExprKind::Match(input, arms)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		ast::{Backtrace, LetDecl, StmtKind},
+		parse::parse,
+	};
+
+	fn lower_source(source: &str) -> Result<SourceFile> {
+		lower(parse(&mut source.as_bytes())?)
+	}
+
+	#[test]
+	fn pipes_become_calls() {
+		let lowered = lower_source(
+			"let a = x >> f;
+			let b = x >> f(1, _) >> g(2);
+			let c = (x >> f)(y >> h(_));",
+		)
+		.unwrap();
+		let expected = parse(
+			&mut "let a = f(x);
+			let b = g(f(1, x), 2);
+			let c = (f(x))(h(y));"
+				.as_bytes(),
+		)
+		.unwrap();
+
+		assert_eq!(lowered, expected);
+	}
+
+	#[test]
+	fn placeholder_errors() {
+		let error = lower_source("let a = f(_);").unwrap_err();
+		assert_eq!(
+			error.downcast_ref::<StrayPlaceholderError>(),
+			Some(&StrayPlaceholderError {
+				span: Span::new(1, 11),
+				backtrace: Backtrace::default(),
+			})
+		);
+
+		let error = lower_source("let a = x >> f(_, g(_));").unwrap_err();
+		assert!(error.is::<StrayPlaceholderError>());
+
+		let error = lower_source("let a = x >> f(_, _);").unwrap_err();
+		assert_eq!(
+			error.to_string(),
+			"A piped call can only have one `_` placeholder at 1:19"
+		);
+	}
+
+	#[test]
+	fn try_pipes_become_matches() {
+		let lowered = lower_source("let a = x ?>> f(1, _);").unwrap();
+
+		let span = Span::default();
+		let mut arena = Arena::default();
+		let expr = |arena: &mut Arena, kind| arena.alloc_expr(kind, span);
+		let success = |arena: &mut Arena, variant: &str| {
+			let piped = arena.alloc_pattern(PatternKind::Binding(PIPED.into()), span);
+			let kind = PatternKind::Variant {
+				enum_name: None,
+				variant: variant.into(),
+				fields: vec![piped],
+			};
+			arena.alloc_pattern(kind, span)
+		};
+
+		let x = expr(&mut arena, ExprKind::Reference("x".into()));
+		let alternatives = vec![success(&mut arena, "Ok"), success(&mut arena, "Some")];
+		let f = expr(&mut arena, ExprKind::Reference("f".into()));
+		let args = vec![
+			expr(&mut arena, ExprKind::NumLit(1.0)),
+			expr(&mut arena, ExprKind::Reference(PIPED.into())),
+		];
+		let arms = vec![
+			MatchArm {
+				pattern: arena.alloc_pattern(PatternKind::Or(alternatives), span),
+				guard: None,
+				body: expr(&mut arena, ExprKind::FnInvoke(f, args)),
+			},
+			MatchArm {
+				pattern: arena.alloc_pattern(PatternKind::Binding(FAILED.into()), span),
+				guard: None,
+				body: expr(&mut arena, ExprKind::Reference(FAILED.into())),
+			},
+		];
+		let decl = LetDecl {
+			attributes: Vec::new(),
+			mutable: false,
+			pattern: arena.alloc_pattern(PatternKind::Binding("a".into()), span),
+			value: expr(&mut arena, ExprKind::Match(x, arms)),
+		};
+		let stmt = arena.alloc_stmt(StmtKind::LetDecl(Box::new(decl)), span);
+
+		assert_eq!(
+			lowered,
+			SourceFile {
+				arena,
+				stmts: vec![stmt],
+				..Default::default()
+			}
+		);
+	}
+}
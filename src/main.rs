@@ -1,13 +1,20 @@
-use std::fs::File;
+use std::{env, error::Error, path::PathBuf};
 
-mod ast;
-mod lex;
-mod parse;
-mod shared;
+use alkali::{check, module};
 
-fn main() -> shared::Result<()> {
-	let mut file = File::open("input.txt").unwrap();
-	let lexer = lex::Lexer::new(&mut file);
+fn main() -> Result<(), Box<dyn Error>> {
+	let path = env::args()
+		.nth(1)
+		.unwrap_or_else(|| "input.txt".to_string());
+	let program = module::load(&PathBuf::from(path))?;
+
+	println!("{:#?}", program.root().file);
+
+	for module in &program.modules {
+		for diagnostic in check::check_module(&program, module) {
+			eprintln!("{}: {diagnostic}", module.path.display());
+		}
+	}
 
 	Ok(())
 }
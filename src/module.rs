@@ -0,0 +1,5 @@
+mod error;
+mod loader;
+
+pub use error::*;
+pub use loader::*;
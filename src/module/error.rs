@@ -0,0 +1,99 @@
+use std::{error::Error, fmt, io, path::PathBuf};
+
+use crate::ast::{Backtrace, Span};
+
+/// A root module naming a file that can't be read
+#[derive(Debug)]
+pub struct ModuleNotFoundError {
+	pub path: PathBuf,
+	pub error: io::Error,
+}
+
+impl Error for ModuleNotFoundError {}
+
+impl fmt::Display for ModuleNotFoundError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"Cannot read module `{}`: {}",
+			self.path.display(),
+			self.error
+		)
+	}
+}
+
+/// An import naming a file that can't be read
+#[derive(Debug)]
+pub struct ImportNotFoundError {
+	/// The module the import is in
+	pub importer: PathBuf,
+	/// The file the import resolves to
+	pub path: PathBuf,
+	pub span: Span,
+	pub backtrace: Backtrace,
+	pub error: io::Error,
+}
+
+impl Error for ImportNotFoundError {}
+
+impl fmt::Display for ImportNotFoundError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"In module `{}`: Cannot read module `{}` imported at {}{}: {}",
+			self.importer.display(),
+			self.path.display(),
+			self.span,
+			self.backtrace,
+			self.error
+		)
+	}
+}
+
+/// A module that can be read but not parsed or lowered
+#[derive(Debug)]
+pub struct ModuleParseError {
+	pub path: PathBuf,
+	pub error: Box<dyn Error>,
+}
+
+impl Error for ModuleParseError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		Some(self.error.as_ref())
+	}
+}
+
+impl fmt::Display for ModuleParseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "In module `{}`: {}", self.path.display(), self.error)
+	}
+}
+
+/// Modules importing each other in a loop, which can't be loaded in any order
+#[derive(Debug, PartialEq)]
+pub struct ImportCycleError {
+	/// The modules in the cycle, in import order and starting and ending with the
+	/// same one
+	pub chain: Vec<PathBuf>,
+}
+
+impl Error for ImportCycleError {}
+
+impl fmt::Display for ImportCycleError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		// Paths are shown relative to the first module's directory to keep them short
+		let base = self.chain.first().and_then(|path| path.parent());
+		let chain = self
+			.chain
+			.iter()
+			.map(|path| {
+				base.and_then(|base| path.strip_prefix(base).ok())
+					.unwrap_or(path)
+					.display()
+					.to_string()
+			})
+			.collect::<Vec<_>>();
+
+		write!(f, "Import cycle: {}", chain.join(" -> "))
+	}
+}
@@ -0,0 +1,246 @@
+use std::{
+	collections::HashMap,
+	fs::File,
+	path::{Path, PathBuf},
+};
+
+use super::{ImportCycleError, ImportNotFoundError, ModuleNotFoundError, ModuleParseError};
+use crate::{
+	ast::StmtKind,
+	lower::lower,
+	parse::{parse, SourceFile},
+	shared::Result,
+};
+
+/// The extension given to import paths written without one
+pub const EXTENSION: &str = "alk";
+
+/// A lowered file, identified by its canonical path
+#[derive(Debug)]
+pub struct Module {
+	pub path: PathBuf,
+	pub file: SourceFile,
+	/// The canonical path each import path written in the file resolves to
+	pub imports: HashMap<String, PathBuf>,
+}
+
+/// A root module together with every module it imports, directly or not
+#[derive(Debug)]
+pub struct Program {
+	/// Every module after all of the modules it imports, ending with the root
+	pub modules: Vec<Module>,
+}
+
+impl Program {
+	pub fn module(&self, path: &Path) -> Option<&Module> {
+		self.modules.iter().find(|module| module.path == path)
+	}
+
+	pub fn root(&self) -> &Module {
+		self.modules
+			.last()
+			.expect("a program has at least its root module")
+	}
+}
+
+/// Loads the module at `root` and everything it imports
+pub fn load(root: &Path) -> Result<Program> {
+	let mut loader = Loader {
+		modules: Vec::new(),
+		loading: Vec::new(),
+	};
+
+	loader.load(canonicalize(root.to_path_buf())?)?;
+
+	Ok(Program {
+		modules: loader.modules,
+	})
+}
+
+/// Resolves an import path relative to the directory of the importing module
+pub fn resolve(importer: &Path, path: &str) -> PathBuf {
+	let mut resolved = importer.parent().unwrap_or(Path::new("")).join(path);
+
+	if resolved.extension().is_none() {
+		resolved.set_extension(EXTENSION);
+	}
+
+	resolved
+}
+
+fn canonicalize(path: PathBuf) -> Result<PathBuf> {
+	match path.canonicalize() {
+		Ok(canonical) => Ok(canonical),
+		Err(error) => Err(ModuleNotFoundError { path, error }.into()),
+	}
+}
+
+struct Loader {
+	modules: Vec<Module>,
+	/// The chain of imports leading to the module being loaded
+	loading: Vec<PathBuf>,
+}
+
+impl Loader {
+	fn load(&mut self, path: PathBuf) -> Result<()> {
+		if let Some(start) = self.loading.iter().position(|loading| *loading == path) {
+			let mut chain = self.loading[start..].to_vec();
+			chain.push(path);
+			return Err(ImportCycleError { chain }.into());
+		}
+
+		if self.modules.iter().any(|module| module.path == path) {
+			return Ok(());
+		}
+
+		let file = match File::open(&path) {
+			Ok(mut file) => match parse(&mut file).and_then(lower) {
+				Ok(file) => file,
+				Err(error) => return Err(ModuleParseError { path, error }.into()),
+			},
+			Err(error) => return Err(ModuleNotFoundError { path, error }.into()),
+		};
+
+		self.loading.push(path.clone());
+
+		let mut imports = HashMap::new();
+
+		for &stmt in &file.stmts {
+			let StmtKind::Import(import) = &file.arena[stmt].kind else {
+				continue;
+			};
+
+			let resolved = resolve(&path, &import.path);
+			let resolved = match resolved.canonicalize() {
+				Ok(canonical) => canonical,
+				Err(error) => {
+					let span = file.arena[stmt].span;
+					return Err(ImportNotFoundError {
+						importer: path,
+						path: resolved,
+						span,
+						backtrace: file.expansions.backtrace(span),
+						error,
+					}
+					.into());
+				}
+			};
+			self.load(resolved.clone())?;
+			imports.insert(import.path.clone(), resolved);
+		}
+
+		self.loading.pop();
+		self.modules.push(Module {
+			path,
+			file,
+			imports,
+		});
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::{env, fs};
+
+	/// Writes `files` into a new temporary directory, returning its path
+	fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+		let dir = env::temp_dir().join(format!("alkali-{name}-{}", std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+
+		for (path, source) in files {
+			let path = dir.join(path);
+			fs::create_dir_all(path.parent().unwrap()).unwrap();
+			fs::write(path, source).unwrap();
+		}
+
+		dir.canonicalize().unwrap()
+	}
+
+	#[test]
+	fn loads_imports_first() {
+		let dir = write_files(
+			"imports",
+			&[
+				(
+					"main.alk",
+					"import \"lib/a\" as a; import { y } from \"b.alk\";",
+				),
+				("lib/a.alk", "import { y } from \"../b\"; export let x = y;"),
+				("b.alk", "export let y = 1;"),
+			],
+		);
+
+		let program = load(&dir.join("main.alk")).unwrap();
+
+		assert_eq!(
+			program
+				.modules
+				.iter()
+				.map(|module| module.path.strip_prefix(&dir).unwrap())
+				.collect::<Vec<_>>(),
+			vec![
+				Path::new("b.alk"),
+				Path::new("lib/a.alk"),
+				Path::new("main.alk")
+			]
+		);
+		assert_eq!(program.root().imports["lib/a"], dir.join("lib/a.alk"));
+		assert_eq!(program.root().imports["b.alk"], dir.join("b.alk"));
+	}
+
+	#[test]
+	fn import_cycle() {
+		let dir = write_files(
+			"cycle",
+			&[
+				("main.alk", "import \"a\" as a;"),
+				("a.alk", "import \"b\" as b;"),
+				("b.alk", "import \"a\" as a;"),
+			],
+		);
+
+		let error = load(&dir.join("main.alk")).unwrap_err();
+
+		assert_eq!(error.to_string(), "Import cycle: a.alk -> b.alk -> a.alk");
+	}
+
+	#[test]
+	fn missing_module() {
+		let dir = write_files("missing", &[("main.alk", "import \"nowhere\" as n;")]);
+
+		let error = load(&dir.join("main.alk")).unwrap_err();
+
+		assert!(error.is::<ImportNotFoundError>());
+		assert!(error.to_string().starts_with(&format!(
+			"In module `{}`: Cannot read module `{}` imported at 1:1",
+			dir.join("main.alk").display(),
+			dir.join("nowhere.alk").display()
+		)));
+
+		let error = load(&dir.join("other.alk")).unwrap_err();
+
+		assert!(error.is::<ModuleNotFoundError>());
+	}
+
+	#[test]
+	fn parse_error_names_module() {
+		let dir = write_files(
+			"parse-error",
+			&[("main.alk", "import \"a\" as a;"), ("a.alk", "let = 1;")],
+		);
+
+		let error = load(&dir.join("main.alk")).unwrap_err();
+
+		assert!(error.is::<ModuleParseError>());
+		assert_eq!(
+			error.to_string(),
+			format!(
+				"In module `{}`: Expected pattern, found Equals at 1:5",
+				dir.join("a.alk").display()
+			)
+		);
+	}
+}
@@ -1,5 +1,7 @@
 mod error;
+mod macros;
 mod parser;
 
 pub use error::*;
+pub use macros::*;
 pub use parser::*;
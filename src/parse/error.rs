@@ -1,14 +1,17 @@
-use crate::lex::TokenKind;
+use crate::{
+	ast::{Backtrace, BinaryOp, Span},
+	lex::{Token, TokenKind},
+};
 use std::{error::Error, fmt};
 
 #[derive(Debug, PartialEq)]
 pub struct ExpectedTokenError {
 	pub expected: TokenKind,
-	pub found: TokenKind,
+	pub found: Token,
 }
 
 impl ExpectedTokenError {
-	pub fn new(expected: TokenKind, found: TokenKind) -> Self {
+	pub fn new(expected: TokenKind, found: Token) -> Self {
 		Self { expected, found }
 	}
 }
@@ -19,8 +22,240 @@ impl fmt::Display for ExpectedTokenError {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		write!(
 			f,
-			"Expected token {:?}, found {:?}",
-			self.expected, self.found
+			"Expected token {:?}, found {:?} at {}:{}",
+			self.expected, self.found.kind, self.found.row, self.found.col
+		)
+	}
+}
+
+/// A token that can't start or continue the construct being parsed.
+#[derive(Debug, PartialEq)]
+pub struct UnexpectedTokenError {
+	/// A short description of what was expected, e.g. "expression".
+	pub expected: &'static str,
+	pub found: Token,
+}
+
+impl UnexpectedTokenError {
+	pub fn new(expected: &'static str, found: Token) -> Self {
+		Self { expected, found }
+	}
+}
+
+impl Error for UnexpectedTokenError {}
+
+impl fmt::Display for UnexpectedTokenError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"Expected {}, found {:?} at {}:{}",
+			self.expected, self.found.kind, self.found.row, self.found.col
+		)
+	}
+}
+
+/// A comparison used as the operand of another, like `a < b < c`, which would
+/// otherwise silently compare a boolean against `c`.
+#[derive(Debug, PartialEq)]
+pub struct ChainedComparisonError {
+	pub first: BinaryOp,
+	pub second: BinaryOp,
+	pub span: Span,
+	pub backtrace: Backtrace,
+}
+
+impl Error for ChainedComparisonError {}
+
+impl fmt::Display for ChainedComparisonError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let (first, second) = (self.first.symbol(), self.second.symbol());
+		write!(
+			f,
+			"Comparison operators cannot be chained at {}{}, write `a {first} b && b {second} c` or add parentheses instead of `a {first} b {second} c`",
+			self.span, self.backtrace
+		)
+	}
+}
+
+/// The left side of an assignment that isn't a variable, field or list
+/// element, like `f() = 1`.
+#[derive(Debug, PartialEq)]
+pub struct InvalidAssignTargetError {
+	pub span: Span,
+	pub backtrace: Backtrace,
+}
+
+impl Error for InvalidAssignTargetError {}
+
+impl fmt::Display for InvalidAssignTargetError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"Expected a variable, field or list element to assign to at {}{}",
+			self.span, self.backtrace
+		)
+	}
+}
+
+/// A second `..` in a list pattern, which would leave it ambiguous how many
+/// elements each one matches.
+#[derive(Debug, PartialEq)]
+pub struct MultipleRestsError {
+	pub span: Span,
+	pub backtrace: Backtrace,
+}
+
+impl Error for MultipleRestsError {}
+
+impl fmt::Display for MultipleRestsError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"Only one `..` is allowed in a list pattern at {}{}",
+			self.span, self.backtrace
+		)
+	}
+}
+
+/// A number literal that isn't a valid number, which the lexer lets through as
+/// it accepts any numeric character, like `½`.
+#[derive(Debug, PartialEq)]
+pub struct InvalidNumberError {
+	pub literal: String,
+	pub span: Span,
+	pub backtrace: Backtrace,
+}
+
+impl Error for InvalidNumberError {}
+
+impl fmt::Display for InvalidNumberError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"Invalid number literal `{}` at {}{}",
+			self.literal, self.span, self.backtrace
+		)
+	}
+}
+
+/// An invocation `name!(...)` of a macro that isn't defined above it.
+#[derive(Debug, PartialEq)]
+pub struct UnknownMacroError {
+	pub name: String,
+	pub span: Span,
+	pub backtrace: Backtrace,
+}
+
+impl Error for UnknownMacroError {}
+
+impl fmt::Display for UnknownMacroError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"Unknown macro `{}!` at {}{}",
+			self.name, self.span, self.backtrace
+		)
+	}
+}
+
+/// An invocation whose arguments match the pattern of none of the macro's rules.
+#[derive(Debug, PartialEq)]
+pub struct NoMatchingRuleError {
+	pub name: String,
+	pub span: Span,
+	pub backtrace: Backtrace,
+}
+
+impl Error for NoMatchingRuleError {}
+
+impl fmt::Display for NoMatchingRuleError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"No rule of `{}!` matches its arguments at {}{}",
+			self.name, self.span, self.backtrace
+		)
+	}
+}
+
+/// Invocations nested too deep, which is almost always a macro that expands
+/// to an invocation of itself without ever stopping.
+#[derive(Debug, PartialEq)]
+pub struct MacroRecursionError {
+	pub name: String,
+	pub limit: usize,
+	pub span: Span,
+	pub backtrace: Backtrace,
+}
+
+impl Error for MacroRecursionError {}
+
+impl fmt::Display for MacroRecursionError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"Expanding `{}!` nests more than {} invocations at {}{}",
+			self.name, self.limit, self.span, self.backtrace
+		)
+	}
+}
+
+/// A `$name` in the body of a rule that its pattern doesn't capture.
+#[derive(Debug, PartialEq)]
+pub struct UnboundMetavariableError {
+	pub name: String,
+	pub span: Span,
+}
+
+impl Error for UnboundMetavariableError {}
+
+impl fmt::Display for UnboundMetavariableError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"`${}` is not captured by the pattern of its rule at {}",
+			self.name, self.span
+		)
+	}
+}
+
+/// A `$( ... )` in the body of a rule that doesn't repeat as the pattern
+/// captured it, either because no variable in it was captured repeatedly, or
+/// two were captured a different number of times, or a repeated variable is
+/// used outside of it.
+#[derive(Debug, PartialEq)]
+pub struct MacroRepetitionError {
+	pub span: Span,
+}
+
+impl Error for MacroRepetitionError {}
+
+impl fmt::Display for MacroRepetitionError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"Variables repeat a different number of times than the `$( ... )` they are used in at {}",
+			self.span
+		)
+	}
+}
+
+/// An attribute before something other than a `let`, typedef, enum or function.
+#[derive(Debug, PartialEq)]
+pub struct MisplacedAttributeError {
+	pub name: String,
+	pub span: Span,
+	pub backtrace: Backtrace,
+}
+
+impl Error for MisplacedAttributeError {}
+
+impl fmt::Display for MisplacedAttributeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"Attribute `{}` is only allowed on a `let`, typedef, enum or function at {}{}",
+			self.name, self.span, self.backtrace
 		)
 	}
 }
@@ -0,0 +1,950 @@
+use std::{collections::HashMap, ops::Range};
+
+use super::{MacroRepetitionError, UnboundMetavariableError, UnexpectedTokenError};
+use crate::{
+	ast::{ExpansionId, Span},
+	intern::Symbol,
+	lex::{Keyword, Token, TokenKind},
+	shared::Result,
+};
+
+/// A token along with its span, which for a token expanded from a macro points
+/// into the macro's definition
+pub type SpannedToken = (Token, Span);
+
+/// A token, or a sequence of them between matching delimiters
+#[derive(Debug, Clone)]
+pub enum TokenTree {
+	Token(SpannedToken),
+	Group {
+		open: SpannedToken,
+		trees: Vec<TokenTree>,
+		close: SpannedToken,
+	},
+}
+
+impl TokenTree {
+	fn kind(&self) -> &TokenKind {
+		match self {
+			Self::Token((token, _))
+			| Self::Group {
+				open: (token, _), ..
+			} => &token.kind,
+		}
+	}
+
+	/// Appends the tokens of the tree in source order
+	fn flatten_into(&self, tokens: &mut Vec<SpannedToken>) {
+		match self {
+			Self::Token(token) => tokens.push(token.clone()),
+			Self::Group { open, trees, close } => {
+				tokens.push(open.clone());
+
+				for tree in trees {
+					tree.flatten_into(tokens);
+				}
+
+				tokens.push(close.clone());
+			}
+		}
+	}
+}
+
+/// The delimiter closing a group opened by `open`, if it opens one
+pub fn closing_delimiter(open: &TokenKind) -> Option<TokenKind> {
+	match open {
+		TokenKind::LParen => Some(TokenKind::RParen),
+		TokenKind::LBracket => Some(TokenKind::RBracket),
+		TokenKind::LBrace => Some(TokenKind::RBrace),
+		_ => None,
+	}
+}
+
+/// A declarative macro, `macro name { (pattern) => { body } ... }`, invoked as
+/// `name!(...)` with any delimiters.
+///
+/// An invocation expands to the body of the first rule whose pattern matches
+/// its arguments, which is then parsed in place of the invocation.
+#[derive(Debug)]
+pub struct Macro {
+	pub name: Symbol,
+	pub rules: Vec<Rule>,
+}
+
+#[derive(Debug)]
+pub struct Rule {
+	pattern: Vec<Matcher>,
+	body: Vec<Transcriber>,
+}
+
+/// What a `$name:kind` variable in a pattern captures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FragmentKind {
+	/// Every token tree up to the one the pattern expects next, substituted in
+	/// parentheses so that it stays one operand
+	Expr,
+	Ident,
+	/// A single token tree
+	Tt,
+}
+
+#[derive(Debug)]
+enum Matcher {
+	Token(TokenKind),
+	Group(TokenKind, Vec<Matcher>),
+	Fragment(Symbol, FragmentKind),
+	/// `$( ... ) sep *`, or `+` for at least once, with an optional separator
+	Repeat {
+		matchers: Vec<Matcher>,
+		separator: Option<TokenKind>,
+		at_least_once: bool,
+	},
+}
+
+#[derive(Debug)]
+enum Transcriber {
+	Token(SpannedToken),
+	Group {
+		open: SpannedToken,
+		body: Vec<Transcriber>,
+		close: SpannedToken,
+	},
+	Variable(Symbol, Span),
+	Repeat {
+		body: Vec<Transcriber>,
+		separator: Option<SpannedToken>,
+		span: Span,
+	},
+}
+
+/// What a variable captured, nested once for each `$( ... )` it is in
+#[derive(Debug, Clone)]
+enum Capture {
+	Fragment(FragmentKind, Vec<TokenTree>),
+	Repeated(Vec<Capture>),
+}
+
+type Captures = HashMap<Symbol, Capture>;
+
+impl Macro {
+	/// Expands an invocation whose arguments are `args`, giving the tokens of the
+	/// body of the first rule that matches them, or nothing if none does.
+	///
+	/// Tokens from the body get spans pointing to `expansion`, while those from
+	/// the arguments keep their own.
+	pub fn expand(
+		&self,
+		args: &[TokenTree],
+		expansion: ExpansionId,
+	) -> Result<Option<Vec<SpannedToken>>> {
+		for rule in &self.rules {
+			let mut captures = Captures::new();
+
+			if match_trees(&rule.pattern, args, None, &mut captures) != Some(args.len()) {
+				continue;
+			}
+
+			let mut tokens = Vec::new();
+			transcribe(
+				&rule.body,
+				&captures,
+				&mut Vec::new(),
+				expansion,
+				&mut tokens,
+			)?;
+
+			return Ok(Some(rename_binders(tokens, expansion)));
+		}
+
+		Ok(None)
+	}
+}
+
+impl Rule {
+	/// Builds a rule from the trees inside the delimiters of its pattern and
+	/// body, which are closed by `pattern_close` and `body_close`
+	pub fn new(
+		pattern: &[TokenTree],
+		pattern_close: &Token,
+		body: &[TokenTree],
+		body_close: &Token,
+	) -> Result<Self> {
+		let pattern = parse_matchers(pattern, pattern_close)?;
+		let body = parse_transcribers(body, body_close)?;
+
+		let mut captured = Vec::new();
+		matcher_variables(&pattern, &mut captured);
+		check_bound(&body, &captured)?;
+
+		Ok(Self { pattern, body })
+	}
+}
+
+/// Parses the pattern of a rule
+fn parse_matchers(trees: &[TokenTree], close: &Token) -> Result<Vec<Matcher>> {
+	let mut matchers = Vec::new();
+	let mut trees = trees.iter();
+
+	while let Some(tree) = trees.next() {
+		let matcher = match tree {
+			TokenTree::Token((token, _)) if token.kind == TokenKind::Dollar => match trees.next() {
+				Some(TokenTree::Token((token, _))) => {
+					let TokenKind::Identifier(name) = &token.kind else {
+						return Err(
+							UnexpectedTokenError::new("macro variable", token.clone()).into()
+						);
+					};
+
+					match trees.next() {
+						Some(TokenTree::Token((token, _))) if token.kind == TokenKind::Colon => {}
+						other => return Err(unexpected_tree("`:`", other, close)),
+					}
+
+					let kind = match trees.next() {
+						Some(TokenTree::Token((token, _))) => match &token.kind {
+							TokenKind::Identifier(kind) if kind == "expr" => FragmentKind::Expr,
+							TokenKind::Identifier(kind) if kind == "ident" => FragmentKind::Ident,
+							TokenKind::Identifier(kind) if kind == "tt" => FragmentKind::Tt,
+							_ => {
+								return Err(UnexpectedTokenError::new(
+									"fragment kind",
+									token.clone(),
+								)
+								.into());
+							}
+						},
+						other => return Err(unexpected_tree("fragment kind", other, close)),
+					};
+
+					Matcher::Fragment(*name, kind)
+				}
+				Some(TokenTree::Group {
+					open: (open, _),
+					trees: inner,
+					close: (inner_close, _),
+				}) if open.kind == TokenKind::LParen => {
+					let (separator, at_least_once) = parse_repetition(&mut trees, close)?;
+
+					Matcher::Repeat {
+						matchers: parse_matchers(inner, inner_close)?,
+						separator: separator.map(|(token, _)| token.kind),
+						at_least_once,
+					}
+				}
+				other => return Err(unexpected_tree("macro variable", other, close)),
+			},
+			TokenTree::Token((token, _)) => Matcher::Token(token.kind.clone()),
+			TokenTree::Group {
+				open: (open, _),
+				trees,
+				close: (inner_close, _),
+			} => Matcher::Group(open.kind.clone(), parse_matchers(trees, inner_close)?),
+		};
+
+		matchers.push(matcher);
+	}
+
+	Ok(matchers)
+}
+
+/// Parses the body of a rule
+fn parse_transcribers(trees: &[TokenTree], close: &Token) -> Result<Vec<Transcriber>> {
+	let mut transcribers = Vec::new();
+	let mut trees = trees.iter();
+
+	while let Some(tree) = trees.next() {
+		let transcriber = match tree {
+			TokenTree::Token((token, span)) if token.kind == TokenKind::Dollar => {
+				match trees.next() {
+					Some(TokenTree::Token((token, _))) => {
+						let TokenKind::Identifier(name) = &token.kind else {
+							return Err(
+								UnexpectedTokenError::new("macro variable", token.clone()).into()
+							);
+						};
+
+						Transcriber::Variable(*name, *span)
+					}
+					Some(TokenTree::Group {
+						open: (open, _),
+						trees: inner,
+						close: (inner_close, _),
+					}) if open.kind == TokenKind::LParen => {
+						let (separator, _) = parse_repetition(&mut trees, close)?;
+
+						Transcriber::Repeat {
+							body: parse_transcribers(inner, inner_close)?,
+							separator,
+							span: *span,
+						}
+					}
+					other => return Err(unexpected_tree("macro variable", other, close)),
+				}
+			}
+			TokenTree::Token(token) => Transcriber::Token(token.clone()),
+			TokenTree::Group {
+				open,
+				trees,
+				close: inner_close,
+			} => Transcriber::Group {
+				open: open.clone(),
+				body: parse_transcribers(trees, &inner_close.0)?,
+				close: inner_close.clone(),
+			},
+		};
+
+		transcribers.push(transcriber);
+	}
+
+	Ok(transcribers)
+}
+
+/// Parses what follows the `$( ... )` of a repetition, an optional separator
+/// and then `*` or `+`, returning the separator and whether it was `+`
+fn parse_repetition<'t>(
+	trees: &mut impl Iterator<Item = &'t TokenTree>,
+	close: &Token,
+) -> Result<(Option<SpannedToken>, bool)> {
+	let mut separator = None;
+
+	loop {
+		match trees.next() {
+			Some(TokenTree::Token((token, _))) if token.kind == TokenKind::Star => {
+				return Ok((separator, false));
+			}
+			Some(TokenTree::Token((token, _))) if token.kind == TokenKind::Plus => {
+				return Ok((separator, true));
+			}
+			Some(TokenTree::Token(token)) if separator.is_none() => separator = Some(token.clone()),
+			other => return Err(unexpected_tree("`*` or `+`", other, close)),
+		}
+	}
+}
+
+/// Errors on the tree found where another was expected, or on the delimiter
+/// `close` when the trees ran out first
+fn unexpected_tree(
+	expected: &'static str,
+	found: Option<&TokenTree>,
+	close: &Token,
+) -> Box<dyn std::error::Error> {
+	let found = match found {
+		Some(
+			TokenTree::Token((token, _))
+			| TokenTree::Group {
+				open: (token, _), ..
+			},
+		) => token,
+		None => close,
+	};
+
+	UnexpectedTokenError::new(expected, found.clone()).into()
+}
+
+/// Collects the names of the variables a pattern captures
+fn matcher_variables(matchers: &[Matcher], names: &mut Vec<Symbol>) {
+	for matcher in matchers {
+		match matcher {
+			Matcher::Token(_) => {}
+			Matcher::Group(_, inner)
+			| Matcher::Repeat {
+				matchers: inner, ..
+			} => {
+				matcher_variables(inner, names);
+			}
+			Matcher::Fragment(name, _) => names.push(*name),
+		}
+	}
+}
+
+/// Collects the names of the variables a body uses
+fn transcriber_variables(body: &[Transcriber], names: &mut Vec<Symbol>) {
+	for transcriber in body {
+		match transcriber {
+			Transcriber::Token(_) => {}
+			Transcriber::Group { body, .. } | Transcriber::Repeat { body, .. } => {
+				transcriber_variables(body, names);
+			}
+			Transcriber::Variable(name, _) => names.push(*name),
+		}
+	}
+}
+
+/// Checks that every variable of a body is captured by its pattern
+fn check_bound(body: &[Transcriber], captured: &[Symbol]) -> Result<()> {
+	for transcriber in body {
+		match transcriber {
+			Transcriber::Token(_) => {}
+			Transcriber::Group { body, .. } | Transcriber::Repeat { body, .. } => {
+				check_bound(body, captured)?;
+			}
+			Transcriber::Variable(name, span) if !captured.contains(name) => {
+				return Err(UnboundMetavariableError {
+					name: name.to_string(),
+					span: *span,
+				}
+				.into());
+			}
+			Transcriber::Variable(..) => {}
+		}
+	}
+
+	Ok(())
+}
+
+/// A name bound by an expansion, at its index among the expanded tokens
+struct Binder {
+	index: usize,
+	/// The tokens in which the name refers to this binding
+	scope: Range<usize>,
+	/// Whether it's a field of a record pattern bound to a variable of the same
+	/// name, like `{ x }`, which has to be spelled out as `{ x: x }` to rename it
+	shorthand: bool,
+}
+
+/// Renames the names the body of an expansion binds, along with the uses of
+/// them in the body within the scope of the binding, to names the invoker can't
+/// write as `$` isn't part of names. Tokens from the arguments are left alone,
+/// so that the expansion can neither capture nor shadow the invoker's bindings.
+///
+/// Field names, which follow a `.` or precede a `:`, and macro names, which
+/// precede a `!`, are never renamed.
+fn rename_binders(tokens: Vec<SpannedToken>, expansion: ExpansionId) -> Vec<SpannedToken> {
+	let from_body = |(_, span): &SpannedToken| span.expansion == Some(expansion);
+	let name_at = |index: usize| match tokens[index].0.kind {
+		TokenKind::Identifier(name) => name,
+		_ => unreachable!("binders are identifiers"),
+	};
+
+	let binders = find_binders(&tokens)
+		.into_iter()
+		.filter(|binder| from_body(&tokens[binder.index]))
+		.collect::<Vec<_>>();
+
+	let mut renamed = Vec::with_capacity(tokens.len());
+
+	for (i, (token, span)) in tokens.iter().enumerate() {
+		let kind_at =
+			|index: Option<usize>| index.and_then(|index| tokens.get(index)).map(|t| &t.0.kind);
+		let binder = binders.iter().find(|binder| binder.index == i);
+
+		let rename = match &token.kind {
+			TokenKind::Identifier(name) if from_body(&tokens[i]) => {
+				binder.is_some()
+					|| binders
+						.iter()
+						.any(|binder| binder.scope.contains(&i) && name_at(binder.index) == *name)
+						&& !matches!(
+							(kind_at(i.checked_sub(1)), kind_at(Some(i + 1))),
+							(Some(TokenKind::Dot), _)
+								| (_, Some(TokenKind::Colon | TokenKind::Bang))
+						)
+			}
+			_ => false,
+		};
+
+		if !rename {
+			renamed.push((token.clone(), *span));
+			continue;
+		}
+
+		if binder.is_some_and(|binder| binder.shorthand) {
+			renamed.push((token.clone(), *span));
+			renamed.push((
+				Token {
+					kind: TokenKind::Colon,
+					..token.clone()
+				},
+				*span,
+			));
+		}
+
+		let name = Symbol::intern(&format!("{}${}", name_at(i), expansion.0));
+		renamed.push((
+			Token {
+				kind: TokenKind::Identifier(name),
+				..token.clone()
+			},
+			*span,
+		));
+	}
+
+	renamed
+}
+
+/// Finds the names bound by expanded tokens: those in the patterns of `let`,
+/// `for` and match arms, the names and parameters of functions, the parameters
+/// of lambdas, and the names of constants
+fn find_binders(tokens: &[SpannedToken]) -> Vec<Binder> {
+	let kind = |index: usize| tokens.get(index).map(|(token, _)| &token.kind);
+	let keyword =
+		|index: usize, keyword: Keyword| kind(index) == Some(&TokenKind::Keyword(keyword));
+	let mut binders = Vec::new();
+
+	for (i, (token, _)) in tokens.iter().enumerate() {
+		match token.kind {
+			// The names are bound from the end of the statement, as the value can
+			// still refer to what they shadow
+			TokenKind::Keyword(Keyword::Let) => {
+				let start = if keyword(i + 1, Keyword::Mut) {
+					i + 2
+				} else {
+					i + 1
+				};
+				let end = find_at_depth(tokens, start, |kind| *kind == TokenKind::Equals);
+				let scope = statement_end(tokens, end)..enclosing_group(tokens, i).end;
+				pattern_binders(tokens, start..end, scope, &mut binders);
+			}
+			TokenKind::Keyword(Keyword::For) => {
+				let end = find_at_depth(tokens, i + 1, |kind| {
+					*kind == TokenKind::Keyword(Keyword::In)
+				});
+				let body = find_at_depth(tokens, end, |kind| *kind == TokenKind::LBrace);
+				let scope = body..matching_close(tokens, body);
+				pattern_binders(tokens, i + 1..end, scope, &mut binders);
+			}
+			TokenKind::Keyword(Keyword::Const) if !keyword(i + 1, Keyword::Fn) => {
+				let scope = statement_end(tokens, i)..enclosing_group(tokens, i).end;
+				name_binder(tokens, i + 1, scope, &mut binders);
+			}
+			TokenKind::Keyword(Keyword::Match) => {
+				let open = find_at_depth(tokens, i + 1, |kind| *kind == TokenKind::LBrace);
+				arm_binders(tokens, open, &mut binders);
+			}
+			// A function can be called anywhere in the block it's declared in, while
+			// its parameters, each the first name before its type or default value,
+			// are bound in its signature and body
+			TokenKind::Keyword(Keyword::Fn) => {
+				if !is_method(tokens, i) {
+					name_binder(tokens, i + 1, enclosing_group(tokens, i), &mut binders);
+				}
+
+				let open = find_at_depth(tokens, i + 1, |kind| *kind == TokenKind::LParen);
+				let close = matching_close(tokens, open);
+				// The method of an interface may have no body, ending at a `;`
+				let body = find_at_depth(tokens, close + 1, |kind| {
+					matches!(kind, TokenKind::LBrace | TokenKind::Semicolon)
+				});
+				let end = match kind(body) {
+					Some(TokenKind::LBrace) => matching_close(tokens, body),
+					_ => body,
+				};
+				let mut param_start = open + 1;
+
+				while param_start < close {
+					name_binder(tokens, param_start, open..end, &mut binders);
+					param_start =
+						find_at_depth(tokens, param_start, |kind| *kind == TokenKind::Comma) + 1;
+				}
+			}
+			// Lambda parameters, bound in the block after the `=>`
+			TokenKind::LParen => {
+				let close = matching_close(tokens, i);
+
+				if kind(close + 1) == Some(&TokenKind::FatArrow) {
+					let scope = i..matching_close(tokens, close + 2);
+					pattern_binders(tokens, i + 1..close, scope, &mut binders);
+				}
+			}
+			_ => {}
+		}
+	}
+
+	binders
+}
+
+/// Finds the binders of the arms of a match, whose braces open at `open`
+fn arm_binders(tokens: &[SpannedToken], open: usize, binders: &mut Vec<Binder>) {
+	let close = matching_close(tokens, open).min(tokens.len());
+	let mut arm = open + 1;
+
+	while arm < close {
+		let end = find_at_depth(tokens, arm, |kind| {
+			matches!(kind, TokenKind::FatArrow | TokenKind::Keyword(Keyword::If))
+		});
+
+		// A body in braces ends the arm, any other ends at a comma
+		let body = find_at_depth(tokens, end, |kind| *kind == TokenKind::FatArrow) + 1;
+		let next = match tokens.get(body) {
+			Some((token, _)) if token.kind == TokenKind::LBrace => {
+				let after = matching_close(tokens, body) + 1;
+				match tokens.get(after) {
+					Some((token, _)) if token.kind == TokenKind::Comma => after + 1,
+					_ => after,
+				}
+			}
+			_ => find_at_depth(tokens, body, |kind| *kind == TokenKind::Comma) + 1,
+		};
+
+		// The names are bound in the guard and the body
+		pattern_binders(tokens, arm..end.min(close), end..next, binders);
+		arm = next;
+	}
+}
+
+/// Collects the name at `index`, if there is one there that can be renamed
+fn name_binder(
+	tokens: &[SpannedToken],
+	index: usize,
+	scope: Range<usize>,
+	binders: &mut Vec<Binder>,
+) {
+	if let Some((token, _)) = tokens.get(index) {
+		if matches!(&token.kind, TokenKind::Identifier(name) if renamable(*name)) {
+			binders.push(Binder {
+				index,
+				scope,
+				shorthand: false,
+			});
+		}
+	}
+}
+
+/// Whether a bound name can be renamed, which isn't the case for the receiver
+/// of a method, or for `_` which binds nothing
+fn renamable(name: Symbol) -> bool {
+	name != Symbol::UNDERSCORE && name != Symbol::SELF
+}
+
+/// Collects the names a pattern in `range` binds in `scope`, which are those
+/// that don't name a type, enum, variant or field
+fn pattern_binders(
+	tokens: &[SpannedToken],
+	range: Range<usize>,
+	scope: Range<usize>,
+	binders: &mut Vec<Binder>,
+) {
+	let kind = |index: usize| tokens.get(index).map(|(token, _)| &token.kind);
+	let mut open = Vec::new();
+
+	let end = range.end.min(tokens.len());
+
+	for (i, (token, _)) in tokens.iter().enumerate().take(end).skip(range.start) {
+		match &token.kind {
+			TokenKind::LParen | TokenKind::LBracket | TokenKind::LBrace => open.push(&token.kind),
+			TokenKind::RParen | TokenKind::RBracket | TokenKind::RBrace => {
+				open.pop();
+			}
+			TokenKind::Identifier(name) if renamable(*name) => {
+				let names_something = i.checked_sub(1).and_then(kind) == Some(&TokenKind::Dot)
+					|| matches!(
+						kind(i + 1),
+						Some(
+							TokenKind::LParen
+								| TokenKind::LBrace | TokenKind::Dot
+								| TokenKind::Colon
+						)
+					);
+
+				if !names_something {
+					binders.push(Binder {
+						index: i,
+						scope: scope.clone(),
+						shorthand: open.last() == Some(&&TokenKind::LBrace),
+					});
+				}
+			}
+			_ => {}
+		}
+	}
+}
+
+/// The index of the first token from `start` on, outside of any delimiters
+/// opened after it, for which `found` holds, or where the enclosing group
+/// or the tokens end if there is none
+fn find_at_depth(
+	tokens: &[SpannedToken],
+	start: usize,
+	found: impl Fn(&TokenKind) -> bool,
+) -> usize {
+	let mut depth = 0usize;
+
+	for (i, (token, _)) in tokens.iter().enumerate().skip(start) {
+		if depth == 0 && found(&token.kind) {
+			return i;
+		}
+
+		match token.kind {
+			TokenKind::LParen | TokenKind::LBracket | TokenKind::LBrace => depth += 1,
+			TokenKind::RParen | TokenKind::RBracket | TokenKind::RBrace => match depth {
+				0 => return i,
+				_ => depth -= 1,
+			},
+			_ => {}
+		}
+	}
+
+	tokens.len()
+}
+
+/// The index of the delimiter closing the group opened at `open`
+fn matching_close(tokens: &[SpannedToken], open: usize) -> usize {
+	find_at_depth(tokens, open + 1, |_| false)
+}
+
+/// The index of the `;` ending the statement `start` is in, or of the end of
+/// its group if it's the last one
+fn statement_end(tokens: &[SpannedToken], start: usize) -> usize {
+	find_at_depth(tokens, start, |kind| *kind == TokenKind::Semicolon)
+}
+
+/// The tokens inside the delimiters around `index`, which are all of them if
+/// it isn't inside any
+fn enclosing_group(tokens: &[SpannedToken], index: usize) -> Range<usize> {
+	let mut depth = 0usize;
+	let open = (0..index).rev().find(|&i| match tokens[i].0.kind {
+		TokenKind::RParen | TokenKind::RBracket | TokenKind::RBrace => {
+			depth += 1;
+			false
+		}
+		TokenKind::LParen | TokenKind::LBracket | TokenKind::LBrace => match depth {
+			0 => true,
+			_ => {
+				depth -= 1;
+				false
+			}
+		},
+		_ => false,
+	});
+
+	open.map_or(0, |open| open + 1)..find_at_depth(tokens, index, |_| false)
+}
+
+/// Whether the `fn` at `index` declares a method of an interface or impl, whose
+/// name is looked up through the type it's called on rather than bound
+fn is_method(tokens: &[SpannedToken], index: usize) -> bool {
+	// The methods are right inside the braces after `interface Name` or
+	// `impl Interface for Type`
+	let start = enclosing_group(tokens, index).start;
+	let before = |back: usize| {
+		start
+			.checked_sub(back)
+			.and_then(|i| tokens.get(i))
+			.map(|(token, _)| &token.kind)
+	};
+
+	before(3) == Some(&TokenKind::Keyword(Keyword::Interface))
+		|| before(3) == Some(&TokenKind::Keyword(Keyword::For))
+			&& before(5) == Some(&TokenKind::Keyword(Keyword::Impl))
+}
+
+/// Matches `trees` against a pattern, returning how many trees it took, or
+/// nothing if they don't match. An expression stops at the `follow` token if
+/// nothing in the pattern comes after it.
+fn match_trees(
+	matchers: &[Matcher],
+	trees: &[TokenTree],
+	follow: Option<&TokenKind>,
+	captures: &mut Captures,
+) -> Option<usize> {
+	let mut pos = 0;
+
+	for (i, matcher) in matchers.iter().enumerate() {
+		let next = match matchers.get(i + 1) {
+			Some(Matcher::Token(kind) | Matcher::Group(kind, _)) => Some(kind),
+			Some(_) => None,
+			None => follow,
+		};
+		let rest = &trees[pos..];
+
+		match matcher {
+			Matcher::Token(kind) => {
+				if !matches!(rest.first()?, TokenTree::Token((token, _)) if token.kind == *kind) {
+					return None;
+				}
+
+				pos += 1;
+			}
+			Matcher::Group(open, inner) => {
+				let TokenTree::Group {
+					open: (token, _),
+					trees: inner_trees,
+					..
+				} = rest.first()?
+				else {
+					return None;
+				};
+
+				if token.kind != *open
+					|| match_trees(inner, inner_trees, None, captures)? != inner_trees.len()
+				{
+					return None;
+				}
+
+				pos += 1;
+			}
+			Matcher::Fragment(name, kind) => {
+				let len = match kind {
+					FragmentKind::Ident => {
+						matches!(rest.first()?.kind(), TokenKind::Identifier(_)).then_some(1)?
+					}
+					FragmentKind::Tt => rest.first().map(|_| 1)?,
+					FragmentKind::Expr => {
+						let len = rest
+							.iter()
+							.position(|tree| Some(tree.kind()) == next)
+							.unwrap_or(rest.len());
+
+						(len > 0).then_some(len)?
+					}
+				};
+
+				captures.insert(*name, Capture::Fragment(*kind, rest[..len].to_vec()));
+				pos += len;
+			}
+			Matcher::Repeat {
+				matchers: inner,
+				separator,
+				at_least_once,
+			} => {
+				let mut iterations: Vec<Captures> = Vec::new();
+
+				loop {
+					let rest = &trees[pos..];
+
+					// Every iteration after the first follows a separator
+					let skip = match separator {
+						Some(separator) if !iterations.is_empty() => {
+							if rest.first().map(TokenTree::kind) != Some(separator) {
+								break;
+							}
+
+							1
+						}
+						_ => 0,
+					};
+
+					let mut iteration = Captures::new();
+					let follow = separator.as_ref().or(next);
+
+					match match_trees(inner, &rest[skip..], follow, &mut iteration) {
+						Some(len) if len > 0 => {
+							pos += skip + len;
+							iterations.push(iteration);
+						}
+						_ => break,
+					}
+				}
+
+				if *at_least_once && iterations.is_empty() {
+					return None;
+				}
+
+				let mut names = Vec::new();
+				matcher_variables(inner, &mut names);
+
+				for name in names {
+					let captured = iterations
+						.iter_mut()
+						.map(|iteration| iteration.remove(&name).expect("every iteration captures"))
+						.collect();
+
+					captures.insert(name, Capture::Repeated(captured));
+				}
+			}
+		}
+	}
+
+	Some(pos)
+}
+
+/// Looks up what a variable captured in the iterations given by `indices`
+fn lookup<'c>(mut capture: &'c Capture, indices: &[usize]) -> &'c Capture {
+	for &index in indices {
+		match capture {
+			Capture::Repeated(iterations) => capture = &iterations[index],
+			Capture::Fragment(..) => break,
+		}
+	}
+
+	capture
+}
+
+/// Writes out the tokens of a body, substituting the variables
+fn transcribe(
+	body: &[Transcriber],
+	captures: &Captures,
+	indices: &mut Vec<usize>,
+	expansion: ExpansionId,
+	tokens: &mut Vec<SpannedToken>,
+) -> Result<()> {
+	let expanded = |(token, span): &SpannedToken| {
+		let span = Span {
+			expansion: Some(expansion),
+			..*span
+		};
+
+		(token.clone(), span)
+	};
+
+	for transcriber in body {
+		match transcriber {
+			Transcriber::Token(token) => tokens.push(expanded(token)),
+			Transcriber::Group { open, body, close } => {
+				tokens.push(expanded(open));
+				transcribe(body, captures, indices, expansion, tokens)?;
+				tokens.push(expanded(close));
+			}
+			Transcriber::Variable(name, span) => match lookup(&captures[name], indices) {
+				Capture::Fragment(kind, trees) => {
+					let paren = |kind| {
+						let token = Token {
+							kind,
+							row: span.row,
+							col: span.col,
+						};
+
+						expanded(&(token, *span))
+					};
+
+					if *kind == FragmentKind::Expr {
+						tokens.push(paren(TokenKind::LParen));
+					}
+
+					for tree in trees {
+						tree.flatten_into(tokens);
+					}
+
+					if *kind == FragmentKind::Expr {
+						tokens.push(paren(TokenKind::RParen));
+					}
+				}
+				Capture::Repeated(_) => return Err(MacroRepetitionError { span: *span }.into()),
+			},
+			Transcriber::Repeat {
+				body,
+				separator,
+				span,
+			} => {
+				let mut names = Vec::new();
+				transcriber_variables(body, &mut names);
+
+				let mut counts =
+					names
+						.iter()
+						.filter_map(|name| match lookup(&captures[name], indices) {
+							Capture::Repeated(iterations) => Some(iterations.len()),
+							Capture::Fragment(..) => None,
+						});
+
+				let count = counts.next();
+
+				let Some(count) = count.filter(|&count| counts.all(|other| other == count)) else {
+					return Err(MacroRepetitionError { span: *span }.into());
+				};
+
+				for index in 0..count {
+					if let Some(separator) = separator.as_ref().filter(|_| index > 0) {
+						tokens.push(expanded(separator));
+					}
+
+					indices.push(index);
+					transcribe(body, captures, indices, expansion, tokens)?;
+					indices.pop();
+				}
+			}
+		}
+	}
+
+	Ok(())
+}
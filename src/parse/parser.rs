@@ -1,347 +1,3664 @@
-// use std::error::Error;
-
-// use crate::{
-// 	ast::{BinaryOp, Expr, ExprKind, LetDecl, Stmt, StmtKind},
-// 	lex::{self, Lexer, TokenKind},
-// };
-
-// #[derive(Debug, PartialEq)]
-// pub struct SourceFile {
-// 	pub stmts: Vec<Stmt>,
-// }
-
-// impl SourceFile {
-// 	fn new() -> Self {
-// 		Self { stmts: Vec::new() }
-// 	}
-// }
-
-// pub fn parse(src: &mut dyn std::io::Read) -> Result<SourceFile, Box<dyn Error>> {
-// 	let mut root = SourceFile::new();
-
-// 	let mut lexer = Lexer::new(src);
-
-// 	while lexer.peek_token().kind != TokenKind::End {
-// 		let token = lexer.peek_token();
-
-// 		println!("{token:?}");
-
-// 		match token.kind {
-// 			TokenKind::Identifier(ident) => {
-// 				if ident == "let" {
-// 					lexer.next();
-// 					let let_decl = parse_let(&mut lexer)?;
-
-// 					root.stmts.push(let_decl);
-// 				}
-// 			}
-// 			_ => todo!("Parse error {:?}", token.kind),
-// 		};
-
-// 		if lexer.peek_token().kind == TokenKind::End {
-// 			break;
-// 		}
-// 	}
-
-// 	Ok(root)
-// }
-
-// pub fn parse_term(lexer: &mut Lexer) -> Result<Expr, Box<dyn Error>> {
-// 	let mut expr = parse_factor(lexer)?;
-
-// 	while lexer.peek_token().kind == TokenKind::Plus
-// 		|| lexer.peek_token().kind == TokenKind::Minus
-// 		|| lexer.peek_token().kind == TokenKind::Pipe
-// 	{
-// 		let token = lexer.peek_token();
-
-// 		match token.kind {
-// 			TokenKind::Plus => {
-// 				lexer.next();
-// 				let right = parse_factor(lexer)?;
-// 				expr = Expr {
-// 					kind: ExprKind::Binary(BinaryOp::Add, Box::new(expr), Box::new(right)),
-// 				};
-// 			}
-// 			TokenKind::Minus => {
-// 				lexer.next();
-// 				let right = parse_factor(lexer)?;
-// 				expr = Expr {
-// 					kind: ExprKind::Binary(BinaryOp::Subtract, Box::new(expr), Box::new(right)),
-// 				};
-// 			}
-// 			TokenKind::Pipe => {
-// 				lexer.next();
-// 				let right = parse_factor(lexer)?;
-// 				expr = Expr {
-// 					kind: ExprKind::Binary(BinaryOp::Pipe, Box::new(expr), Box::new(right)),
-// 				};
-// 			}
-// 			_ => todo!(),
-// 		}
-// 	}
-
-// 	Ok(expr)
-// }
-
-// pub fn parse_expr(lexer: &mut Lexer) -> Result<Expr, Box<dyn Error>> {
-// 	parse_term(lexer)
-// }
-
-// pub fn parse_primary(lexer: &mut Lexer) -> Result<Expr, Box<dyn Error>> {
-// 	let token = lexer.peek_token();
-
-// 	match token.kind {
-// 		TokenKind::NumberLiteral(num) => {
-// 			lexer.next();
-// 			Ok(Expr {
-// 				kind: ExprKind::NumLit(num.parse().unwrap()),
-// 			})
-// 		}
-// 		TokenKind::StringLiteral(str) => {
-// 			lexer.next();
-// 			Ok(Expr {
-// 				kind: ExprKind::StrLit(str),
-// 			})
-// 		}
-// 		TokenKind::Identifier(ident) => {
-// 			lexer.next();
-// 			Ok(Expr {
-// 				kind: ExprKind::Reference(ident),
-// 			})
-// 		}
-// 		TokenKind::LParen => {
-// 			// Consume the left paren
-// 			lexer.next();
-
-// 			// Parse the expression within the parens
-// 			let expr = parse_expr(lexer)?;
-
-// 			// Check for the closing paren
-// 			if lexer.peek_token().kind != TokenKind::RParen {
-// 				return Err("Expected closing paren".into());
-// 			}
-
-// 			// Consume the right paren
-// 			lexer.next();
-
-// 			Ok(Expr {
-// 				kind: ExprKind::Group(Box::new(expr)),
-// 			})
-// 		}
-// 		_ => Err(format!("Expected unary token, got {:?}", token.kind).into()),
-// 	}
-// }
-
-// pub fn parse_factor(lexer: &mut Lexer) -> Result<Expr, Box<dyn Error>> {
-// 	let mut unary = parse_primary(lexer)?;
-
-// 	while lexer.peek_token().kind == TokenKind::Slash || lexer.peek_token().kind == TokenKind::Star
-// 	{
-// 		let token = lexer.peek_token();
-
-// 		match token.kind {
-// 			TokenKind::Slash => {
-// 				lexer.next();
-// 				let right = parse_primary(lexer)?;
-// 				unary = Expr {
-// 					kind: ExprKind::Binary(BinaryOp::Divide, Box::new(unary), Box::new(right)),
-// 				};
-// 			}
-// 			TokenKind::Star => {
-// 				lexer.next();
-// 				let right = parse_primary(lexer)?;
-// 				unary = Expr {
-// 					kind: ExprKind::Binary(BinaryOp::Multiply, Box::new(unary), Box::new(right)),
-// 				};
-// 			}
-// 			_ => return Err(format!("Expected factor token, got {:?}", token.kind).into()),
-// 		}
-// 	}
-
-// 	Ok(unary)
-// }
-
-// pub fn parse_let(lexer: &mut Lexer) -> Result<Stmt, Box<dyn Error>> {
-// 	println!("Current token {:?}", lexer.peek_token());
-// 	let token = lexer.next_token();
-
-// 	if let TokenKind::Identifier(name) = token.kind {
-// 		lexer.next_token();
-// 		let equals = lexer.peek_token();
-
-// 		println!("equals tok = {equals:?}");
-
-// 		match equals.kind {
-// 			TokenKind::Equals => (),
-// 			_ => panic!("Unexpected token: {equals:?}"),
-// 		}
-
-// 		lexer.next();
-
-// 		let value = Box::new(parse_expr(lexer)?);
-// 		let decl = Box::new(LetDecl { name, value });
-
-// 		if lexer.peek_token().kind != TokenKind::Semicolon {
-// 			panic!("Expected semicolon,(saw {:?}", lexer.peek_token().kind);
-// 		}
-
-// 		lexer.next();
-
-// 		Ok(Stmt {
-// 			kind: StmtKind::LetDecl(decl),
-// 		})
-// 	} else {
-// 		panic!("Expected identifier, got {:?}", token);
-// 	}
-// }
-
-// #[cfg(test)]
-// mod tests {
-// 	use super::*;
-
-// 	#[test]
-// 	fn basic_decl() {
-// 		let ast = parse(&mut "let x = 1;".as_bytes()).unwrap();
-
-// 		assert_eq!(
-// 			ast,
-// 			SourceFile {
-// 				stmts: vec![Stmt {
-// 					kind: StmtKind::LetDecl(Box::new(LetDecl {
-// 						name: "x".to_string(),
-// 						value: Box::new(Expr {
-// 							kind: ExprKind::NumLit(1.0)
-// 						})
-// 					}))
-// 				}]
-// 			}
-// 		);
-// 	}
-
-// 	#[test]
-// 	fn binary_plus() {
-// 		let ast = parse(&mut "let x = 1 + 2;".as_bytes()).unwrap();
-
-// 		assert_eq!(
-// 			ast,
-// 			SourceFile {
-// 				stmts: vec![Stmt {
-// 					kind: StmtKind::LetDecl(Box::new(LetDecl {
-// 						name: "x".to_string(),
-// 						value: Box::new(Expr {
-// 							kind: ExprKind::Binary(
-// 								BinaryOp::Add,
-// 								Box::new(Expr {
-// 									kind: ExprKind::NumLit(1.0)
-// 								}),
-// 								Box::new(Expr {
-// 									kind: ExprKind::NumLit(2.0)
-// 								})
-// 							)
-// 						})
-// 					}))
-// 				}]
-// 			}
-// 		);
-// 	}
-
-// 	#[test]
-// 	fn binary_minus() {
-// 		let ast = parse(&mut "let x = 1 - 2;".as_bytes()).unwrap();
-
-// 		assert_eq!(
-// 			ast,
-// 			SourceFile {
-// 				stmts: vec![Stmt {
-// 					kind: StmtKind::LetDecl(Box::new(LetDecl {
-// 						name: "x".to_string(),
-// 						value: Box::new(Expr {
-// 							kind: ExprKind::Binary(
-// 								BinaryOp::Subtract,
-// 								Box::new(Expr {
-// 									kind: ExprKind::NumLit(1.0)
-// 								}),
-// 								Box::new(Expr {
-// 									kind: ExprKind::NumLit(2.0)
-// 								})
-// 							)
-// 						})
-// 					}))
-// 				}]
-// 			}
-// 		);
-// 	}
-
-// 	#[test]
-// 	fn multiline() {
-// 		let ast = parse(&mut "let x = 1;\nlet y = 2 + 2;".as_bytes()).unwrap();
-
-// 		assert_eq!(
-// 			ast,
-// 			SourceFile {
-// 				stmts: vec![
-// 					Stmt {
-// 						kind: StmtKind::LetDecl(Box::new(LetDecl {
-// 							name: "x".to_string(),
-// 							value: Box::new(Expr {
-// 								kind: ExprKind::NumLit(1.0)
-// 							})
-// 						}))
-// 					},
-// 					Stmt {
-// 						kind: StmtKind::LetDecl(Box::new(LetDecl {
-// 							name: "y".to_string(),
-// 							value: Box::new(Expr {
-// 								kind: ExprKind::Binary(
-// 									BinaryOp::Add,
-// 									Box::new(Expr {
-// 										kind: ExprKind::NumLit(2.0)
-// 									}),
-// 									Box::new(Expr {
-// 										kind: ExprKind::NumLit(2.0)
-// 									})
-// 								)
-// 							})
-// 						}))
-// 					}
-// 				]
-// 			}
-// 		);
-// 	}
-
-// 	#[test]
-// 	pub fn precedence() {
-// 		let ast = parse(&mut "let x = 1 + 2 * 3;".as_bytes()).unwrap();
-
-// 		assert_eq!(
-// 			ast,
-// 			SourceFile {
-// 				stmts: vec![Stmt {
-// 					kind: StmtKind::LetDecl(Box::new(LetDecl {
-// 						name: "x".to_string(),
-// 						value: Box::new(Expr {
-// 							kind: ExprKind::Binary(
-// 								BinaryOp::Add,
-// 								Box::new(Expr {
-// 									kind: ExprKind::NumLit(1.0)
-// 								}),
-// 								Box::new(Expr {
-// 									kind: ExprKind::Binary(
-// 										BinaryOp::Multiply,
-// 										Box::new(Expr {
-// 											kind: ExprKind::NumLit(2.0)
-// 										}),
-// 										Box::new(Expr {
-// 											kind: ExprKind::NumLit(3.0)
-// 										})
-// 									)
-// 								})
-// 							)
-// 						})
-// 					}))
-// 				}]
-// 			}
-// 		);
-// 	}
-// }
+use std::{collections::HashMap, rc::Rc};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+	closing_delimiter, ChainedComparisonError, ExpectedTokenError, InvalidAssignTargetError,
+	InvalidNumberError, Macro, MacroRecursionError, MisplacedAttributeError, MultipleRestsError,
+	NoMatchingRuleError, Rule, SpannedToken, TokenTree, UnexpectedTokenError, UnknownMacroError,
+};
+use crate::{
+	ast::{
+		Arena, Attribute, BinaryOp, Block, ConstDecl, Copier, DanglingIdError, EnumVariant,
+		Expansion, Expansions, ExprId, ExprKind, FieldInit, FieldPattern, FnDecl, Fold, ImplDecl,
+		Import, ImportNames, InterfaceDecl, InterfaceMethod, LetDecl, MatchArm, Node, NodeId,
+		PatternId, PatternKind, RecordLit, Span, StmtId, StmtKind, TypeExprId, TypeExprKind,
+		TypedefField, UnaryOp,
+	},
+	intern::Symbol,
+	lex::{Keyword, Lexer, Token, TokenKind},
+	shared::Result,
+};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(try_from = "WrittenFile")]
+pub struct SourceFile {
+	/// The nodes of the file's tree
+	pub arena: Arena,
+	pub stmts: Vec<StmtId>,
+	/// The macro invocations the spans of expanded nodes refer to
+	pub expansions: Expansions,
+}
+
+impl SourceFile {
+	/// Copies the tree into a new arena, in which it's numbered by its shape
+	/// alone
+	fn copy(&self) -> (Vec<StmtId>, Arena) {
+		let mut copier = Copier::new(&self.arena);
+		let stmts = self
+			.stmts
+			.iter()
+			.map(|&stmt| copier.fold_stmt(stmt))
+			.collect();
+
+		(stmts, copier.finish())
+	}
+}
+
+/// A file as written out, whose IDs are checked before it's read back
+#[derive(Deserialize)]
+struct WrittenFile {
+	arena: Arena,
+	stmts: Vec<StmtId>,
+	expansions: Expansions,
+}
+
+impl TryFrom<WrittenFile> for SourceFile {
+	type Error = DanglingIdError;
+
+	fn try_from(file: WrittenFile) -> std::result::Result<Self, DanglingIdError> {
+		for &stmt in &file.stmts {
+			let id = NodeId::from(stmt);
+			if !matches!(file.arena.get(id), Some(Node::Stmt(_))) {
+				return Err(DanglingIdError {
+					expected: "statement",
+					id: id.0,
+				});
+			}
+		}
+
+		file.expansions.check_spans(&file.arena, &file.stmts)?;
+
+		Ok(Self {
+			arena: file.arena,
+			stmts: file.stmts,
+			expansions: file.expansions,
+		})
+	}
+}
+
+/// Expansions are ignored like spans, and so are the nodes left out of the
+/// tree, so that files can be compared by the shape of their trees alone
+impl PartialEq for SourceFile {
+	fn eq(&self, other: &Self) -> bool {
+		self.copy() == other.copy()
+	}
+}
+
+pub fn parse(src: &mut dyn std::io::Read) -> Result<SourceFile> {
+	let mut stmts = Vec::new();
+	let mut parser = Parser::new(Lexer::new(src));
+
+	loop {
+		parser.expand_stmt_macros()?;
+
+		match &parser.peek().kind {
+			TokenKind::End => break,
+			TokenKind::Keyword(Keyword::Macro) => parser.parse_macro()?,
+			_ => {
+				let stmt = parser.parse_top_level_stmt()?;
+				stmts.push(stmt);
+			}
+		}
+	}
+
+	Ok(SourceFile {
+		arena: parser.arena,
+		stmts,
+		expansions: parser.expansions,
+	})
+}
+
+/// How many invocations deep expansions can nest, which stops a macro that
+/// keeps invoking itself before parsing its expansion overflows the stack
+const MACRO_RECURSION_LIMIT: usize = 32;
+
+fn is_keyword(token: &Token, keyword: Keyword) -> bool {
+	token.kind == TokenKind::Keyword(keyword)
+}
+
+/// The attributes of a declaration
+fn attributes_of(kind: &mut StmtKind) -> Option<&mut Vec<Attribute>> {
+	match kind {
+		StmtKind::LetDecl(decl) => Some(&mut decl.attributes),
+		StmtKind::FnDecl(decl) => Some(&mut decl.attributes),
+		StmtKind::ConstDecl(decl) => Some(&mut decl.attributes),
+		_ => None,
+	}
+}
+
+fn comparison_op(kind: &TokenKind) -> Option<BinaryOp> {
+	match kind {
+		TokenKind::DoubleEquals => Some(BinaryOp::Equal),
+		TokenKind::NotEquals => Some(BinaryOp::NotEqual),
+		TokenKind::LessThan => Some(BinaryOp::LessThan),
+		TokenKind::LessThanEquals => Some(BinaryOp::LessThanOrEqual),
+		TokenKind::GreaterThan => Some(BinaryOp::GreaterThan),
+		TokenKind::GreaterThanEquals => Some(BinaryOp::GreaterThanOrEqual),
+		_ => None,
+	}
+}
+
+fn compound_assignment_op(kind: &TokenKind) -> Option<BinaryOp> {
+	match kind {
+		TokenKind::PlusEquals => Some(BinaryOp::Add),
+		TokenKind::MinusEquals => Some(BinaryOp::Subtract),
+		TokenKind::StarEquals => Some(BinaryOp::Multiply),
+		TokenKind::SlashEquals => Some(BinaryOp::Divide),
+		TokenKind::PercentEquals => Some(BinaryOp::Modulo),
+		TokenKind::CaretEquals => Some(BinaryOp::Power),
+		TokenKind::AmpersandEquals => Some(BinaryOp::BitAnd),
+		TokenKind::BarEquals => Some(BinaryOp::BitOr),
+		TokenKind::TildeEquals => Some(BinaryOp::BitXor),
+		_ => None,
+	}
+}
+
+pub struct Parser<'a> {
+	lexer: Lexer<'a>,
+	/// Tokens to parse before the lexer's, last first, which are the expansions
+	/// of macros and the rest of split tokens
+	expanded: Vec<SpannedToken>,
+	/// The macros defined so far
+	macros: HashMap<Symbol, Rc<Macro>>,
+	/// The invocations expanded so far, which the file takes over once parsed
+	expansions: Expansions,
+	/// The nodes parsed so far, which the file takes over once parsed
+	arena: Arena,
+	/// Whether `Name { ... }` may be parsed as a record literal, which is not the
+	/// case in conditions where the brace opens the body instead
+	records_allowed: bool,
+}
+
+impl<'a> Parser<'a> {
+	pub fn new(lexer: Lexer<'a>) -> Self {
+		Self {
+			lexer,
+			expanded: Vec::new(),
+			macros: HashMap::new(),
+			expansions: Expansions::default(),
+			arena: Arena::default(),
+			records_allowed: true,
+		}
+	}
+
+	/// Returns the next token without consuming it
+	fn peek(&mut self) -> &Token {
+		if let Some((token, _)) = self.expanded.last() {
+			return token;
+		}
+
+		self.lexer
+			.peek_token()
+			.expect("the lexer always ends with an end token")
+	}
+
+	/// Returns the span of the next token without consuming it
+	fn peek_span(&mut self) -> Span {
+		if let Some((_, span)) = self.expanded.last() {
+			return *span;
+		}
+
+		let token = self.peek();
+		Span::new(token.row, token.col)
+	}
+
+	/// Consumes the next token
+	fn advance(&mut self) -> Token {
+		if let Some((token, _)) = self.expanded.pop() {
+			return token;
+		}
+
+		let token = self.peek().clone();
+		self.lexer.next();
+		token
+	}
+
+	/// Consumes the next token, returning its span
+	fn advance_span(&mut self) -> Span {
+		let span = self.peek_span();
+		self.advance();
+		span
+	}
+
+	/// Consumes the next token if it is of the `expected` kind, otherwise errors
+	fn expect(&mut self, expected: TokenKind) -> Result<Token> {
+		let token = self.peek();
+
+		if token.kind != expected {
+			return Err(ExpectedTokenError::new(expected, token.clone()).into());
+		}
+
+		Ok(self.advance())
+	}
+
+	/// Parses `macro name { (pattern) => { body } ... }`, which defines `name!`
+	/// for the rest of the file
+	fn parse_macro(&mut self) -> Result<()> {
+		// Consume the `macro`
+		self.advance();
+		let name = self.expect_identifier()?;
+		self.expect(TokenKind::LBrace)?;
+
+		let mut rules = Vec::new();
+
+		while self.peek().kind != TokenKind::RBrace {
+			let (pattern, pattern_close) = self.parse_group("macro pattern")?;
+			self.expect(TokenKind::FatArrow)?;
+			let (body, body_close) = self.parse_group("macro body")?;
+
+			rules.push(Rule::new(&pattern, &pattern_close, &body, &body_close)?);
+
+			if matches!(self.peek().kind, TokenKind::Semicolon | TokenKind::Comma) {
+				self.advance();
+			}
+		}
+
+		self.expect(TokenKind::RBrace)?;
+		self.macros.insert(name, Rc::new(Macro { name, rules }));
+
+		Ok(())
+	}
+
+	/// Parses a delimited group of token trees, returning the trees inside and
+	/// the closing delimiter
+	fn parse_group(&mut self, expected: &'static str) -> Result<(Vec<TokenTree>, Token)> {
+		if closing_delimiter(&self.peek().kind).is_none() {
+			return Err(UnexpectedTokenError::new(expected, self.peek().clone()).into());
+		}
+
+		match self.parse_token_tree()? {
+			TokenTree::Group { trees, close, .. } => Ok((trees, close.0)),
+			TokenTree::Token(_) => unreachable!("a delimiter opens a group"),
+		}
+	}
+
+	/// Parses a single token, or a group of token trees between delimiters
+	fn parse_token_tree(&mut self) -> Result<TokenTree> {
+		let span = self.peek_span();
+		let token = self.advance();
+
+		let Some(closing) = closing_delimiter(&token.kind) else {
+			return match token.kind {
+				TokenKind::End | TokenKind::RParen | TokenKind::RBracket | TokenKind::RBrace => {
+					Err(UnexpectedTokenError::new("token", token).into())
+				}
+				_ => Ok(TokenTree::Token((token, span))),
+			};
+		};
+
+		let mut trees = Vec::new();
+
+		while self.peek().kind != closing {
+			trees.push(self.parse_token_tree()?);
+		}
+
+		let close_span = self.peek_span();
+		let close = self.advance();
+
+		Ok(TokenTree::Group {
+			open: (token, span),
+			trees,
+			close: (close, close_span),
+		})
+	}
+
+	/// Expands the invocation of the macro `name` at `span`, whose `!` is next,
+	/// into the tokens parsed next.
+	///
+	/// The expansion is wrapped in parentheses so that it stays one operand,
+	/// unless it is a whole statement, invoked with braces or with nothing but a
+	/// `;` or the end of the block after it. Then it may expand to several
+	/// statements, and the `;` is dropped if the expansion already ends with one.
+	fn expand_macro(&mut self, name: Symbol, span: Span, in_stmt: bool) -> Result<()> {
+		self.expect(TokenKind::Bang)?;
+		let braced = self.peek().kind == TokenKind::LBrace;
+		let (args, _) = self.parse_group("macro arguments")?;
+
+		let Some(mac) = self.macros.get(&name).cloned() else {
+			return Err(UnknownMacroError {
+				name: name.to_string(),
+				span,
+				backtrace: self.expansions.backtrace(span),
+			}
+			.into());
+		};
+
+		if self.expansions.depth(span) >= MACRO_RECURSION_LIMIT {
+			return Err(MacroRecursionError {
+				name: name.to_string(),
+				limit: MACRO_RECURSION_LIMIT,
+				span,
+				backtrace: self.expansions.backtrace(span),
+			}
+			.into());
+		}
+
+		let expansion = self.expansions.push(Expansion {
+			macro_name: name.to_string(),
+			call_site: span,
+		});
+
+		let Some(mut tokens) = mac.expand(&args, expansion)? else {
+			return Err(NoMatchingRuleError {
+				name: name.to_string(),
+				span,
+				backtrace: self.expansions.backtrace(span),
+			}
+			.into());
+		};
+
+		let follow = self.peek().kind.clone();
+		let whole_stmt = in_stmt
+			&& (braced
+				|| matches!(
+					follow,
+					TokenKind::Semicolon | TokenKind::RBrace | TokenKind::End
+				));
+
+		if !whole_stmt {
+			let delimiter = |kind| {
+				(
+					Token {
+						kind,
+						row: span.row,
+						col: span.col,
+					},
+					span,
+				)
+			};
+
+			tokens.insert(0, delimiter(TokenKind::LParen));
+			tokens.push(delimiter(TokenKind::RParen));
+		} else if follow == TokenKind::Semicolon
+			&& tokens
+				.last()
+				.is_some_and(|(token, _)| token.kind == TokenKind::Semicolon)
+		{
+			self.advance();
+		}
+
+		self.expanded.extend(tokens.into_iter().rev());
+
+		Ok(())
+	}
+
+	/// Expands the macros invoked at the start of a statement, so that the
+	/// statements they expand to are parsed in their place
+	fn expand_stmt_macros(&mut self) -> Result<()> {
+		while let TokenKind::Identifier(_) = self.peek().kind {
+			let span = self.peek_span();
+			let token = self.advance();
+
+			if self.peek().kind != TokenKind::Bang {
+				self.expanded.push((token, span));
+				break;
+			}
+
+			let TokenKind::Identifier(name) = token.kind else {
+				unreachable!("the token was peeked as an identifier");
+			};
+
+			self.expand_macro(name, span, true)?;
+		}
+
+		Ok(())
+	}
+
+	/// Consumes an identifier, returning its name
+	fn expect_identifier(&mut self) -> Result<Symbol> {
+		let token = self.advance();
+
+		match token.kind {
+			TokenKind::Identifier(name) => Ok(name),
+			_ => Err(UnexpectedTokenError::new("identifier", token).into()),
+		}
+	}
+
+	/// Consumes an identifier spelled `word`, which only has a special meaning
+	/// in the construct being parsed and so isn't reserved like a keyword
+	fn expect_contextual_keyword(&mut self, word: &'static str) -> Result<()> {
+		let token = self.advance();
+
+		match &token.kind {
+			TokenKind::Identifier(name) if name == word => Ok(()),
+			_ => Err(UnexpectedTokenError::new(word, token).into()),
+		}
+	}
+
+	/// Consumes a string literal, returning its contents
+	fn expect_string(&mut self) -> Result<String> {
+		let token = self.advance();
+
+		match token.kind {
+			TokenKind::StringLiteral(value) => Ok(value),
+			_ => Err(UnexpectedTokenError::new("string", token).into()),
+		}
+	}
+
+	/// Parses `f` with record literals allowed again, for the inside of
+	/// brackets where a `{` can no longer open the body of a condition
+	fn delimited<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+		let records_allowed = std::mem::replace(&mut self.records_allowed, true);
+		let result = f(self);
+		self.records_allowed = records_allowed;
+		result
+	}
+
+	/// Parses the condition of an `if` or `while`, in which `a {` starts the body
+	/// rather than a record literal
+	fn parse_condition(&mut self) -> Result<ExprId> {
+		let records_allowed = std::mem::replace(&mut self.records_allowed, false);
+		let condition = self.parse_expr();
+		self.records_allowed = records_allowed;
+		condition
+	}
+
+	/// Whether the next token starts a declaration rather than an expression
+	fn at_declaration(&mut self) -> bool {
+		let token = self.peek();
+		matches!(token.kind, TokenKind::At | TokenKind::Hash)
+			|| is_keyword(token, Keyword::Let)
+			|| is_keyword(token, Keyword::Const)
+			|| is_keyword(token, Keyword::Typedef)
+			|| is_keyword(token, Keyword::Enum)
+			|| is_keyword(token, Keyword::Fn)
+	}
+
+	/// Parses a statement at the top level of a file, where it can also be an
+	/// import, an export, an interface or an impl
+	pub fn parse_top_level_stmt(&mut self) -> Result<StmtId> {
+		if self.at_attribute() {
+			return self.parse_attributed(Self::parse_top_level_stmt);
+		}
+
+		if is_keyword(self.peek(), Keyword::Import) {
+			return self.parse_import();
+		}
+
+		if is_keyword(self.peek(), Keyword::Interface) {
+			return self.parse_interface();
+		}
+
+		if is_keyword(self.peek(), Keyword::Impl) {
+			return self.parse_impl();
+		}
+
+		if !is_keyword(self.peek(), Keyword::Export) {
+			return self.parse_stmt();
+		}
+
+		// Consume the `export`
+		let span = self.advance_span();
+
+		let exported = if is_keyword(self.peek(), Keyword::Interface) {
+			self.parse_interface()?
+		} else if self.at_declaration() {
+			self.parse_stmt()?
+		} else {
+			let found = self.peek().clone();
+			return Err(UnexpectedTokenError::new("declaration", found).into());
+		};
+
+		Ok(self.arena.alloc_stmt(StmtKind::Export(exported), span))
+	}
+
+	/// Parses `interface Name { fn method(self) -> Type; fn other(self) { ... } }`,
+	/// where methods with a body have a default implementation
+	fn parse_interface(&mut self) -> Result<StmtId> {
+		// Consume the `interface`
+		let span = self.advance_span();
+		let name = self.expect_identifier()?;
+
+		self.expect(TokenKind::LBrace)?;
+
+		let mut methods = Vec::new();
+
+		while self.peek().kind != TokenKind::RBrace {
+			if !is_keyword(self.peek(), Keyword::Fn) {
+				let found = self.peek().clone();
+				return Err(ExpectedTokenError::new(TokenKind::Keyword(Keyword::Fn), found).into());
+			}
+
+			let mut method = self.parse_fn_signature()?;
+
+			if self.peek().kind == TokenKind::Semicolon {
+				self.advance();
+			} else {
+				method.default = Some(self.parse_block()?);
+			}
+
+			methods.push(method);
+		}
+
+		self.expect(TokenKind::RBrace)?;
+
+		let decl = InterfaceDecl { name, methods };
+		Ok(self
+			.arena
+			.alloc_stmt(StmtKind::InterfaceDecl(Box::new(decl)), span))
+	}
+
+	/// Parses `impl Interface for Type { fn method(self) { ... } ... }`
+	fn parse_impl(&mut self) -> Result<StmtId> {
+		// Consume the `impl`
+		let span = self.advance_span();
+		let interface = self.expect_identifier()?;
+
+		self.expect(TokenKind::Keyword(Keyword::For))?;
+
+		let type_name = self.expect_identifier()?;
+
+		self.expect(TokenKind::LBrace)?;
+
+		let mut methods = Vec::new();
+
+		while self.peek().kind != TokenKind::RBrace {
+			let attributes = self.parse_attributes()?;
+
+			if !is_keyword(self.peek(), Keyword::Fn) {
+				let found = self.peek().clone();
+				return Err(ExpectedTokenError::new(TokenKind::Keyword(Keyword::Fn), found).into());
+			}
+
+			methods.push(FnDecl {
+				attributes,
+				constant: false,
+				..self.parse_fn_decl()?
+			});
+		}
+
+		self.expect(TokenKind::RBrace)?;
+
+		let decl = ImplDecl {
+			interface,
+			type_name,
+			methods,
+		};
+		Ok(self
+			.arena
+			.alloc_stmt(StmtKind::ImplDecl(Box::new(decl)), span))
+	}
+
+	fn at_attribute(&mut self) -> bool {
+		matches!(self.peek().kind, TokenKind::At | TokenKind::Hash)
+	}
+
+	/// Parses the attributes before a declaration, each `@name` or `#[name]`
+	/// followed by optional arguments in parentheses
+	fn parse_attributes(&mut self) -> Result<Vec<Attribute>> {
+		let mut attributes = Vec::new();
+
+		while self.at_attribute() {
+			let span = self.peek_span();
+			let bracketed = self.advance().kind == TokenKind::Hash;
+
+			if bracketed {
+				self.expect(TokenKind::LBracket)?;
+			}
+
+			let name = self.expect_identifier()?;
+			let mut args = Vec::new();
+
+			if self.peek().kind == TokenKind::LParen {
+				self.advance();
+				args = self.parse_comma_separated(TokenKind::RParen, Self::parse_expr)?;
+				self.expect(TokenKind::RParen)?;
+			}
+
+			if bracketed {
+				self.expect(TokenKind::RBracket)?;
+			}
+
+			attributes.push(Attribute { name, args, span });
+		}
+
+		Ok(attributes)
+	}
+
+	/// Parses attributes followed by the statement they are attached to, which
+	/// `parse` parses and must be a `let`, typedef, enum or function, or an
+	/// export of one
+	fn parse_attributed(&mut self, parse: fn(&mut Self) -> Result<StmtId>) -> Result<StmtId> {
+		let attributes = self.parse_attributes()?;
+		let stmt = parse(self)?;
+
+		let target = match self.arena[stmt].kind {
+			StmtKind::Export(exported) => exported,
+			_ => stmt,
+		};
+
+		// Those before an `export` come before those after it
+		let misplaced = self
+			.arena
+			.update_stmt(target, |kind| match attributes_of(kind) {
+				Some(target) => {
+					target.splice(0..0, attributes);
+					None
+				}
+				None => Some(attributes),
+			});
+
+		if let Some(attributes) = misplaced {
+			let first = &attributes[0];
+
+			return Err(MisplacedAttributeError {
+				name: first.name.to_string(),
+				span: first.span,
+				backtrace: self.expansions.backtrace(first.span),
+			}
+			.into());
+		}
+
+		Ok(stmt)
+	}
+
+	/// Parses `import "path" as m;` or `import { a, b } from "path";`
+	fn parse_import(&mut self) -> Result<StmtId> {
+		// Consume the `import`
+		let span = self.advance_span();
+
+		let import = if self.peek().kind == TokenKind::LBrace {
+			self.advance();
+			let names = self.parse_comma_separated(TokenKind::RBrace, Self::expect_identifier)?;
+			self.expect(TokenKind::RBrace)?;
+			self.expect_contextual_keyword("from")?;
+
+			Import {
+				path: self.expect_string()?,
+				names: ImportNames::Only(names),
+			}
+		} else {
+			let path = self.expect_string()?;
+			self.expect_contextual_keyword("as")?;
+
+			Import {
+				path,
+				names: ImportNames::Module(self.expect_identifier()?),
+			}
+		};
+
+		self.expect(TokenKind::Semicolon)?;
+
+		Ok(self
+			.arena
+			.alloc_stmt(StmtKind::Import(Box::new(import)), span))
+	}
+
+	pub fn parse_stmt(&mut self) -> Result<StmtId> {
+		if self.at_attribute() {
+			return self.parse_attributed(Self::parse_stmt);
+		}
+
+		if is_keyword(self.peek(), Keyword::Let) {
+			return self.parse_let();
+		}
+
+		if is_keyword(self.peek(), Keyword::Const) {
+			return self.parse_const();
+		}
+
+		if is_keyword(self.peek(), Keyword::Typedef) {
+			return self.parse_typedef();
+		}
+
+		if is_keyword(self.peek(), Keyword::Enum) {
+			return self.parse_enum();
+		}
+
+		if is_keyword(self.peek(), Keyword::Fn) {
+			return self.parse_fn();
+		}
+
+		let expr = self.parse_stmt_expr()?;
+		self.finish_expr_stmt(expr)
+	}
+
+	/// Parses an expression in statement position.
+	///
+	/// Like in Rust, a statement starting with a block-like expression ends at its
+	/// closing brace, so `if a { b } -1` is two statements rather than a subtraction.
+	fn parse_stmt_expr(&mut self) -> Result<ExprId> {
+		let token = self.peek();
+
+		if token.kind == TokenKind::LBrace
+			|| is_keyword(token, Keyword::If)
+			|| is_keyword(token, Keyword::While)
+			|| is_keyword(token, Keyword::For)
+			|| is_keyword(token, Keyword::Match)
+		{
+			self.parse_primary()
+		} else {
+			self.parse_expr()
+		}
+	}
+
+	/// Wraps an expression into a statement, consuming the terminating semicolon
+	/// which is optional after block-like expressions
+	fn finish_expr_stmt(&mut self, expr: ExprId) -> Result<StmtId> {
+		if self.peek().kind == TokenKind::Semicolon {
+			self.advance();
+		} else if !self.arena[expr].kind.is_block_like() {
+			return Err(ExpectedTokenError::new(TokenKind::Semicolon, self.peek().clone()).into());
+		}
+
+		let span = self.arena[expr].span;
+		Ok(self.arena.alloc_stmt(StmtKind::Expr(expr), span))
+	}
+
+	pub fn parse_block(&mut self) -> Result<Block> {
+		self.expect(TokenKind::LBrace)?;
+		self.parse_block_rest(None)
+	}
+
+	/// Parses the remainder of a block after the opening brace, starting with the
+	/// already parsed expression `first` if there is one
+	fn parse_block_rest(&mut self, first: Option<ExprId>) -> Result<Block> {
+		let mut stmts = Vec::new();
+		let mut tail = None;
+		let mut pending = first;
+
+		loop {
+			if pending.is_none() {
+				self.expand_stmt_macros()?;
+			}
+
+			let expr = match pending.take() {
+				Some(expr) => expr,
+				None if self.peek().kind == TokenKind::RBrace => break,
+				None if self.at_declaration() => {
+					stmts.push(self.parse_stmt()?);
+					continue;
+				}
+				None => self.parse_stmt_expr()?,
+			};
+
+			// An expression directly before the closing brace is the value of the block
+			if self.peek().kind == TokenKind::RBrace {
+				tail = Some(expr);
+				break;
+			}
+
+			stmts.push(self.finish_expr_stmt(expr)?);
+		}
+
+		self.expect(TokenKind::RBrace)?;
+
+		Ok(Block { stmts, expr: tail })
+	}
+
+	/// Parses either a block, a map literal or a functional record update.
+	///
+	/// Blocks and maps are told apart by a `:` following the first expression
+	/// inside the braces, so `{ x }` is a block and `{ x: 1 }` is a map. `{}` is an
+	/// empty block, an empty map is written `{:}`. Updates start with `..`.
+	fn parse_block_or_map(&mut self) -> Result<ExprKind> {
+		self.expect(TokenKind::LBrace)?;
+		self.expand_stmt_macros()?;
+
+		if self.at_declaration() {
+			return Ok(ExprKind::Block(self.parse_block_rest(None)?));
+		}
+
+		match self.peek().kind {
+			TokenKind::Colon => {
+				self.advance();
+				self.expect(TokenKind::RBrace)?;
+				return Ok(ExprKind::Map(Vec::new()));
+			}
+			TokenKind::DotDot => return Ok(ExprKind::Record(self.parse_record_rest(None)?)),
+			TokenKind::RBrace => return Ok(ExprKind::Block(self.parse_block_rest(None)?)),
+			_ => {}
+		}
+
+		let first = self.parse_stmt_expr()?;
+
+		if self.peek().kind != TokenKind::Colon {
+			return Ok(ExprKind::Block(self.parse_block_rest(Some(first))?));
+		}
+
+		self.advance();
+		let mut entries = vec![(first, self.parse_expr()?)];
+
+		if self.peek().kind == TokenKind::Comma {
+			self.advance();
+			entries.extend(self.parse_comma_separated(TokenKind::RBrace, |parser| {
+				let key = parser.parse_expr()?;
+				parser.expect(TokenKind::Colon)?;
+				Ok((key, parser.parse_expr()?))
+			})?);
+		}
+
+		self.expect(TokenKind::RBrace)?;
+
+		Ok(ExprKind::Map(entries))
+	}
+
+	/// Parses items separated by commas, allowing a trailing comma, up until but
+	/// not including the `close` token
+	fn parse_comma_separated<T>(
+		&mut self,
+		close: TokenKind,
+		mut item: impl FnMut(&mut Self) -> Result<T>,
+	) -> Result<Vec<T>> {
+		let mut items = Vec::new();
+
+		while self.peek().kind != close {
+			items.push(item(self)?);
+
+			if self.peek().kind != TokenKind::Comma {
+				break;
+			}
+
+			self.advance();
+		}
+
+		Ok(items)
+	}
+
+	pub fn parse_expr(&mut self) -> Result<ExprId> {
+		self.parse_assignment()
+	}
+
+	/// assignment → or ( ( "=" | "+=" | "-=" | ... ) assignment )? ;
+	///
+	/// Assignment binds loosest of all and is right associative, so `a = b = c`
+	/// assigns `c` to both.
+	pub fn parse_assignment(&mut self) -> Result<ExprId> {
+		let target = self.parse_range()?;
+
+		let op = match self.peek().kind {
+			TokenKind::Equals => None,
+			ref kind => match compound_assignment_op(kind) {
+				Some(op) => Some(op),
+				None => return Ok(target),
+			},
+		};
+
+		let span = self.arena[target].span;
+
+		if !matches!(
+			self.arena[target].kind,
+			ExprKind::Reference(_) | ExprKind::Field(..) | ExprKind::Index(..)
+		) {
+			return Err(InvalidAssignTargetError {
+				span,
+				backtrace: self.expansions.backtrace(span),
+			}
+			.into());
+		}
+
+		self.advance();
+		let value = self.parse_assignment()?;
+
+		let kind = match op {
+			None => ExprKind::Assign(target, value),
+			Some(op) => ExprKind::CompoundAssign(op, target, value),
+		};
+
+		Ok(self.arena.alloc_expr(kind, span))
+	}
+
+	/// range → or ( ( ".." | "..=" ) or )? ;
+	pub fn parse_range(&mut self) -> Result<ExprId> {
+		let start = self.parse_or()?;
+
+		let inclusive = match self.peek().kind {
+			TokenKind::DotDot => false,
+			TokenKind::DotDotEquals => true,
+			_ => return Ok(start),
+		};
+
+		self.advance();
+		let end = self.parse_or()?;
+
+		let span = self.arena[start].span;
+		Ok(self
+			.arena
+			.alloc_expr(ExprKind::Range(start, end, inclusive), span))
+	}
+
+	/// Parses a left associative chain of binary operators of the same precedence,
+	/// with `operand` parsing the tighter binding level below
+	fn parse_binary_level(
+		&mut self,
+		operand: fn(&mut Self) -> Result<ExprId>,
+		operator: fn(&TokenKind) -> Option<BinaryOp>,
+	) -> Result<ExprId> {
+		let mut expr = operand(self)?;
+
+		while let Some(op) = operator(&self.peek().kind) {
+			self.advance();
+			let right = operand(self)?;
+			let span = self.arena[expr].span;
+			expr = self
+				.arena
+				.alloc_expr(ExprKind::Binary(op, expr, right), span);
+		}
+
+		Ok(expr)
+	}
+
+	pub fn parse_or(&mut self) -> Result<ExprId> {
+		self.parse_binary_level(Self::parse_and, |kind| match kind {
+			TokenKind::DoubleBar => Some(BinaryOp::Or),
+			_ => None,
+		})
+	}
+
+	pub fn parse_and(&mut self) -> Result<ExprId> {
+		self.parse_binary_level(Self::parse_comparison, |kind| match kind {
+			TokenKind::DoubleAmpersand => Some(BinaryOp::And),
+			_ => None,
+		})
+	}
+
+	/// comparison → pipe ( ( "==" | "!=" | "<" | "<=" | ">" | ">=" ) pipe )? ;
+	///
+	/// Comparisons are non-associative, so chains like `a < b < c` are rejected.
+	pub fn parse_comparison(&mut self) -> Result<ExprId> {
+		let left = self.parse_pipe()?;
+
+		let Some(op) = comparison_op(&self.peek().kind) else {
+			return Ok(left);
+		};
+
+		self.advance();
+		let right = self.parse_pipe()?;
+
+		if let Some(second) = comparison_op(&self.peek().kind) {
+			let span = self.peek_span();
+			return Err(ChainedComparisonError {
+				first: op,
+				second,
+				span,
+				backtrace: self.expansions.backtrace(span),
+			}
+			.into());
+		}
+
+		let span = self.arena[left].span;
+		Ok(self
+			.arena
+			.alloc_expr(ExprKind::Binary(op, left, right), span))
+	}
+
+	pub fn parse_pipe(&mut self) -> Result<ExprId> {
+		self.parse_binary_level(Self::parse_bit_or, |kind| match kind {
+			TokenKind::Pipe => Some(BinaryOp::Pipe),
+			TokenKind::TryPipe => Some(BinaryOp::TryPipe),
+			_ => None,
+		})
+	}
+
+	pub fn parse_bit_or(&mut self) -> Result<ExprId> {
+		self.parse_binary_level(Self::parse_bit_xor, |kind| match kind {
+			TokenKind::Bar => Some(BinaryOp::BitOr),
+			_ => None,
+		})
+	}
+
+	pub fn parse_bit_xor(&mut self) -> Result<ExprId> {
+		self.parse_binary_level(Self::parse_bit_and, |kind| match kind {
+			TokenKind::Tilde => Some(BinaryOp::BitXor),
+			_ => None,
+		})
+	}
+
+	pub fn parse_bit_and(&mut self) -> Result<ExprId> {
+		self.parse_binary_level(Self::parse_term, |kind| match kind {
+			TokenKind::Ampersand => Some(BinaryOp::BitAnd),
+			_ => None,
+		})
+	}
+
+	pub fn parse_term(&mut self) -> Result<ExprId> {
+		self.parse_binary_level(Self::parse_factor, |kind| match kind {
+			TokenKind::Plus => Some(BinaryOp::Add),
+			TokenKind::Minus => Some(BinaryOp::Subtract),
+			_ => None,
+		})
+	}
+
+	pub fn parse_primary(&mut self) -> Result<ExprId> {
+		let span = self.peek_span();
+		let token = self.peek().clone();
+
+		let kind = match token.kind {
+			TokenKind::NumberLiteral(literal) => {
+				self.advance();
+				match literal.parse() {
+					Ok(num) => ExprKind::NumLit(num),
+					Err(_) => {
+						return Err(InvalidNumberError {
+							literal,
+							span,
+							backtrace: self.expansions.backtrace(span),
+						}
+						.into())
+					}
+				}
+			}
+			TokenKind::StringLiteral(str) => {
+				self.advance();
+				ExprKind::StrLit(str)
+			}
+			TokenKind::Keyword(Keyword::True) => {
+				self.advance();
+				ExprKind::BoolLit(true)
+			}
+			TokenKind::Keyword(Keyword::False) => {
+				self.advance();
+				ExprKind::BoolLit(false)
+			}
+			TokenKind::Keyword(Keyword::Nil) => {
+				self.advance();
+				ExprKind::NilLit
+			}
+			TokenKind::Keyword(Keyword::If) => self.parse_if()?,
+			TokenKind::Keyword(Keyword::While) => {
+				self.advance();
+				let condition = self.parse_condition()?;
+				let body = self.parse_block()?;
+				ExprKind::While(condition, body)
+			}
+			TokenKind::Keyword(Keyword::For) => {
+				self.advance();
+				let pattern = self.parse_pattern()?;
+				self.expect(TokenKind::Keyword(Keyword::In))?;
+				let iterable = self.parse_condition()?;
+				let body = self.parse_block()?;
+				ExprKind::For(pattern, iterable, body)
+			}
+			TokenKind::Keyword(Keyword::Match) => self.parse_match()?,
+			TokenKind::Identifier(Symbol::UNDERSCORE) => {
+				self.advance();
+				ExprKind::Placeholder
+			}
+			TokenKind::Identifier(ident) => {
+				self.advance();
+
+				if self.peek().kind == TokenKind::Bang {
+					self.expand_macro(ident, span, false)?;
+					return self.parse_primary();
+				}
+
+				ExprKind::Reference(ident)
+			}
+			TokenKind::LBrace => self.delimited(Self::parse_block_or_map)?,
+			TokenKind::LBracket => self.delimited(|parser| {
+				parser.advance();
+				let items = parser.parse_comma_separated(TokenKind::RBracket, Self::parse_expr)?;
+				parser.expect(TokenKind::RBracket)?;
+				Ok(ExprKind::List(items))
+			})?,
+			TokenKind::LParen => self.delimited(Self::parse_paren_or_lambda)?,
+			_ => return Err(UnexpectedTokenError::new("expression", token).into()),
+		};
+
+		Ok(self.arena.alloc_expr(kind, span))
+	}
+
+	fn parse_if(&mut self) -> Result<ExprKind> {
+		// Consume the `if`
+		self.advance();
+
+		let condition = self.parse_condition()?;
+		let then_branch = self.parse_block()?;
+
+		if !is_keyword(self.peek(), Keyword::Else) {
+			return Ok(ExprKind::If(condition, then_branch, None));
+		}
+
+		// Consume the `else`
+		self.advance();
+
+		let else_branch = if is_keyword(self.peek(), Keyword::If) {
+			self.parse_primary()?
+		} else {
+			let span = self.peek_span();
+			let block = self.parse_block()?;
+			self.arena.alloc_expr(ExprKind::Block(block), span)
+		};
+
+		Ok(ExprKind::If(condition, then_branch, Some(else_branch)))
+	}
+
+	/// Parses `match value { pattern if guard => body, ... }`, where the comma
+	/// after an arm can be left out if its body is block-like
+	fn parse_match(&mut self) -> Result<ExprKind> {
+		// Consume the `match`
+		self.advance();
+
+		let value = self.parse_condition()?;
+		self.expect(TokenKind::LBrace)?;
+
+		let arms = self.delimited(|parser| {
+			let mut arms = Vec::new();
+
+			while parser.peek().kind != TokenKind::RBrace {
+				let pattern = parser.parse_pattern()?;
+
+				let guard = if is_keyword(parser.peek(), Keyword::If) {
+					parser.advance();
+					Some(parser.parse_expr()?)
+				} else {
+					None
+				};
+
+				parser.expect(TokenKind::FatArrow)?;
+
+				let body = parser.parse_stmt_expr()?;
+				let needs_comma = !parser.arena[body].kind.is_block_like();
+
+				arms.push(MatchArm {
+					pattern,
+					guard,
+					body,
+				});
+
+				if parser.peek().kind == TokenKind::Comma {
+					parser.advance();
+				} else if needs_comma && parser.peek().kind != TokenKind::RBrace {
+					let found = parser.peek().clone();
+					return Err(ExpectedTokenError::new(TokenKind::Comma, found).into());
+				}
+			}
+
+			Ok(arms)
+		})?;
+
+		self.expect(TokenKind::RBrace)?;
+
+		Ok(ExprKind::Match(value, arms))
+	}
+
+	/// pattern → pattern-item ( "|" pattern-item )* ;
+	fn parse_pattern(&mut self) -> Result<PatternId> {
+		let first = self.parse_pattern_item()?;
+
+		if self.peek().kind != TokenKind::Bar {
+			return Ok(first);
+		}
+
+		let span = self.arena[first].span;
+		let mut alternatives = vec![first];
+
+		while self.peek().kind == TokenKind::Bar {
+			self.advance();
+			alternatives.push(self.parse_pattern_item()?);
+		}
+
+		Ok(self
+			.arena
+			.alloc_pattern(PatternKind::Or(alternatives), span))
+	}
+
+	fn parse_pattern_item(&mut self) -> Result<PatternId> {
+		let span = self.peek_span();
+		let token = self.peek().clone();
+
+		let kind = match token.kind {
+			TokenKind::Identifier(Symbol::UNDERSCORE) => {
+				self.advance();
+				PatternKind::Wildcard
+			}
+			TokenKind::Identifier(name) => {
+				self.advance();
+				self.parse_named_pattern(name)?
+			}
+			TokenKind::NumberLiteral(_)
+			| TokenKind::StringLiteral(_)
+			| TokenKind::Keyword(Keyword::True | Keyword::False | Keyword::Nil) => {
+				PatternKind::Literal(self.parse_primary()?)
+			}
+			TokenKind::Minus => {
+				self.advance();
+
+				let number = self.peek().clone();
+				if !matches!(number.kind, TokenKind::NumberLiteral(_)) {
+					return Err(UnexpectedTokenError::new("number", number).into());
+				}
+
+				let operand = self.parse_primary()?;
+				PatternKind::Literal(
+					self.arena
+						.alloc_expr(ExprKind::Unary(UnaryOp::Negate, operand), span),
+				)
+			}
+			TokenKind::LParen => {
+				self.advance();
+
+				if self.peek().kind == TokenKind::RParen {
+					self.advance();
+					PatternKind::Literal(self.arena.alloc_expr(ExprKind::UnitLit, span))
+				} else {
+					let first = self.parse_pattern()?;
+
+					// Like expressions, `(a)` is a group while `(a,)` is a tuple
+					if self.peek().kind == TokenKind::RParen {
+						self.advance();
+						return Ok(first);
+					}
+
+					self.expect(TokenKind::Comma)?;
+					let mut items = vec![first];
+					items.extend(
+						self.parse_comma_separated(TokenKind::RParen, Self::parse_pattern)?,
+					);
+					self.expect(TokenKind::RParen)?;
+
+					PatternKind::Tuple(items)
+				}
+			}
+			TokenKind::LBracket => {
+				self.advance();
+
+				let items = self.parse_comma_separated(TokenKind::RBracket, |parser| {
+					if parser.peek().kind != TokenKind::DotDot {
+						return parser.parse_pattern();
+					}
+
+					let span = parser.advance_span();
+					let name = match parser.peek().kind {
+						TokenKind::Identifier(_) => Some(parser.expect_identifier()?),
+						_ => None,
+					};
+
+					Ok(parser.arena.alloc_pattern(PatternKind::Rest(name), span))
+				})?;
+
+				let mut rests = items
+					.iter()
+					.map(|&item| &self.arena[item])
+					.filter(|item| matches!(item.kind, PatternKind::Rest(_)));
+				if let Some(second) = rests.nth(1) {
+					return Err(MultipleRestsError {
+						span: second.span,
+						backtrace: self.expansions.backtrace(second.span),
+					}
+					.into());
+				}
+
+				self.expect(TokenKind::RBracket)?;
+
+				PatternKind::List(items)
+			}
+			TokenKind::LBrace => {
+				self.advance();
+				PatternKind::Record {
+					type_name: None,
+					fields: self.parse_field_patterns()?,
+				}
+			}
+			_ => return Err(UnexpectedTokenError::new("pattern", token).into()),
+		};
+
+		Ok(self.arena.alloc_pattern(kind, span))
+	}
+
+	/// Parses the rest of a pattern starting with `name`, which is either a
+	/// variant, a record or a binding
+	fn parse_named_pattern(&mut self, name: Symbol) -> Result<PatternKind> {
+		let (enum_name, variant) = match self.peek().kind {
+			TokenKind::LBrace => {
+				self.advance();
+				return Ok(PatternKind::Record {
+					type_name: Some(name),
+					fields: self.parse_field_patterns()?,
+				});
+			}
+			TokenKind::Dot => {
+				self.advance();
+				(Some(name), self.expect_identifier()?)
+			}
+			TokenKind::LParen => (None, name),
+			_ => return Ok(PatternKind::Binding(name)),
+		};
+
+		let fields = if self.peek().kind == TokenKind::LParen {
+			self.advance();
+			let fields = self.parse_comma_separated(TokenKind::RParen, Self::parse_pattern)?;
+			self.expect(TokenKind::RParen)?;
+			fields
+		} else {
+			Vec::new()
+		};
+
+		Ok(PatternKind::Variant {
+			enum_name,
+			variant,
+			fields,
+		})
+	}
+
+	/// Parses the fields of a record pattern after its opening brace
+	fn parse_field_patterns(&mut self) -> Result<Vec<FieldPattern>> {
+		let fields = self.parse_comma_separated(TokenKind::RBrace, |parser| {
+			let span = parser.peek_span();
+			let name = parser.expect_identifier()?;
+
+			let pattern = if parser.peek().kind == TokenKind::Colon {
+				parser.advance();
+				parser.parse_pattern()?
+			} else {
+				parser.arena.alloc_pattern(PatternKind::Binding(name), span)
+			};
+
+			Ok(FieldPattern {
+				name,
+				pattern,
+				span,
+			})
+		})?;
+
+		self.expect(TokenKind::RBrace)?;
+
+		Ok(fields)
+	}
+
+	/// Parses either a parenthesised expression, the unit value `()`, a tuple or a
+	/// lambda, which share a prefix up until the `=>` following the closing paren.
+	fn parse_paren_or_lambda(&mut self) -> Result<ExprKind> {
+		// Consume the left paren
+		self.advance();
+
+		let mut exprs = Vec::new();
+		// The token each expression starts at, which is where a lambda's parameter
+		// that isn't a name is reported
+		let mut starts = Vec::new();
+		let mut trailing_comma = false;
+
+		while self.peek().kind != TokenKind::RParen {
+			starts.push(self.peek().clone());
+			exprs.push(self.parse_expr()?);
+
+			trailing_comma = self.peek().kind == TokenKind::Comma;
+			if !trailing_comma {
+				break;
+			}
+
+			self.advance();
+		}
+
+		// Check for the closing paren
+		self.expect(TokenKind::RParen)?;
+
+		if self.peek().kind == TokenKind::FatArrow {
+			self.advance();
+
+			let params = exprs
+				.into_iter()
+				.zip(starts)
+				.map(|(expr, start)| match self.arena[expr].kind {
+					ExprKind::Reference(name) => Ok(name),
+					ExprKind::Placeholder => Ok(Symbol::UNDERSCORE),
+					_ => Err(UnexpectedTokenError::new("parameter name", start).into()),
+				})
+				.collect::<Result<Vec<_>>>()?;
+
+			return Ok(ExprKind::Lambda(params, self.parse_block()?));
+		}
+
+		// `(a)` is a group while `(a,)` is a tuple with one element
+		Ok(match exprs.len() {
+			0 => ExprKind::UnitLit,
+			1 if !trailing_comma => ExprKind::Group(exprs[0]),
+			_ => ExprKind::Tuple(exprs),
+		})
+	}
+
+	/// postfix → primary ( "(" args ")" | "[" index "]" | "." ident | "?" | "{" fields "}" )* ;
+	///
+	/// Only a bare type name can be followed by the fields of a record literal.
+	pub fn parse_postfix(&mut self) -> Result<ExprId> {
+		let mut expr = self.parse_primary()?;
+
+		loop {
+			let span = self.arena[expr].span;
+			let record_allowed =
+				self.records_allowed && matches!(self.arena[expr].kind, ExprKind::Reference(_));
+			let kind = match self.peek().kind {
+				TokenKind::LParen => self.delimited(|parser| {
+					parser.advance();
+					let args = parser.parse_comma_separated(TokenKind::RParen, Self::parse_expr)?;
+					parser.expect(TokenKind::RParen)?;
+					Ok(ExprKind::FnInvoke(expr, args))
+				})?,
+				TokenKind::LBracket => self.delimited(|parser| {
+					parser.advance();
+					let kind = parser.parse_index(expr)?;
+					parser.expect(TokenKind::RBracket)?;
+					Ok(kind)
+				})?,
+				TokenKind::Dot => {
+					self.advance();
+					ExprKind::Field(expr, self.expect_identifier()?)
+				}
+				TokenKind::Question => {
+					self.advance();
+					ExprKind::Try(expr)
+				}
+				TokenKind::LBrace if record_allowed => {
+					let ExprKind::Reference(type_name) = self.arena[expr].kind else {
+						unreachable!()
+					};
+					self.advance();
+					ExprKind::Record(
+						self.delimited(|parser| parser.parse_record_rest(Some(type_name)))?,
+					)
+				}
+				_ => break,
+			};
+
+			expr = self.arena.alloc_expr(kind, span);
+		}
+
+		Ok(expr)
+	}
+
+	/// Parses the inside of an index operation on `target`, which is a slice if
+	/// it contains a `..`. The bounds are parsed below range expressions so that
+	/// the `..` is left for the slice.
+	fn parse_index(&mut self, target: ExprId) -> Result<ExprKind> {
+		let start = if self.peek().kind == TokenKind::DotDot {
+			None
+		} else {
+			Some(self.parse_or()?)
+		};
+
+		if self.peek().kind != TokenKind::DotDot {
+			let index = start.expect("an index without `..` is always parsed");
+			return Ok(ExprKind::Index(target, index));
+		}
+
+		self.advance();
+
+		let end = if self.peek().kind == TokenKind::RBracket {
+			None
+		} else {
+			Some(self.parse_or()?)
+		};
+
+		Ok(ExprKind::Slice(target, start, end))
+	}
+
+	pub fn parse_factor(&mut self) -> Result<ExprId> {
+		self.parse_binary_level(Self::parse_unary, |kind| match kind {
+			TokenKind::Slash => Some(BinaryOp::Divide),
+			TokenKind::Star => Some(BinaryOp::Multiply),
+			TokenKind::Percent => Some(BinaryOp::Modulo),
+			_ => None,
+		})
+	}
+
+	/// unary → ( "-" | "+" | "!" | "~" ) unary | power ;
+	pub fn parse_unary(&mut self) -> Result<ExprId> {
+		let op = match self.peek().kind {
+			TokenKind::Minus => UnaryOp::Negate,
+			TokenKind::Plus => UnaryOp::Plus,
+			TokenKind::Bang => UnaryOp::Not,
+			TokenKind::Tilde => UnaryOp::BitNot,
+			_ => return self.parse_power(),
+		};
+
+		let span = self.advance_span();
+		let operand = self.parse_unary()?;
+
+		Ok(self.arena.alloc_expr(ExprKind::Unary(op, operand), span))
+	}
+
+	/// power → postfix ( "^" unary )? ;
+	///
+	/// Exponentiation binds tighter than a unary operator on its left, so `-2 ^ 2`
+	/// is `-(2 ^ 2)`, and is right associative through the unary on its right.
+	pub fn parse_power(&mut self) -> Result<ExprId> {
+		let base = self.parse_postfix()?;
+
+		if self.peek().kind != TokenKind::Caret {
+			return Ok(base);
+		}
+
+		self.advance();
+		let exponent = self.parse_unary()?;
+
+		let span = self.arena[base].span;
+		Ok(self
+			.arena
+			.alloc_expr(ExprKind::Binary(BinaryOp::Power, base, exponent), span))
+	}
+
+	/// Parses the fields of a record literal after its opening brace, starting
+	/// with the base record of a functional update if there is one
+	fn parse_record_rest(&mut self, type_name: Option<Symbol>) -> Result<RecordLit> {
+		let mut base = None;
+
+		if self.peek().kind == TokenKind::DotDot {
+			self.advance();
+			base = Some(self.parse_expr()?);
+
+			if self.peek().kind != TokenKind::RBrace {
+				self.expect(TokenKind::Comma)?;
+			}
+		}
+
+		let fields = self.parse_comma_separated(TokenKind::RBrace, |parser| {
+			let span = parser.peek_span();
+			let name = parser.expect_identifier()?;
+			parser.expect(TokenKind::Colon)?;
+			let value = parser.parse_expr()?;
+
+			Ok(FieldInit { name, value, span })
+		})?;
+
+		self.expect(TokenKind::RBrace)?;
+
+		Ok(RecordLit {
+			type_name,
+			base,
+			fields,
+		})
+	}
+
+	pub fn parse_let(&mut self) -> Result<StmtId> {
+		// Consume the `let`
+		let span = self.advance_span();
+
+		let mutable = is_keyword(self.peek(), Keyword::Mut);
+		if mutable {
+			self.advance();
+		}
+
+		let pattern = self.parse_pattern()?;
+
+		self.expect(TokenKind::Equals)?;
+
+		let value = self.parse_expr()?;
+		let decl = Box::new(LetDecl {
+			attributes: Vec::new(),
+			mutable,
+			pattern,
+			value,
+		});
+
+		self.expect(TokenKind::Semicolon)?;
+
+		Ok(self.arena.alloc_stmt(StmtKind::LetDecl(decl), span))
+	}
+
+	/// Parses `const NAME = expr;`, or a `const fn` which constants can call
+	pub fn parse_const(&mut self) -> Result<StmtId> {
+		// Consume the `const`
+		let span = self.advance_span();
+
+		if is_keyword(self.peek(), Keyword::Fn) {
+			let decl = FnDecl {
+				constant: true,
+				..self.parse_fn_decl()?
+			};
+
+			if self.peek().kind == TokenKind::Semicolon {
+				self.advance();
+			}
+
+			return Ok(self
+				.arena
+				.alloc_stmt(StmtKind::FnDecl(Box::new(decl)), span));
+		}
+
+		let name = self.expect_identifier()?;
+		self.expect(TokenKind::Equals)?;
+		let value = self.parse_expr()?;
+		self.expect(TokenKind::Semicolon)?;
+
+		let decl = ConstDecl {
+			attributes: Vec::new(),
+			name,
+			value,
+		};
+		Ok(self
+			.arena
+			.alloc_stmt(StmtKind::ConstDecl(Box::new(decl)), span))
+	}
+
+	/// Parses `typedef Name<T> = { field: Type = default, ... }`, which binds the
+	/// name to the record type like a `let`
+	pub fn parse_typedef(&mut self) -> Result<StmtId> {
+		// Consume the `typedef`
+		let span = self.advance_span();
+		let pattern = self.parse_declared_name()?;
+		let type_params = self.parse_type_params()?;
+
+		self.expect(TokenKind::Equals)?;
+		self.expect(TokenKind::LBrace)?;
+
+		let fields = self.parse_comma_separated(TokenKind::RBrace, Self::parse_typedef_field)?;
+
+		self.expect(TokenKind::RBrace)?;
+
+		if self.peek().kind == TokenKind::Semicolon {
+			self.advance();
+		}
+
+		let value = self
+			.arena
+			.alloc_expr(ExprKind::Typedef(type_params, fields), span);
+
+		Ok(self.alloc_declared(pattern, value, span))
+	}
+
+	/// Parses the name bound by a typedef or enum, as the pattern of its `let`
+	fn parse_declared_name(&mut self) -> Result<PatternId> {
+		let span = self.peek_span();
+		let name = self.expect_identifier()?;
+
+		Ok(self.arena.alloc_pattern(PatternKind::Binding(name), span))
+	}
+
+	/// Adds the `let` a typedef or enum is parsed as, binding `pattern` to the
+	/// type
+	fn alloc_declared(&mut self, pattern: PatternId, value: ExprId, span: Span) -> StmtId {
+		let decl = LetDecl {
+			attributes: Vec::new(),
+			mutable: false,
+			pattern,
+			value,
+		};
+
+		self.arena
+			.alloc_stmt(StmtKind::LetDecl(Box::new(decl)), span)
+	}
+
+	fn parse_typedef_field(&mut self) -> Result<TypedefField> {
+		let name = self.expect_identifier()?;
+
+		let type_expr = if self.peek().kind == TokenKind::Colon {
+			self.advance();
+			Some(self.parse_type()?)
+		} else {
+			None
+		};
+
+		let default_value = if self.peek().kind == TokenKind::Equals {
+			self.advance();
+			Some(self.parse_expr()?)
+		} else {
+			None
+		};
+
+		Ok(TypedefField {
+			name,
+			type_expr,
+			default_value,
+		})
+	}
+
+	/// Parses the `<A, B>` after the name of a generic declaration, if there is
+	/// one
+	fn parse_type_params(&mut self) -> Result<Vec<Symbol>> {
+		if self.peek().kind != TokenKind::LessThan {
+			return Ok(Vec::new());
+		}
+
+		self.advance();
+		let params = self.parse_comma_separated(TokenKind::GreaterThan, Self::expect_identifier)?;
+		self.expect_closing_angle()?;
+
+		Ok(params)
+	}
+
+	/// type → type-atom "?"* ;
+	pub fn parse_type(&mut self) -> Result<TypeExprId> {
+		let mut type_expr = self.parse_type_atom()?;
+
+		loop {
+			match self.peek().kind {
+				TokenKind::Question => {
+					self.advance();
+				}
+				// The `?` of `[Num?]>> ...` can't be followed by a pipe, so the
+				// `?>>` is really an optional type closing a type argument list
+				TokenKind::TryPipe => self.split_token(TokenKind::Pipe),
+				_ => break,
+			}
+
+			let span = self.arena[type_expr].span;
+			type_expr = self
+				.arena
+				.alloc_type_expr(TypeExprKind::Optional(type_expr), span);
+		}
+
+		Ok(type_expr)
+	}
+
+	/// type-atom → identifier ( "<" type ( "," type )* ","? ">" )?
+	///           | "[" type "]"
+	///           | "{" type ":" type "}"
+	///           | "(" ( type ( "," type )* ","? )? ")" ( "->" type )? ;
+	fn parse_type_atom(&mut self) -> Result<TypeExprId> {
+		let span = self.peek_span();
+		let token = self.peek().clone();
+
+		let kind = match token.kind {
+			TokenKind::Identifier(name) => {
+				self.advance();
+
+				let args = if self.peek().kind == TokenKind::LessThan {
+					self.advance();
+					let args =
+						self.parse_comma_separated(TokenKind::GreaterThan, Self::parse_type)?;
+					self.expect_closing_angle()?;
+					args
+				} else {
+					Vec::new()
+				};
+
+				TypeExprKind::Named(name, args)
+			}
+			TokenKind::LBracket => {
+				self.advance();
+				let item = self.parse_type()?;
+				self.expect(TokenKind::RBracket)?;
+				TypeExprKind::List(item)
+			}
+			TokenKind::LBrace => {
+				self.advance();
+				let key = self.parse_type()?;
+				self.expect(TokenKind::Colon)?;
+				let value = self.parse_type()?;
+				self.expect(TokenKind::RBrace)?;
+				TypeExprKind::Map(key, value)
+			}
+			TokenKind::LParen => {
+				self.advance();
+
+				let mut items = Vec::new();
+				let mut trailing_comma = false;
+
+				while self.peek().kind != TokenKind::RParen {
+					items.push(self.parse_type()?);
+
+					trailing_comma = self.peek().kind == TokenKind::Comma;
+					if !trailing_comma {
+						break;
+					}
+
+					self.advance();
+				}
+
+				self.expect(TokenKind::RParen)?;
+
+				if self.peek().kind == TokenKind::Arrow {
+					self.advance();
+					TypeExprKind::Function(items, self.parse_type()?)
+				} else if items.len() == 1 && !trailing_comma {
+					// Parentheses only group a single type, as in `(() -> Num)?`
+					return Ok(items.pop().expect("there is one item"));
+				} else {
+					TypeExprKind::Tuple(items)
+				}
+			}
+			_ => return Err(UnexpectedTokenError::new("type", token).into()),
+		};
+
+		Ok(self.arena.alloc_type_expr(kind, span))
+	}
+
+	/// Consumes the `>` closing a type argument or parameter list, which may be
+	/// lexed as the start of a `>>` or `>=`, as in `[Pair<A, Pair<B, C>>]`
+	fn expect_closing_angle(&mut self) -> Result<()> {
+		match self.peek().kind {
+			TokenKind::GreaterThan => {
+				self.advance();
+			}
+			TokenKind::Pipe => self.split_token(TokenKind::GreaterThan),
+			TokenKind::GreaterThanEquals => self.split_token(TokenKind::Equals),
+			_ => {
+				let found = self.peek().clone();
+				return Err(ExpectedTokenError::new(TokenKind::GreaterThan, found).into());
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Consumes the first character of a two-character token, leaving the
+	/// second as a token of the `rest` kind
+	fn split_token(&mut self, rest: TokenKind) {
+		let span = self.peek_span();
+		let token = self.advance();
+
+		let rest = Token {
+			kind: rest,
+			row: token.row,
+			col: token.col + 1,
+		};
+
+		self.expanded.push((
+			rest,
+			Span {
+				col: span.col + 1,
+				..span
+			},
+		));
+	}
+
+	/// Parses `enum Name<T> = A(x) | B(y, z) | C`, which binds the name to the
+	/// enum like a `let`
+	pub fn parse_enum(&mut self) -> Result<StmtId> {
+		// Consume the `enum`
+		let span = self.advance_span();
+		let pattern = self.parse_declared_name()?;
+		let type_params = self.parse_type_params()?;
+
+		self.expect(TokenKind::Equals)?;
+
+		// A leading bar is allowed so that variants can be lined up
+		if self.peek().kind == TokenKind::Bar {
+			self.advance();
+		}
+
+		let mut variants = vec![self.parse_enum_variant()?];
+
+		while self.peek().kind == TokenKind::Bar {
+			self.advance();
+			variants.push(self.parse_enum_variant()?);
+		}
+
+		if self.peek().kind == TokenKind::Semicolon {
+			self.advance();
+		}
+
+		let value = self
+			.arena
+			.alloc_expr(ExprKind::Enum(type_params, variants), span);
+
+		Ok(self.alloc_declared(pattern, value, span))
+	}
+
+	/// Parses `fn name<T>(param: Type = default, ...) -> Type { ... }`
+	pub fn parse_fn(&mut self) -> Result<StmtId> {
+		let span = self.peek_span();
+		let decl = self.parse_fn_decl()?;
+
+		if self.peek().kind == TokenKind::Semicolon {
+			self.advance();
+		}
+
+		Ok(self
+			.arena
+			.alloc_stmt(StmtKind::FnDecl(Box::new(decl)), span))
+	}
+
+	fn parse_fn_decl(&mut self) -> Result<FnDecl> {
+		let InterfaceMethod {
+			name,
+			type_params,
+			params,
+			return_type,
+			..
+		} = self.parse_fn_signature()?;
+
+		Ok(FnDecl {
+			attributes: Vec::new(),
+			constant: false,
+			name,
+			type_params,
+			params,
+			return_type,
+			body: self.parse_block()?,
+		})
+	}
+
+	/// Parses everything of a function declaration up to its body, as an
+	/// interface method without a default
+	fn parse_fn_signature(&mut self) -> Result<InterfaceMethod> {
+		// Consume the `fn`
+		self.advance();
+		let name = self.expect_identifier()?;
+		let type_params = self.parse_type_params()?;
+
+		self.expect(TokenKind::LParen)?;
+		let params = self.parse_comma_separated(TokenKind::RParen, Self::parse_typedef_field)?;
+		self.expect(TokenKind::RParen)?;
+
+		let return_type = if self.peek().kind == TokenKind::Arrow {
+			self.advance();
+			Some(self.parse_type()?)
+		} else {
+			None
+		};
+
+		Ok(InterfaceMethod {
+			name,
+			type_params,
+			params,
+			return_type,
+			default: None,
+		})
+	}
+
+	fn parse_enum_variant(&mut self) -> Result<EnumVariant> {
+		let name = self.expect_identifier()?;
+
+		if self.peek().kind != TokenKind::LParen {
+			return Ok(EnumVariant {
+				name,
+				fields: Vec::new(),
+			});
+		}
+
+		self.advance();
+		let fields = self.parse_comma_separated(TokenKind::RParen, Self::parse_typedef_field)?;
+		self.expect(TokenKind::RParen)?;
+
+		Ok(EnumVariant { name, fields })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::cell::RefCell;
+
+	use super::*;
+
+	thread_local! {
+		/// The arena the expected trees are built in
+		static ARENA: RefCell<Arena> = RefCell::default();
+	}
+
+	/// The file of the statements built so far
+	fn file(stmts: Vec<StmtId>) -> SourceFile {
+		SourceFile {
+			arena: ARENA.take(),
+			stmts,
+			..Default::default()
+		}
+	}
+
+	fn expr(kind: ExprKind) -> ExprId {
+		ARENA.with_borrow_mut(|arena| arena.alloc_expr(kind, Span::default()))
+	}
+
+	fn stmt(kind: StmtKind) -> StmtId {
+		ARENA.with_borrow_mut(|arena| arena.alloc_stmt(kind, Span::default()))
+	}
+
+	fn let_decl(name: &str, value: ExprId) -> StmtId {
+		let_pattern(binding(name), value)
+	}
+
+	fn let_pattern(pattern: PatternId, value: ExprId) -> StmtId {
+		stmt(StmtKind::LetDecl(Box::new(LetDecl {
+			attributes: Vec::new(),
+			mutable: false,
+			pattern,
+			value,
+		})))
+	}
+
+	fn pattern(kind: PatternKind) -> PatternId {
+		ARENA.with_borrow_mut(|arena| arena.alloc_pattern(kind, Span::default()))
+	}
+
+	fn binding(name: &str) -> PatternId {
+		pattern(PatternKind::Binding(name.into()))
+	}
+
+	fn binary(op: BinaryOp, left: ExprId, right: ExprId) -> ExprId {
+		expr(ExprKind::Binary(op, left, right))
+	}
+
+	fn unary(op: UnaryOp, operand: ExprId) -> ExprId {
+		expr(ExprKind::Unary(op, operand))
+	}
+
+	fn num(value: f64) -> ExprId {
+		expr(ExprKind::NumLit(value))
+	}
+
+	fn string(value: &str) -> ExprId {
+		expr(ExprKind::StrLit(value.to_string()))
+	}
+
+	fn group(inner: ExprId) -> ExprId {
+		expr(ExprKind::Group(inner))
+	}
+
+	fn reference(name: &str) -> ExprId {
+		expr(ExprKind::Reference(name.into()))
+	}
+
+	fn type_expr(kind: TypeExprKind) -> TypeExprId {
+		ARENA.with_borrow_mut(|arena| arena.alloc_type_expr(kind, Span::default()))
+	}
+
+	fn named(name: &str) -> TypeExprId {
+		type_expr(TypeExprKind::Named(name.into(), vec![]))
+	}
+
+	/// A type copied out of `arena` on its own, to compare with one built here
+	fn copied_type(arena: &Arena, type_expr: TypeExprId) -> Arena {
+		let mut copier = Copier::new(arena);
+		copier.fold_type_expr(type_expr);
+		copier.finish()
+	}
+
+	fn built_type(type_expr: TypeExprId) -> Arena {
+		ARENA.with_borrow(|arena| copied_type(arena, type_expr))
+	}
+
+	#[test]
+	fn basic_decl() {
+		let ast = parse(&mut "let x = 1;".as_bytes()).unwrap();
+
+		assert_eq!(ast, file(vec![let_decl("x", num(1.0))]));
+	}
+
+	#[test]
+	fn binary_plus() {
+		let ast = parse(&mut "let x = 1 + 2;".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![let_decl(
+				"x",
+				binary(BinaryOp::Add, num(1.0), num(2.0))
+			)])
+		);
+	}
+
+	#[test]
+	fn binary_minus() {
+		let ast = parse(&mut "let x = 1 - 2;".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![let_decl(
+				"x",
+				binary(BinaryOp::Subtract, num(1.0), num(2.0))
+			)])
+		);
+	}
+
+	#[test]
+	fn multiline() {
+		let ast = parse(&mut "let x = 1;\nlet y = 2 + 2;".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![
+				let_decl("x", num(1.0)),
+				let_decl("y", binary(BinaryOp::Add, num(2.0), num(2.0)))
+			])
+		);
+	}
+
+	#[test]
+	pub fn precedence() {
+		let ast = parse(&mut "let x = 1 + 2 * 3;".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![let_decl(
+				"x",
+				binary(
+					BinaryOp::Add,
+					num(1.0),
+					binary(BinaryOp::Multiply, num(2.0), num(3.0))
+				)
+			)])
+		);
+	}
+
+	#[test]
+	fn negate_power() {
+		let ast = parse(&mut "let x = -2 ^ 2;".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![let_decl(
+				"x",
+				unary(UnaryOp::Negate, binary(BinaryOp::Power, num(2.0), num(2.0)))
+			)])
+		);
+	}
+
+	#[test]
+	fn power_is_right_associative() {
+		let ast = parse(&mut "let x = 2 ^ -3 ^ 2;".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![let_decl(
+				"x",
+				binary(
+					BinaryOp::Power,
+					num(2.0),
+					unary(UnaryOp::Negate, binary(BinaryOp::Power, num(3.0), num(2.0)))
+				)
+			)])
+		);
+	}
+
+	#[test]
+	fn unary_operators() {
+		let ast = parse(&mut "let x = !a * ~b - +-c;".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![let_decl(
+				"x",
+				binary(
+					BinaryOp::Subtract,
+					binary(
+						BinaryOp::Multiply,
+						unary(UnaryOp::Not, reference("a")),
+						unary(UnaryOp::BitNot, reference("b"))
+					),
+					unary(UnaryOp::Plus, unary(UnaryOp::Negate, reference("c")))
+				)
+			)])
+		);
+	}
+
+	#[test]
+	fn negate_call() {
+		let ast = parse(&mut "let x = -f(1) ^ 2;".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![let_decl(
+				"x",
+				unary(
+					UnaryOp::Negate,
+					binary(
+						BinaryOp::Power,
+						expr(ExprKind::FnInvoke(reference("f"), vec![num(1.0)])),
+						num(2.0)
+					)
+				)
+			)])
+		);
+	}
+
+	#[test]
+	fn logical_precedence() {
+		let ast = parse(&mut "let x = a || b && c == d % 2;".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![let_decl(
+				"x",
+				binary(
+					BinaryOp::Or,
+					reference("a"),
+					binary(
+						BinaryOp::And,
+						reference("b"),
+						binary(
+							BinaryOp::Equal,
+							reference("c"),
+							binary(BinaryOp::Modulo, reference("d"), num(2.0))
+						)
+					)
+				)
+			)])
+		);
+	}
+
+	#[test]
+	fn pipe_binds_tighter_than_comparison() {
+		let ast = parse(&mut "let x = a + 1 >> f != b | c & d;".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![let_decl(
+				"x",
+				binary(
+					BinaryOp::NotEqual,
+					binary(
+						BinaryOp::Pipe,
+						binary(BinaryOp::Add, reference("a"), num(1.0)),
+						reference("f")
+					),
+					binary(
+						BinaryOp::BitOr,
+						reference("b"),
+						binary(BinaryOp::BitAnd, reference("c"), reference("d"))
+					)
+				)
+			)])
+		);
+	}
+
+	#[test]
+	fn comparison_operators() {
+		for (source, op) in [
+			("a == b", BinaryOp::Equal),
+			("a != b", BinaryOp::NotEqual),
+			("a < b", BinaryOp::LessThan),
+			("a <= b", BinaryOp::LessThanOrEqual),
+			("a > b", BinaryOp::GreaterThan),
+			("a >= b", BinaryOp::GreaterThanOrEqual),
+		] {
+			let ast = parse(&mut format!("{source};").as_bytes()).unwrap();
+
+			assert_eq!(
+				ast,
+				file(vec![stmt(StmtKind::Expr(binary(
+					op,
+					reference("a"),
+					reference("b")
+				)))])
+			);
+		}
+	}
+
+	#[test]
+	fn bitwise_operators() {
+		for (source, op) in [
+			("a & b", BinaryOp::BitAnd),
+			("a ~ b", BinaryOp::BitXor),
+			("a | b", BinaryOp::BitOr),
+		] {
+			let ast = parse(&mut format!("{source};").as_bytes()).unwrap();
+
+			assert_eq!(
+				ast,
+				file(vec![stmt(StmtKind::Expr(binary(
+					op,
+					reference("a"),
+					reference("b")
+				)))])
+			);
+		}
+	}
+
+	#[test]
+	fn bitwise_precedence() {
+		// `&` binds tighter than `~`, which binds tighter than `|`
+		let ast = parse(&mut "a | b ~ c & d; a & b ~ c | d;".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![
+				stmt(StmtKind::Expr(binary(
+					BinaryOp::BitOr,
+					reference("a"),
+					binary(
+						BinaryOp::BitXor,
+						reference("b"),
+						binary(BinaryOp::BitAnd, reference("c"), reference("d"))
+					)
+				))),
+				stmt(StmtKind::Expr(binary(
+					BinaryOp::BitOr,
+					binary(
+						BinaryOp::BitXor,
+						binary(BinaryOp::BitAnd, reference("a"), reference("b")),
+						reference("c")
+					),
+					reference("d")
+				))),
+			])
+		);
+	}
+
+	#[test]
+	fn chained_comparison() {
+		let err = parse(&mut "let x = a < b < c;".as_bytes()).unwrap_err();
+
+		assert_eq!(
+			err.to_string(),
+			"Comparison operators cannot be chained at 1:15, write `a < b && b < c` or add parentheses instead of `a < b < c`"
+		);
+
+		assert!(parse(&mut "let x = a == b != c;".as_bytes()).is_err());
+		assert!(parse(&mut "let x = (a < b) == c;".as_bytes()).is_ok());
+	}
+
+	#[test]
+	fn invalid_number() {
+		let err = parse(&mut "let a = ½;".as_bytes()).unwrap_err();
+
+		assert_eq!(err.to_string(), "Invalid number literal `½` at 1:9");
+	}
+
+	#[test]
+	fn literals() {
+		let ast =
+			parse(&mut "let x = true; let y = false; let z = nil; let w = ();".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![
+				let_decl("x", expr(ExprKind::BoolLit(true))),
+				let_decl("y", expr(ExprKind::BoolLit(false))),
+				let_decl("z", expr(ExprKind::NilLit)),
+				let_decl("w", expr(ExprKind::UnitLit)),
+			])
+		);
+	}
+
+	#[test]
+	fn reserved_words() {
+		assert!(parse(&mut "let if = 1;".as_bytes()).is_err());
+		assert!(parse(&mut "let match = 1;".as_bytes()).is_err());
+		assert!(parse(&mut "let nilly = 1;".as_bytes()).is_ok());
+	}
+
+	#[test]
+	fn list_literal() {
+		let ast = parse(&mut "let x = [1, [], [2,],];".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![let_decl(
+				"x",
+				expr(ExprKind::List(vec![
+					num(1.0),
+					expr(ExprKind::List(vec![])),
+					expr(ExprKind::List(vec![num(2.0)])),
+				]))
+			)])
+		);
+	}
+
+	#[test]
+	fn map_literal() {
+		let ast = parse(&mut "let x = {\"a\": 1, b + 1: 2,}; let y = {:};".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![
+				let_decl(
+					"x",
+					expr(ExprKind::Map(vec![
+						(string("a"), num(1.0)),
+						(binary(BinaryOp::Add, reference("b"), num(1.0)), num(2.0)),
+					]))
+				),
+				let_decl("y", expr(ExprKind::Map(vec![]))),
+			])
+		);
+	}
+
+	#[test]
+	fn braces_without_colon_are_blocks() {
+		let ast = parse(&mut "let x = { a }; let y = {}; let z = { a; b };".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![
+				let_decl(
+					"x",
+					expr(ExprKind::Block(Block {
+						stmts: vec![],
+						expr: Some(reference("a"))
+					}))
+				),
+				let_decl(
+					"y",
+					expr(ExprKind::Block(Block {
+						stmts: vec![],
+						expr: None
+					}))
+				),
+				let_decl(
+					"z",
+					expr(ExprKind::Block(Block {
+						stmts: vec![stmt(StmtKind::Expr(reference("a")))],
+						expr: Some(reference("b"))
+					}))
+				),
+			])
+		);
+	}
+
+	#[test]
+	fn tuples_and_groups() {
+		let ast = parse(&mut "let x = (1, a); let y = (1,); let z = (1);".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![
+				let_decl("x", expr(ExprKind::Tuple(vec![num(1.0), reference("a")]))),
+				let_decl("y", expr(ExprKind::Tuple(vec![num(1.0)]))),
+				let_decl("z", expr(ExprKind::Group(num(1.0)))),
+			])
+		);
+	}
+
+	#[test]
+	fn index_and_slice() {
+		let ast = parse(&mut "let x = f(a)[0][1..i + 1][..2][3..][..];".as_bytes()).unwrap();
+
+		let call = expr(ExprKind::FnInvoke(reference("f"), vec![reference("a")]));
+		let index = expr(ExprKind::Index(call, num(0.0)));
+		let slice = expr(ExprKind::Slice(
+			index,
+			Some(num(1.0)),
+			Some(binary(BinaryOp::Add, reference("i"), num(1.0))),
+		));
+		let slice = expr(ExprKind::Slice(slice, None, Some(num(2.0))));
+		let slice = expr(ExprKind::Slice(slice, Some(num(3.0)), None));
+		let slice = expr(ExprKind::Slice(slice, None, None));
+
+		assert_eq!(ast, file(vec![let_decl("x", slice)]));
+	}
+
+	#[test]
+	fn index_binds_tighter_than_unary() {
+		let ast = parse(&mut "let x = -xs[0] ^ 2;".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![let_decl(
+				"x",
+				unary(
+					UnaryOp::Negate,
+					binary(
+						BinaryOp::Power,
+						expr(ExprKind::Index(reference("xs"), num(0.0))),
+						num(2.0)
+					)
+				)
+			)])
+		);
+	}
+
+	#[test]
+	fn typedef() {
+		let ast = parse(&mut "typedef Point = { x: Num = 0, y }".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![let_decl(
+				"Point",
+				expr(ExprKind::Typedef(
+					vec![],
+					vec![
+						TypedefField {
+							name: "x".into(),
+							type_expr: Some(named("Num")),
+							default_value: Some(num(0.0)),
+						},
+						TypedefField {
+							name: "y".into(),
+							type_expr: None,
+							default_value: None,
+						},
+					]
+				))
+			)])
+		);
+	}
+
+	#[test]
+	fn enum_decl() {
+		let ast = parse(&mut "enum Shape = | Circle(r: Num) | Rect(w, h = 1) | Empty;".as_bytes())
+			.unwrap();
+
+		let field = |name: &str, type_name: Option<&str>, default_value| TypedefField {
+			name: name.into(),
+			type_expr: type_name.map(named),
+			default_value,
+		};
+
+		assert_eq!(
+			ast,
+			file(vec![let_decl(
+				"Shape",
+				expr(ExprKind::Enum(
+					vec![],
+					vec![
+						EnumVariant {
+							name: "Circle".into(),
+							fields: vec![field("r", Some("Num"), None)],
+						},
+						EnumVariant {
+							name: "Rect".into(),
+							fields: vec![field("w", None, None), field("h", None, Some(num(1.0)))],
+						},
+						EnumVariant {
+							name: "Empty".into(),
+							fields: vec![],
+						},
+					]
+				))
+			)])
+		);
+	}
+
+	#[test]
+	fn record_literal_and_update() {
+		let ast = parse(&mut "let p = Point { x: 1, y: 2, }.x; let q = { ..p, x: 3 };".as_bytes())
+			.unwrap();
+
+		let field = |name: &str, value| FieldInit {
+			name: name.into(),
+			value,
+			span: Span::default(),
+		};
+
+		assert_eq!(
+			ast,
+			file(vec![
+				let_decl(
+					"p",
+					expr(ExprKind::Field(
+						expr(ExprKind::Record(RecordLit {
+							type_name: Some("Point".into()),
+							base: None,
+							fields: vec![field("x", num(1.0)), field("y", num(2.0))],
+						})),
+						"x".into()
+					))
+				),
+				let_decl(
+					"q",
+					expr(ExprKind::Record(RecordLit {
+						type_name: None,
+						base: Some(reference("p")),
+						fields: vec![field("x", num(3.0))],
+					}))
+				),
+			])
+		);
+	}
+
+	#[test]
+	fn no_record_literal_in_condition() {
+		let ast = parse(&mut "if a { b } while (P { x: 1 }).x { }".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![
+				stmt(StmtKind::Expr(expr(ExprKind::If(
+					reference("a"),
+					Block {
+						stmts: vec![],
+						expr: Some(reference("b"))
+					},
+					None
+				)))),
+				stmt(StmtKind::Expr(expr(ExprKind::While(
+					expr(ExprKind::Field(
+						expr(ExprKind::Group(expr(ExprKind::Record(RecordLit {
+							type_name: Some("P".into()),
+							base: None,
+							fields: vec![FieldInit {
+								name: "x".into(),
+								value: num(1.0),
+								span: Span::default(),
+							}],
+						})))),
+						"x".into()
+					)),
+					Block {
+						stmts: vec![],
+						expr: None
+					}
+				)))),
+			])
+		);
+	}
+
+	#[test]
+	fn block_with_tail() {
+		let ast = parse(&mut "let x = { let y = 2; y * 3 };".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![let_decl(
+				"x",
+				expr(ExprKind::Block(Block {
+					stmts: vec![let_decl("y", num(2.0))],
+					expr: Some(binary(BinaryOp::Multiply, reference("y"), num(3.0)))
+				}))
+			)])
+		);
+	}
+
+	#[test]
+	fn block_without_tail() {
+		let ast = parse(&mut "{ f(1); 2; }".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![stmt(StmtKind::Expr(expr(ExprKind::Block(Block {
+				stmts: vec![
+					stmt(StmtKind::Expr(expr(ExprKind::FnInvoke(
+						reference("f"),
+						vec![num(1.0)]
+					)))),
+					stmt(StmtKind::Expr(num(2.0))),
+				],
+				expr: None
+			}))))])
+		);
+	}
+
+	#[test]
+	fn if_else() {
+		let ast = parse(&mut "let x = if a { 1 } else if b { 2 } else { 3 };".as_bytes()).unwrap();
+
+		let block = |value| Block {
+			stmts: vec![],
+			expr: Some(num(value)),
+		};
+
+		assert_eq!(
+			ast,
+			file(vec![let_decl(
+				"x",
+				expr(ExprKind::If(
+					reference("a"),
+					block(1.0),
+					Some(expr(ExprKind::If(
+						reference("b"),
+						block(2.0),
+						Some(expr(ExprKind::Block(block(3.0))))
+					)))
+				))
+			)])
+		);
+	}
+
+	#[test]
+	fn block_like_statements() {
+		let ast = parse(&mut "while a { b; } { c } let d = 1;".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![
+				stmt(StmtKind::Expr(expr(ExprKind::While(
+					reference("a"),
+					Block {
+						stmts: vec![stmt(StmtKind::Expr(reference("b")))],
+						expr: None
+					}
+				)))),
+				stmt(StmtKind::Expr(expr(ExprKind::Block(Block {
+					stmts: vec![],
+					expr: Some(reference("c"))
+				})))),
+				let_decl("d", num(1.0)),
+			])
+		);
+	}
+
+	#[test]
+	fn block_like_tail() {
+		let ast = parse(&mut "{ while a { b } }".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![stmt(StmtKind::Expr(expr(ExprKind::Block(Block {
+				stmts: vec![],
+				expr: Some(expr(ExprKind::While(
+					reference("a"),
+					Block {
+						stmts: vec![],
+						expr: Some(reference("b"))
+					}
+				)))
+			}))))])
+		);
+	}
+
+	#[test]
+	fn lambda() {
+		let ast = parse(&mut "let add = (a, b) => { a + b };".as_bytes()).unwrap();
+
+		assert_eq!(
+			ast,
+			file(vec![let_decl(
+				"add",
+				expr(ExprKind::Lambda(
+					vec!["a".into(), "b".into()],
+					Block {
+						stmts: vec![],
+						expr: Some(binary(BinaryOp::Add, reference("a"), reference("b")))
+					}
+				))
+			)])
+		);
+	}
+
+	#[test]
+	fn lambda_parameter_that_is_not_a_name() {
+		assert_eq!(
+			parse(&mut "let f = (a, 1) => { a };".as_bytes())
+				.unwrap_err()
+				.to_string(),
+			"Expected parameter name, found NumberLiteral(\"1\") at 1:13"
+		);
+	}
+
+	#[test]
+	fn missing_semicolon() {
+		assert!(parse(&mut "let x = 1 let y = 2;".as_bytes()).is_err());
+		assert!(parse(&mut "{ 1 2 }".as_bytes()).is_err());
+	}
+
+	#[test]
+	fn spans() {
+		let ast = parse(&mut "let x = 1;\n  { y }".as_bytes()).unwrap();
+
+		assert_eq!(ast.arena[ast.stmts[0]].span, Span::new(1, 1));
+		assert_eq!(ast.arena[ast.stmts[1]].span, Span::new(2, 3));
+	}
+
+	#[test]
+	fn match_arms() {
+		let source = "match shape {
+			Circle(r) if r > 0 => r,
+			Shape.Rect(w, _) | Square(w) => { w }
+			Empty => 0,
+		}";
+
+		let arm = |pattern, guard, body| MatchArm {
+			pattern,
+			guard,
+			body,
+		};
+		let variant = |enum_name: Option<&str>, variant: &str, fields| {
+			pattern(PatternKind::Variant {
+				enum_name: enum_name.map(Symbol::from),
+				variant: variant.into(),
+				fields,
+			})
+		};
+
+		assert_eq!(
+			parse(&mut source.as_bytes()).unwrap(),
+			file(vec![stmt(StmtKind::Expr(expr(ExprKind::Match(
+				reference("shape"),
+				vec![
+					arm(
+						variant(None, "Circle", vec![binding("r")]),
+						Some(binary(BinaryOp::GreaterThan, reference("r"), num(0.0))),
+						reference("r"),
+					),
+					arm(
+						pattern(PatternKind::Or(vec![
+							variant(
+								Some("Shape"),
+								"Rect",
+								vec![binding("w"), pattern(PatternKind::Wildcard)],
+							),
+							variant(None, "Square", vec![binding("w")]),
+						])),
+						None,
+						expr(ExprKind::Block(Block {
+							stmts: vec![],
+							expr: Some(reference("w")),
+						})),
+					),
+					arm(binding("Empty"), None, num(0.0)),
+				],
+			))))])
+		);
+	}
+
+	#[test]
+	fn literal_tuple_and_list_patterns() {
+		let source = "match x {
+			(1, -2) => a,
+			(\"s\", true | nil,) => b,
+			[first, ..rest] | [..] => c,
+			() => d
+		}";
+
+		let literal = |value| pattern(PatternKind::Literal(value));
+		let arm = |pattern, body| MatchArm {
+			pattern,
+			guard: None,
+			body,
+		};
+
+		assert_eq!(
+			parse(&mut source.as_bytes()).unwrap(),
+			file(vec![stmt(StmtKind::Expr(expr(ExprKind::Match(
+				reference("x"),
+				vec![
+					arm(
+						pattern(PatternKind::Tuple(vec![
+							literal(num(1.0)),
+							literal(unary(UnaryOp::Negate, num(2.0))),
+						])),
+						reference("a"),
+					),
+					arm(
+						pattern(PatternKind::Tuple(vec![
+							literal(string("s")),
+							pattern(PatternKind::Or(vec![
+								literal(expr(ExprKind::BoolLit(true))),
+								literal(expr(ExprKind::NilLit)),
+							])),
+						])),
+						reference("b"),
+					),
+					arm(
+						pattern(PatternKind::Or(vec![
+							pattern(PatternKind::List(vec![
+								binding("first"),
+								pattern(PatternKind::Rest(Some("rest".into()))),
+							])),
+							pattern(PatternKind::List(vec![pattern(PatternKind::Rest(None))])),
+						])),
+						reference("c"),
+					),
+					arm(literal(expr(ExprKind::UnitLit)), reference("d")),
+				],
+			))))])
+		);
+	}
+
+	#[test]
+	fn match_arm_needs_comma() {
+		let source = "match x { 1 => a 2 => b }";
+		assert!(parse(&mut source.as_bytes()).is_err());
+	}
+
+	#[test]
+	fn one_rest_per_list_pattern() {
+		let source = "let [.., x, ..] = xs;";
+		assert_eq!(
+			parse(&mut source.as_bytes()).unwrap_err().to_string(),
+			"Only one `..` is allowed in a list pattern at 1:13"
+		);
+	}
+
+	#[test]
+	fn let_destructuring() {
+		let source = "let { x, y: (a, _) } = point; let Point { x } = p;";
+
+		let field = |name: &str, pattern| FieldPattern {
+			name: name.into(),
+			pattern,
+			span: Span::default(),
+		};
+
+		assert_eq!(
+			parse(&mut source.as_bytes()).unwrap(),
+			file(vec![
+				let_pattern(
+					pattern(PatternKind::Record {
+						type_name: None,
+						fields: vec![
+							field("x", binding("x")),
+							field(
+								"y",
+								pattern(PatternKind::Tuple(vec![
+									binding("a"),
+									pattern(PatternKind::Wildcard),
+								])),
+							),
+						],
+					}),
+					reference("point"),
+				),
+				let_pattern(
+					pattern(PatternKind::Record {
+						type_name: Some("Point".into()),
+						fields: vec![field("x", binding("x"))],
+					}),
+					reference("p"),
+				),
+			])
+		);
+	}
+
+	#[test]
+	fn fn_decl() {
+		let source = "fn area(w: Num, h = 1) -> Num { w * h } fn log(msg: Str = \"x\") {};";
+
+		let param = |name: &str, type_name: Option<&str>, default_value| TypedefField {
+			name: name.into(),
+			type_expr: type_name.map(named),
+			default_value,
+		};
+
+		assert_eq!(
+			parse(&mut source.as_bytes()).unwrap(),
+			file(vec![
+				stmt(StmtKind::FnDecl(Box::new(FnDecl {
+					attributes: Vec::new(),
+					constant: false,
+					name: "area".into(),
+					type_params: vec![],
+					params: vec![
+						param("w", Some("Num"), None),
+						param("h", None, Some(num(1.0))),
+					],
+					return_type: Some(named("Num")),
+					body: Block {
+						stmts: vec![],
+						expr: Some(binary(BinaryOp::Multiply, reference("w"), reference("h"),)),
+					},
+				}))),
+				stmt(StmtKind::FnDecl(Box::new(FnDecl {
+					attributes: Vec::new(),
+					constant: false,
+					name: "log".into(),
+					type_params: vec![],
+					params: vec![param("msg", Some("Str"), Some(string("x")))],
+					return_type: None,
+					body: Block {
+						stmts: vec![],
+						expr: None,
+					},
+				}))),
+			])
+		);
+	}
+
+	#[test]
+	fn generic_decls() {
+		let source = "typedef Pair<A, B> = { first: A, second: B }
+			enum Tree<T> = Leaf | Node(left: Tree<T>, value: T?, right: Tree<T>)
+			fn map<T, U>(xs: [T], f: (T) -> U) -> [U] { xs }";
+
+		let ast = parse(&mut source.as_bytes()).unwrap();
+
+		let field = |name: &str, type_expr| TypedefField {
+			name: name.into(),
+			type_expr: Some(type_expr),
+			default_value: None,
+		};
+		let tree = || type_expr(TypeExprKind::Named("Tree".into(), vec![named("T")]));
+		let list = |item| type_expr(TypeExprKind::List(item));
+
+		assert_eq!(
+			ast,
+			file(vec![
+				let_decl(
+					"Pair",
+					expr(ExprKind::Typedef(
+						vec!["A".into(), "B".into()],
+						vec![field("first", named("A")), field("second", named("B"))],
+					))
+				),
+				let_decl(
+					"Tree",
+					expr(ExprKind::Enum(
+						vec!["T".into()],
+						vec![
+							EnumVariant {
+								name: "Leaf".into(),
+								fields: vec![],
+							},
+							EnumVariant {
+								name: "Node".into(),
+								fields: vec![
+									field("left", tree()),
+									field("value", type_expr(TypeExprKind::Optional(named("T")))),
+									field("right", tree()),
+								],
+							},
+						]
+					))
+				),
+				stmt(StmtKind::FnDecl(Box::new(FnDecl {
+					attributes: Vec::new(),
+					constant: false,
+					name: "map".into(),
+					type_params: vec!["T".into(), "U".into()],
+					params: vec![
+						field("xs", list(named("T"))),
+						field(
+							"f",
+							type_expr(TypeExprKind::Function(vec![named("T")], named("U")))
+						),
+					],
+					return_type: Some(list(named("U"))),
+					body: Block {
+						stmts: vec![],
+						expr: Some(reference("xs")),
+					},
+				}))),
+			])
+		);
+	}
+
+	#[test]
+	fn nested_type_args() {
+		let source =
+			"typedef A = { p: Pair<Num, Pair<Str, [Num?]>>, q: Pair<Num, Pair<Str, Num?>>= nil }";
+
+		let ast = parse(&mut source.as_bytes()).unwrap();
+		let StmtKind::LetDecl(decl) = &ast.arena[ast.stmts[0]].kind else {
+			panic!("expected a let");
+		};
+		let ExprKind::Typedef(_, fields) = &ast.arena[decl.value].kind else {
+			panic!("expected a typedef");
+		};
+
+		let pair =
+			|first, second| type_expr(TypeExprKind::Named("Pair".into(), vec![first, second]));
+		let optional_nums = type_expr(TypeExprKind::List(type_expr(TypeExprKind::Optional(
+			named("Num"),
+		))));
+
+		let types = fields
+			.iter()
+			.map(|field| copied_type(&ast.arena, field.type_expr.unwrap()))
+			.collect::<Vec<_>>();
+
+		assert_eq!(
+			types[0],
+			built_type(pair(named("Num"), pair(named("Str"), optional_nums)))
+		);
+		assert_eq!(
+			types[1],
+			built_type(pair(
+				named("Num"),
+				pair(
+					named("Str"),
+					type_expr(TypeExprKind::Optional(named("Num")))
+				)
+			))
+		);
+		assert_eq!(
+			ast.arena[fields[1].default_value.unwrap()].kind,
+			ExprKind::NilLit
+		);
+	}
+
+	#[test]
+	fn tuple_and_function_types() {
+		let source = "fn f(a: (), b: (Num), c: (Num,), d: {Str: (Num, Num)}, e: (() -> Num)?) {}";
+
+		let ast = parse(&mut source.as_bytes()).unwrap();
+		let StmtKind::FnDecl(decl) = &ast.arena[ast.stmts[0]].kind else {
+			panic!("expected a fn");
+		};
+		let types = decl
+			.params
+			.iter()
+			.map(|param| copied_type(&ast.arena, param.type_expr.unwrap()))
+			.collect::<Vec<_>>();
+
+		let tuple = |items| type_expr(TypeExprKind::Tuple(items));
+
+		assert_eq!(types[0], built_type(tuple(vec![])));
+		assert_eq!(types[1], built_type(named("Num")));
+		assert_eq!(types[2], built_type(tuple(vec![named("Num")])));
+		assert_eq!(
+			types[3],
+			built_type(type_expr(TypeExprKind::Map(
+				named("Str"),
+				tuple(vec![named("Num"), named("Num")])
+			)))
+		);
+		assert_eq!(
+			types[4],
+			built_type(type_expr(TypeExprKind::Optional(type_expr(
+				TypeExprKind::Function(vec![], named("Num"))
+			))))
+		);
+	}
+
+	#[test]
+	fn assignment() {
+		let source = "let mut x = 1; x = y = 2; p.x += 1; xs[0] ~= x;";
+
+		assert_eq!(
+			parse(&mut source.as_bytes()).unwrap(),
+			file(vec![
+				stmt(StmtKind::LetDecl(Box::new(LetDecl {
+					attributes: Vec::new(),
+					mutable: true,
+					pattern: binding("x"),
+					value: num(1.0),
+				}))),
+				stmt(StmtKind::Expr(expr(ExprKind::Assign(
+					reference("x"),
+					expr(ExprKind::Assign(reference("y"), num(2.0),)),
+				)))),
+				stmt(StmtKind::Expr(expr(ExprKind::CompoundAssign(
+					BinaryOp::Add,
+					expr(ExprKind::Field(reference("p"), "x".into(),)),
+					num(1.0),
+				)))),
+				stmt(StmtKind::Expr(expr(ExprKind::CompoundAssign(
+					BinaryOp::BitXor,
+					expr(ExprKind::Index(reference("xs"), num(0.0),)),
+					reference("x"),
+				)))),
+			])
+		);
+	}
+
+	#[test]
+	fn invalid_assignment_target() {
+		assert_eq!(
+			parse(&mut "f() = 1;".as_bytes()).unwrap_err().to_string(),
+			"Expected a variable, field or list element to assign to at 1:1"
+		);
+		assert!(parse(&mut "a + b += 1;".as_bytes()).is_err());
+	}
+
+	#[test]
+	fn ranges() {
+		let source = "let a = 1..n + 1; let b = 0..=9; let c = xs[1..n];";
+
+		assert_eq!(
+			parse(&mut source.as_bytes()).unwrap(),
+			file(vec![
+				let_decl(
+					"a",
+					expr(ExprKind::Range(
+						num(1.0),
+						binary(BinaryOp::Add, reference("n"), num(1.0)),
+						false,
+					)),
+				),
+				let_decl("b", expr(ExprKind::Range(num(0.0), num(9.0), true)),),
+				let_decl(
+					"c",
+					expr(ExprKind::Slice(
+						reference("xs"),
+						Some(num(1.0)),
+						Some(reference("n")),
+					)),
+				),
+			])
+		);
+	}
+
+	#[test]
+	fn for_loop() {
+		let source = "for (k, v) in map { total += v } for i in 0..n {}";
+
+		assert_eq!(
+			parse(&mut source.as_bytes()).unwrap(),
+			file(vec![
+				stmt(StmtKind::Expr(expr(ExprKind::For(
+					pattern(PatternKind::Tuple(vec![binding("k"), binding("v")])),
+					reference("map"),
+					Block {
+						stmts: vec![],
+						expr: Some(expr(ExprKind::CompoundAssign(
+							BinaryOp::Add,
+							reference("total"),
+							reference("v"),
+						))),
+					},
+				)))),
+				stmt(StmtKind::Expr(expr(ExprKind::For(
+					binding("i"),
+					expr(ExprKind::Range(num(0.0), reference("n"), false,)),
+					Block {
+						stmts: vec![],
+						expr: None,
+					},
+				)))),
+			])
+		);
+	}
+
+	#[test]
+	fn pipe_placeholder() {
+		let source = "x >> f(1, _);";
+
+		assert_eq!(
+			parse(&mut source.as_bytes()).unwrap(),
+			file(vec![stmt(StmtKind::Expr(binary(
+				BinaryOp::Pipe,
+				reference("x"),
+				expr(ExprKind::FnInvoke(
+					reference("f"),
+					vec![num(1.0), expr(ExprKind::Placeholder)],
+				)),
+			)))])
+		);
+	}
+
+	#[test]
+	fn try_operators() {
+		let source = "let a = f(x)?.y ?>> g;";
+
+		assert_eq!(
+			parse(&mut source.as_bytes()).unwrap(),
+			file(vec![let_decl(
+				"a",
+				binary(
+					BinaryOp::TryPipe,
+					expr(ExprKind::Field(
+						expr(ExprKind::Try(expr(ExprKind::FnInvoke(
+							reference("f"),
+							vec![reference("x")],
+						)))),
+						"y".into(),
+					)),
+					reference("g"),
+				),
+			)])
+		);
+	}
+
+	#[test]
+	fn imports_and_exports() {
+		let source = "import \"lib/shapes\" as shapes;
+			import { area, Point } from \"../geometry\";
+			export fn f() {}
+			export let x = 1;";
+
+		assert_eq!(
+			parse(&mut source.as_bytes()).unwrap(),
+			file(vec![
+				stmt(StmtKind::Import(Box::new(Import {
+					path: "lib/shapes".to_string(),
+					names: ImportNames::Module("shapes".into()),
+				}))),
+				stmt(StmtKind::Import(Box::new(Import {
+					path: "../geometry".to_string(),
+					names: ImportNames::Only(vec!["area".into(), "Point".into()]),
+				}))),
+				stmt(StmtKind::Export(stmt(StmtKind::FnDecl(Box::new(FnDecl {
+					attributes: Vec::new(),
+					constant: false,
+					name: "f".into(),
+					type_params: vec![],
+					params: vec![],
+					return_type: None,
+					body: Block {
+						stmts: vec![],
+						expr: None,
+					},
+				}))))),
+				stmt(StmtKind::Export(let_decl("x", num(1.0)))),
+			])
+		);
+	}
+
+	#[test]
+	fn imports_and_exports_only_at_top_level() {
+		assert!(parse(&mut "{ import \"a\" as a; }".as_bytes()).is_err());
+		assert!(parse(&mut "{ export let x = 1; }".as_bytes()).is_err());
+		assert!(parse(&mut "export x = 1;".as_bytes()).is_err());
+	}
+
+	#[test]
+	fn interfaces_and_impls() {
+		let source = "export interface Shape {
+				fn area(self) -> Num;
+				fn name(self) { \"shape\" }
+			}
+			impl Shape for Square { fn area(self) -> Num { self.side } }";
+
+		let ast = parse(&mut source.as_bytes()).unwrap();
+
+		let receiver = || TypedefField {
+			name: "self".into(),
+			type_expr: None,
+			default_value: None,
+		};
+
+		assert_eq!(
+			ast,
+			file(vec![
+				stmt(StmtKind::Export(stmt(StmtKind::InterfaceDecl(Box::new(
+					InterfaceDecl {
+						name: "Shape".into(),
+						methods: vec![
+							InterfaceMethod {
+								name: "area".into(),
+								type_params: vec![],
+								params: vec![receiver()],
+								return_type: Some(named("Num")),
+								default: None,
+							},
+							InterfaceMethod {
+								name: "name".into(),
+								type_params: vec![],
+								params: vec![receiver()],
+								return_type: None,
+								default: Some(Block {
+									stmts: vec![],
+									expr: Some(string("shape")),
+								}),
+							},
+						],
+					}
+				))))),
+				stmt(StmtKind::ImplDecl(Box::new(ImplDecl {
+					interface: "Shape".into(),
+					type_name: "Square".into(),
+					methods: vec![FnDecl {
+						attributes: Vec::new(),
+						constant: false,
+						name: "area".into(),
+						type_params: vec![],
+						params: vec![receiver()],
+						return_type: Some(named("Num")),
+						body: Block {
+							stmts: vec![],
+							expr: Some(expr(ExprKind::Field(reference("self"), "side".into()))),
+						},
+					}],
+				}))),
+			])
+		);
+	}
+
+	#[test]
+	fn interfaces_and_impls_only_at_top_level() {
+		assert!(parse(&mut "{ interface A {} }".as_bytes()).is_err());
+		assert!(parse(&mut "{ impl A for B {} }".as_bytes()).is_err());
+		assert!(parse(&mut "export impl A for B {}".as_bytes()).is_err());
+		assert!(parse(&mut "interface A { let x = 1; }".as_bytes()).is_err());
+	}
+
+	fn parse_str(source: &str) -> SourceFile {
+		parse(&mut source.as_bytes()).unwrap()
+	}
+
+	fn parse_error(source: &str) -> String {
+		parse(&mut source.as_bytes()).unwrap_err().to_string()
+	}
+
+	#[test]
+	fn macro_operands_keep_precedence() {
+		assert_eq!(
+			parse_str(
+				"macro sub { ($a:expr, $b:expr) => { $a - $b } }
+				let x = 2 * sub!(1 + 1, 3);"
+			),
+			parse_str("let x = 2 * ((1 + 1) - (3));")
+		);
+	}
+
+	#[test]
+	fn macro_repetitions_and_rules() {
+		assert_eq!(
+			parse_str(
+				"macro doubled {
+					() => { [] };
+					($($x:expr),+ $(,)*) => { [$($x * 2),*] };
+				}
+				let a = doubled![];
+				let b = doubled![1, 2 + 3,];"
+			),
+			parse_str("let a = ([]); let b = ([(1) * 2, (2 + 3) * 2]);")
+		);
+	}
+
+	#[test]
+	fn macros_expand_to_statements() {
+		assert_eq!(
+			parse_str(
+				"macro point { ($name:ident = $x:expr, $y:expr) => { let $name = { x: $x, y: $y }; } }
+				point!(p = 1, 2);
+				fn f() { point! { q = 3, 4 } q }"
+			),
+			parse_str("let p = { x: (1), y: (2) }; fn f() { let q = { x: (3), y: (4) }; q }")
+		);
+	}
+
+	#[test]
+	fn macro_bindings_are_renamed() {
+		let ast = parse_str(
+			"macro double { ($x:expr) => { { let tmp = $x; tmp + tmp } } }
+			let tmp = 1;
+			let y = double!(tmp);",
+		);
+
+		let body = Block {
+			stmts: vec![let_decl("tmp$0", group(reference("tmp")))],
+			expr: Some(binary(
+				BinaryOp::Add,
+				reference("tmp$0"),
+				reference("tmp$0"),
+			)),
+		};
+
+		assert_eq!(
+			ast,
+			file(vec![
+				let_decl("tmp", num(1.0)),
+				let_decl("y", group(expr(ExprKind::Block(body)))),
+			])
+		);
+	}
+
+	#[test]
+	fn macro_bindings_are_renamed_only_in_their_scope() {
+		let ast = parse_str(
+			"macro reset { () => { { let old = count; let count = 0; old + count } } }
+			let count = 1;
+			let y = reset!();",
+		);
+
+		let body = Block {
+			stmts: vec![
+				let_decl("old$0", reference("count")),
+				let_decl("count$0", num(0.0)),
+			],
+			expr: Some(binary(
+				BinaryOp::Add,
+				reference("old$0"),
+				reference("count$0"),
+			)),
+		};
+
+		assert_eq!(
+			ast,
+			file(vec![
+				let_decl("count", num(1.0)),
+				let_decl("y", group(expr(ExprKind::Block(body)))),
+			])
+		);
+	}
+
+	#[test]
+	fn macro_functions_are_renamed() {
+		let ast = parse_str(
+			"macro helper { () => { let two = one() + one(); fn one() { 1 } } }
+			helper!();
+			fn one() { 2 }
+			let three = one() + two;",
+		);
+
+		// The name each `let` binds and refers to, or each function declares
+		let name = |expr: ExprId| match &ast.arena[expr].kind {
+			ExprKind::FnInvoke(callee, _) => match ast.arena[*callee].kind {
+				ExprKind::Reference(name) => name,
+				_ => unreachable!(),
+			},
+			ExprKind::Reference(name) => *name,
+			_ => unreachable!(),
+		};
+		let names = ast
+			.stmts
+			.iter()
+			.map(|&stmt| match &ast.arena[stmt].kind {
+				StmtKind::LetDecl(decl) => {
+					let (PatternKind::Binding(bound), ExprKind::Binary(_, left, right)) =
+						(&ast.arena[decl.pattern].kind, &ast.arena[decl.value].kind)
+					else {
+						unreachable!()
+					};
+					format!("let {bound} = {} + {}", name(*left), name(*right))
+				}
+				StmtKind::FnDecl(decl) => format!("fn {}", decl.name),
+				_ => unreachable!(),
+			})
+			.collect::<Vec<_>>();
+
+		assert_eq!(
+			names,
+			[
+				"let two$0 = one$0 + one$0",
+				"fn one$0",
+				"fn one",
+				"let three = one + two",
+			]
+		);
+	}
+
+	#[test]
+	fn macro_spans_point_through_expansions() {
+		let ast = parse_str(
+			"macro one { () => { 1 } }
+			macro two { () => { one!() + one!() } }
+			let x = two!();",
+		);
+
+		let located = |span: Span| format!("{}{}", span, ast.expansions.backtrace(span));
+
+		let StmtKind::LetDecl(decl) = &ast.arena[ast.stmts[0]].kind else {
+			panic!("expected a let");
+		};
+
+		let ExprKind::Group(sum) = ast.arena[decl.value].kind else {
+			panic!("expected an expansion");
+		};
+
+		assert_eq!(
+			located(ast.arena[sum].span),
+			"2:24, in the expansion of `two!` at 3:12"
+		);
+
+		let ExprKind::Binary(_, left, _) = ast.arena[sum].kind else {
+			panic!("expected a sum");
+		};
+
+		let ExprKind::Group(one) = ast.arena[left].kind else {
+			panic!("expected an expansion");
+		};
+
+		assert_eq!(
+			located(ast.arena[one].span),
+			"1:21, in the expansion of `one!` at 2:24, in the expansion of `two!` at 3:12"
+		);
+	}
+
+	#[test]
+	fn macro_errors() {
+		assert_eq!(parse_error("let x = f!(1);"), "Unknown macro `f!` at 1:9");
+		assert_eq!(
+			parse_error("macro m { ($x:ident) => { $x } } let x = m!(1);"),
+			"No rule of `m!` matches its arguments at 1:42"
+		);
+		assert_eq!(
+			parse_error("macro m { ($x:ident) => { $y } }"),
+			"`$y` is not captured by the pattern of its rule at 1:27"
+		);
+		assert_eq!(
+			parse_error("macro m { ($($x:ident)*) => { $x } } m!(a b);"),
+			"Variables repeat a different number of times than the `$( ... )` they are used in at 1:31"
+		);
+		assert_eq!(
+			parse_error("macro forever { () => { forever!() } } let x = forever!();"),
+			format!(
+				"Expanding `forever!` nests more than 32 invocations at 1:25{inner}{inner}{inner}, and 26 more expansions{inner}{inner}{outer}",
+				inner = ", in the expansion of `forever!` at 1:25",
+				outer = ", in the expansion of `forever!` at 1:48",
+			)
+		);
+	}
+
+	#[test]
+	fn attributes() {
+		let ast = parse_str(
+			"@test
+			fn f() { @allow(shadowing) let x = 1; x }
+			#[deprecated(\"use g\")] @inline let h = 1;
+			@allow(unused) export @sealed typedef P = { x: Num }
+			impl A for P { @inline fn m(self) {} }",
+		);
+
+		fn names(attributes: &[Attribute]) -> Vec<&str> {
+			attributes
+				.iter()
+				.map(|attribute| attribute.name.as_str())
+				.collect()
+		}
+
+		let StmtKind::FnDecl(f) = &ast.arena[ast.stmts[0]].kind else {
+			panic!("expected a function");
+		};
+		assert_eq!(names(&f.attributes), vec!["test"]);
+
+		let StmtKind::LetDecl(x) = &ast.arena[f.body.stmts[0]].kind else {
+			panic!("expected a let");
+		};
+		assert_eq!(
+			ast.arena[x.attributes[0].args[0]].kind,
+			ExprKind::Reference("shadowing".into())
+		);
+
+		let StmtKind::LetDecl(h) = &ast.arena[ast.stmts[1]].kind else {
+			panic!("expected a let");
+		};
+		assert_eq!(names(&h.attributes), vec!["deprecated", "inline"]);
+		assert_eq!(
+			ast.arena[Attribute::find(&h.attributes, "deprecated").unwrap().args[0]].kind,
+			ExprKind::StrLit("use g".to_string())
+		);
+
+		let StmtKind::Export(export) = ast.arena[ast.stmts[2]].kind else {
+			panic!("expected an export");
+		};
+		let StmtKind::LetDecl(p) = &ast.arena[export].kind else {
+			panic!("expected a typedef");
+		};
+		assert_eq!(names(&p.attributes), vec!["allow", "sealed"]);
+
+		let StmtKind::ImplDecl(impl_decl) = &ast.arena[ast.stmts[3]].kind else {
+			panic!("expected an impl");
+		};
+		assert_eq!(names(&impl_decl.methods[0].attributes), vec!["inline"]);
+	}
+
+	#[test]
+	fn attributes_only_on_declarations() {
+		assert_eq!(
+			parse_error("@test 1 + 2;"),
+			"Attribute `test` is only allowed on a `let`, typedef, enum or function at 1:1"
+		);
+		assert_eq!(
+			parse_error("let x = 1;\n#[inline] import \"a\" as a;"),
+			"Attribute `inline` is only allowed on a `let`, typedef, enum or function at 2:1"
+		);
+		assert!(parse(&mut "#[inline fn f() {}".as_bytes()).is_err());
+	}
+
+	#[test]
+	fn constants() {
+		let ast = parse_str("const X = 1 + 2; @inline export const fn f() { X }");
+
+		assert_eq!(
+			ast,
+			file(vec![
+				stmt(StmtKind::ConstDecl(Box::new(ConstDecl {
+					attributes: Vec::new(),
+					name: "X".into(),
+					value: binary(BinaryOp::Add, num(1.0), num(2.0)),
+				}))),
+				stmt(StmtKind::Export(stmt(StmtKind::FnDecl(Box::new(FnDecl {
+					attributes: vec![Attribute {
+						name: "inline".into(),
+						args: Vec::new(),
+						span: Span::default(),
+					}],
+					constant: true,
+					name: "f".into(),
+					type_params: Vec::new(),
+					params: Vec::new(),
+					return_type: None,
+					body: Block {
+						stmts: Vec::new(),
+						expr: Some(reference("X")),
+					},
+				}))))),
+			])
+		);
+		assert!(parse(&mut "const (a, b) = (1, 2);".as_bytes()).is_err());
+	}
+}
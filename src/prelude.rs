@@ -0,0 +1,31 @@
+use std::sync::LazyLock;
+
+use crate::parse::{parse, SourceFile};
+
+/// Declarations every file can use without declaring them itself, including the
+/// interfaces through which operators are overloaded
+pub const SOURCE: &str = "
+enum Result = Ok(value) | Err(error)
+enum Option = Some(value) | None
+
+interface Add { fn add(self, other); }
+interface Subtract { fn subtract(self, other); }
+interface Multiply { fn multiply(self, other); }
+interface Divide { fn divide(self, other); }
+interface Modulo { fn modulo(self, other); }
+interface Power { fn power(self, other); }
+interface BitAnd { fn bit_and(self, other); }
+interface BitOr { fn bit_or(self, other); }
+interface BitXor { fn bit_xor(self, other); }
+interface Equal { fn equals(self, other) -> Bool; }
+interface Compare { fn compare(self, other) -> Num; }
+interface Index { fn index(self, key); }
+";
+
+/// The parsed prelude, which is in an outer scope of every file
+pub fn prelude() -> &'static SourceFile {
+	static PRELUDE: LazyLock<SourceFile> =
+		LazyLock::new(|| parse(&mut SOURCE.as_bytes()).expect("the prelude is valid"));
+
+	&PRELUDE
+}
@@ -0,0 +1,3 @@
+mod printer;
+
+pub use printer::*;
@@ -0,0 +1,1951 @@
+use std::{
+	collections::{HashMap, HashSet},
+	fmt::Write,
+};
+
+use crate::{
+	ast::{
+		walk_expr, walk_fn_decl, walk_pattern, walk_stmt, walk_typedef_field, Arena, Attribute,
+		BinaryOp, Block, EnumVariant, ExprId, ExprKind, FnDecl, ImportNames, PatternId,
+		PatternKind, RecordLit, StmtId, StmtKind, TypeExprId, TypeExprKind, TypedefField, Visitor,
+	},
+	intern::Symbol,
+	parse::SourceFile,
+};
+
+/// Turns a file back into source, which parses to the same tree.
+///
+/// Parentheses are only added where the tree would otherwise parse differently,
+/// besides those of `Group`s, which are always kept. Names with a `$`, which
+/// macro hygiene and lowering make up, are printed with a `_` in its place and
+/// as many more `_`s at the end as it takes not to clash with another name.
+pub fn print(file: &SourceFile) -> String {
+	let mut names = Names::new(&file.arena);
+	file.stmts.iter().for_each(|&stmt| names.visit_stmt(stmt));
+	let mut printer = Printer::new(&file.arena, names);
+
+	for &stmt in &file.stmts {
+		printer.stmt(stmt, false);
+		printer.out.push('\n');
+	}
+
+	printer.out
+}
+
+/// Prints a statement as it would be written at the top level of a file
+pub fn print_stmt(arena: &Arena, stmt: StmtId) -> String {
+	let mut names = Names::new(arena);
+	names.visit_stmt(stmt);
+	let mut printer = Printer::new(arena, names);
+	printer.stmt(stmt, false);
+	printer.out
+}
+
+/// Prints an expression as it would be written inside parentheses, unless it
+/// is a typedef or enum, which can only be written as the declaration binding
+/// it
+pub fn print_expr(arena: &Arena, expr: ExprId) -> Option<String> {
+	if matches!(arena[expr].kind, ExprKind::Typedef(..) | ExprKind::Enum(..)) {
+		return None;
+	}
+
+	let mut names = Names::new(arena);
+	names.visit_expr(expr);
+	let mut printer = Printer::new(arena, names);
+	printer.expr(expr, Precedence::Assignment);
+	Some(printer.out)
+}
+
+/// How tightly an expression binds, from loosest to tightest, following the
+/// levels of the parser
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum Precedence {
+	Assignment,
+	Range,
+	Or,
+	And,
+	Comparison,
+	Pipe,
+	BitOr,
+	BitXor,
+	BitAnd,
+	Term,
+	Factor,
+	Unary,
+	Power,
+	Postfix,
+	Primary,
+}
+
+impl Precedence {
+	const LEVELS: [Self; 15] = [
+		Self::Assignment,
+		Self::Range,
+		Self::Or,
+		Self::And,
+		Self::Comparison,
+		Self::Pipe,
+		Self::BitOr,
+		Self::BitXor,
+		Self::BitAnd,
+		Self::Term,
+		Self::Factor,
+		Self::Unary,
+		Self::Power,
+		Self::Postfix,
+		Self::Primary,
+	];
+
+	fn of(kind: &ExprKind) -> Self {
+		match kind {
+			ExprKind::Assign(..) | ExprKind::CompoundAssign(..) => Self::Assignment,
+			ExprKind::Range(..) => Self::Range,
+			ExprKind::Binary(op, ..) => Self::of_binary(*op),
+			ExprKind::Unary(..) => Self::Unary,
+			ExprKind::FnInvoke(..)
+			| ExprKind::Index(..)
+			| ExprKind::Slice(..)
+			| ExprKind::Field(..)
+			| ExprKind::Try(_)
+			| ExprKind::Record(RecordLit {
+				type_name: Some(_), ..
+			}) => Self::Postfix,
+			_ => Self::Primary,
+		}
+	}
+
+	fn of_binary(op: BinaryOp) -> Self {
+		match op {
+			BinaryOp::Or => Self::Or,
+			BinaryOp::And => Self::And,
+			BinaryOp::Equal
+			| BinaryOp::NotEqual
+			| BinaryOp::LessThan
+			| BinaryOp::LessThanOrEqual
+			| BinaryOp::GreaterThan
+			| BinaryOp::GreaterThanOrEqual => Self::Comparison,
+			BinaryOp::Pipe | BinaryOp::TryPipe => Self::Pipe,
+			BinaryOp::BitOr => Self::BitOr,
+			BinaryOp::BitXor => Self::BitXor,
+			BinaryOp::BitAnd => Self::BitAnd,
+			BinaryOp::Add | BinaryOp::Subtract => Self::Term,
+			BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Modulo => Self::Factor,
+			BinaryOp::Power => Self::Power,
+		}
+	}
+
+	/// The levels the left and right operands of `op` have to bind at least as
+	/// tightly as to go without parentheses
+	fn operands(op: BinaryOp) -> (Self, Self) {
+		match Self::of_binary(op) {
+			// Comparisons can't be chained, and `^` is right associative with a
+			// postfix expression as its base
+			Self::Comparison => (Self::Pipe, Self::Pipe),
+			Self::Power => (Self::Postfix, Self::Unary),
+			level => (level, Self::LEVELS[level as usize + 1]),
+		}
+	}
+}
+
+struct Printer<'a> {
+	arena: &'a Arena,
+	out: String,
+	indent: usize,
+	/// Whether this is the condition of an `if`, `while`, `for` or `match`
+	/// outside of any delimiters, where the parser would take the braces of a
+	/// record literal for the body
+	in_condition: bool,
+	/// Whether the next expression starts a statement, which the parser ends
+	/// right after a leading block-like expression or braces
+	stmt_start: bool,
+	/// The names used in the tree, which renamed names mustn't clash with
+	taken: HashSet<String>,
+	/// What each name with a `$` is printed as
+	renamed: HashMap<Symbol, String>,
+}
+
+/// Collects the names that can be bound or referred to in a tree
+struct Names<'ast> {
+	arena: &'ast Arena,
+	names: HashSet<String>,
+}
+
+impl<'ast> Names<'ast> {
+	fn new(arena: &'ast Arena) -> Self {
+		Self {
+			arena,
+			names: HashSet::new(),
+		}
+	}
+}
+
+impl<'ast> Visitor<'ast> for Names<'ast> {
+	fn arena(&self) -> &'ast Arena {
+		self.arena
+	}
+
+	fn visit_stmt(&mut self, id: StmtId) {
+		if let StmtKind::ConstDecl(decl) = &self.arena[id].kind {
+			self.names.insert(decl.name.to_string());
+		}
+		walk_stmt(self, id);
+	}
+
+	fn visit_expr(&mut self, id: ExprId) {
+		match &self.arena[id].kind {
+			ExprKind::Reference(name) => {
+				self.names.insert(name.to_string());
+			}
+			ExprKind::Lambda(params, _) => {
+				self.names
+					.extend(params.iter().map(|param| param.to_string()));
+			}
+			_ => {}
+		}
+		walk_expr(self, id);
+	}
+
+	fn visit_fn_decl(&mut self, decl: &'ast FnDecl) {
+		self.names.insert(decl.name.to_string());
+		walk_fn_decl(self, decl);
+	}
+
+	fn visit_typedef_field(&mut self, field: &'ast TypedefField) {
+		self.names.insert(field.name.to_string());
+		walk_typedef_field(self, field);
+	}
+
+	fn visit_pattern(&mut self, id: PatternId) {
+		if let PatternKind::Binding(name) | PatternKind::Rest(Some(name)) = &self.arena[id].kind {
+			self.names.insert(name.to_string());
+		}
+		walk_pattern(self, id);
+	}
+}
+
+impl<'a> Printer<'a> {
+	fn newline(&mut self) {
+		self.out.push('\n');
+		self.out.extend(std::iter::repeat_n('\t', self.indent));
+	}
+
+	fn indented(&mut self, f: impl FnOnce(&mut Self)) {
+		self.indent += 1;
+		f(self);
+		self.indent -= 1;
+		self.newline();
+	}
+
+	fn new(arena: &'a Arena, names: Names) -> Self {
+		Printer {
+			arena,
+			out: String::new(),
+			indent: 0,
+			in_condition: false,
+			stmt_start: false,
+			taken: names.names,
+			renamed: HashMap::new(),
+		}
+	}
+
+	/// Prints a name that can be bound or referred to, renaming it if it has a
+	/// `$`, which can't be written in source
+	fn name(&mut self, name: Symbol) {
+		let text = name.as_str();
+		if !text.contains('$') {
+			self.out.push_str(text);
+			return;
+		}
+
+		if !self.renamed.contains_key(&name) {
+			let mut renamed = text.replace('$', "_");
+			while self.taken.contains(&renamed) {
+				renamed.push('_');
+			}
+
+			self.taken.insert(renamed.clone());
+			self.renamed.insert(name, renamed);
+		}
+
+		self.out.push_str(&self.renamed[&name]);
+	}
+
+	/// Prints what `f` prints inside delimiters, which like in the parser lift
+	/// the restriction on records in conditions
+	fn delimited(&mut self, f: impl FnOnce(&mut Self)) {
+		let in_condition = std::mem::replace(&mut self.in_condition, false);
+		f(self);
+		self.in_condition = in_condition;
+	}
+
+	fn comma_separated<T>(&mut self, items: &[T], mut item: impl FnMut(&mut Self, &T)) {
+		for (i, x) in items.iter().enumerate() {
+			if i > 0 {
+				self.out.push_str(", ");
+			}
+			item(self, x);
+		}
+	}
+
+	fn names(&mut self, names: &[Symbol]) {
+		self.comma_separated(names, |printer, name| printer.out.push_str(name.as_str()));
+	}
+
+	/// Prints a statement, which needs its semicolon even after a block-like
+	/// expression when it `ends_block`, as it would be the block's value otherwise
+	fn stmt(&mut self, id: StmtId, ends_block: bool) {
+		match &self.arena[id].kind {
+			StmtKind::LetDecl(decl) => {
+				self.attributes(&decl.attributes);
+
+				match (&self.arena[decl.pattern].kind, &self.arena[decl.value].kind) {
+					(PatternKind::Binding(name), ExprKind::Typedef(type_params, fields))
+						if !decl.mutable =>
+					{
+						self.typedef(name.as_str(), type_params, fields);
+					}
+					(PatternKind::Binding(name), ExprKind::Enum(type_params, variants))
+						if !decl.mutable =>
+					{
+						self.enum_decl(name.as_str(), type_params, variants);
+					}
+					_ => {
+						self.out.push_str("let ");
+						if decl.mutable {
+							self.out.push_str("mut ");
+						}
+						self.pattern(decl.pattern);
+						self.out.push_str(" = ");
+						self.expr(decl.value, Precedence::Assignment);
+						self.out.push(';');
+					}
+				}
+			}
+			StmtKind::ConstDecl(decl) => {
+				self.attributes(&decl.attributes);
+				self.out.push_str("const ");
+				self.name(decl.name);
+				self.out.push_str(" = ");
+				self.expr(decl.value, Precedence::Assignment);
+				self.out.push(';');
+			}
+			StmtKind::FnDecl(decl) => self.fn_decl(decl),
+			StmtKind::InterfaceDecl(decl) => {
+				write!(self.out, "interface {} {{", decl.name).unwrap();
+
+				if !decl.methods.is_empty() {
+					self.indented(|printer| {
+						for method in &decl.methods {
+							printer.newline();
+							printer.signature(
+								method.name,
+								&method.type_params,
+								&method.params,
+								method.return_type,
+							);
+
+							match &method.default {
+								Some(default) => {
+									printer.out.push(' ');
+									printer.block(default);
+								}
+								None => printer.out.push(';'),
+							}
+						}
+					});
+				}
+
+				self.out.push('}');
+			}
+			StmtKind::ImplDecl(decl) => {
+				write!(
+					self.out,
+					"impl {} for {} {{",
+					decl.interface, decl.type_name
+				)
+				.unwrap();
+
+				if !decl.methods.is_empty() {
+					self.indented(|printer| {
+						for method in &decl.methods {
+							printer.newline();
+							printer.fn_decl(method);
+						}
+					});
+				}
+
+				self.out.push('}');
+			}
+			StmtKind::Import(import) => match &import.names {
+				ImportNames::Module(name) => {
+					write!(self.out, "import \"{}\" as {name};", import.path).unwrap();
+				}
+				ImportNames::Only(names) => {
+					self.out.push_str("import { ");
+					self.names(names);
+					write!(self.out, " }} from \"{}\";", import.path).unwrap();
+				}
+			},
+			StmtKind::Export(exported) => {
+				self.out.push_str("export ");
+				self.stmt(*exported, ends_block);
+			}
+			StmtKind::Expr(expr) => {
+				self.stmt_expr(*expr);
+
+				if ends_block || !self.arena[*expr].kind.is_block_like() {
+					self.out.push(';');
+				}
+			}
+		}
+	}
+
+	fn attributes(&mut self, attributes: &[Attribute]) {
+		for attribute in attributes {
+			write!(self.out, "@{}", attribute.name).unwrap();
+
+			if !attribute.args.is_empty() {
+				self.out.push('(');
+				self.comma_separated(&attribute.args, |printer, &arg| {
+					printer.expr(arg, Precedence::Assignment);
+				});
+				self.out.push(')');
+			}
+
+			self.newline();
+		}
+	}
+
+	fn typedef(&mut self, name: &str, type_params: &[Symbol], fields: &[TypedefField]) {
+		self.declared_name("typedef", name);
+		self.type_params(type_params);
+		self.out.push_str(" = ");
+
+		if fields.is_empty() {
+			self.out.push_str("{}");
+			return;
+		}
+
+		self.out.push_str("{ ");
+		self.comma_separated(fields, Self::typedef_field);
+		self.out.push_str(" }");
+	}
+
+	fn enum_decl(&mut self, name: &str, type_params: &[Symbol], variants: &[EnumVariant]) {
+		self.declared_name("enum", name);
+		self.type_params(type_params);
+		self.out.push_str(" = ");
+
+		for (i, variant) in variants.iter().enumerate() {
+			if i > 0 {
+				self.out.push_str(" | ");
+			}
+
+			self.out.push_str(variant.name.as_str());
+
+			if !variant.fields.is_empty() {
+				self.out.push('(');
+				self.comma_separated(&variant.fields, Self::typedef_field);
+				self.out.push(')');
+			}
+		}
+
+		// Without it, a bare last variant would take parentheses starting the next
+		// statement for its fields
+		self.out.push(';');
+	}
+
+	fn declared_name(&mut self, keyword: &str, name: &str) {
+		self.out.push_str(keyword);
+
+		if !name.is_empty() {
+			write!(self.out, " {name}").unwrap();
+		}
+	}
+
+	fn type_params(&mut self, type_params: &[Symbol]) {
+		if !type_params.is_empty() {
+			self.out.push('<');
+			self.names(type_params);
+			self.out.push('>');
+		}
+	}
+
+	fn typedef_field(&mut self, field: &TypedefField) {
+		self.name(field.name);
+
+		if let Some(type_expr) = field.type_expr {
+			self.out.push_str(": ");
+			self.type_expr(type_expr);
+		}
+
+		if let Some(default) = field.default_value {
+			self.out.push_str(" = ");
+			self.expr(default, Precedence::Assignment);
+		}
+	}
+
+	fn fn_decl(&mut self, decl: &FnDecl) {
+		self.attributes(&decl.attributes);
+
+		if decl.constant {
+			self.out.push_str("const ");
+		}
+
+		self.signature(decl.name, &decl.type_params, &decl.params, decl.return_type);
+		self.out.push(' ');
+		self.block(&decl.body);
+	}
+
+	fn signature(
+		&mut self,
+		name: Symbol,
+		type_params: &[Symbol],
+		params: &[TypedefField],
+		return_type: Option<TypeExprId>,
+	) {
+		self.out.push_str("fn ");
+		self.name(name);
+		self.type_params(type_params);
+		self.out.push('(');
+		self.comma_separated(params, Self::typedef_field);
+		self.out.push(')');
+
+		if let Some(return_type) = return_type {
+			self.out.push_str(" -> ");
+			self.type_expr(return_type);
+		}
+	}
+
+	/// Prints a block, on a single line if it is only an expression
+	fn block(&mut self, block: &Block) {
+		match (block.stmts.as_slice(), block.expr) {
+			([], None) => self.out.push_str("{}"),
+			([], Some(expr)) if !self.arena[expr].kind.is_block_like() => {
+				self.out.push_str("{ ");
+				self.stmt_expr(expr);
+				self.out.push_str(" }");
+			}
+			(stmts, tail) => {
+				self.out.push('{');
+				self.indented(|printer| {
+					for (i, &stmt) in stmts.iter().enumerate() {
+						printer.newline();
+						printer.stmt(stmt, tail.is_none() && i == stmts.len() - 1);
+					}
+
+					if let Some(expr) = tail {
+						printer.newline();
+						printer.stmt_expr(expr);
+					}
+				});
+				self.out.push('}');
+			}
+		}
+	}
+
+	/// Prints an expression where a statement can start
+	fn stmt_expr(&mut self, id: ExprId) {
+		// A whole block-like expression is fine
+		self.stmt_start = Precedence::of(&self.arena[id].kind) != Precedence::Primary;
+		self.expr(id, Precedence::Assignment);
+	}
+
+	fn needs_parens(&self, id: ExprId, level: Precedence) -> bool {
+		let kind = &self.arena[id].kind;
+		let record = matches!(
+			kind,
+			ExprKind::Record(RecordLit {
+				type_name: Some(_),
+				..
+			})
+		);
+
+		Precedence::of(kind) < level || (record && self.in_condition)
+	}
+
+	fn parenthesized(&mut self, expr: ExprId) {
+		self.delimited(|printer| {
+			printer.out.push('(');
+			printer.expr(expr, Precedence::Assignment);
+			printer.out.push(')');
+		});
+	}
+
+	/// Prints the condition of an `if`, `while`, `for` or `match`
+	fn condition(&mut self, expr: ExprId) {
+		let in_condition = std::mem::replace(&mut self.in_condition, true);
+		self.expr(expr, Precedence::Assignment);
+		self.in_condition = in_condition;
+	}
+
+	/// Prints an expression where it has to bind at least as tightly as `level`,
+	/// parenthesising it otherwise
+	fn expr(&mut self, id: ExprId, level: Precedence) {
+		let stmt_start = std::mem::take(&mut self.stmt_start);
+		let kind = &self.arena[id].kind;
+		let braced = matches!(
+			kind,
+			ExprKind::Map(_)
+				| ExprKind::Record(RecordLit {
+					type_name: None,
+					..
+				})
+		);
+
+		if self.needs_parens(id, level) || (stmt_start && (braced || kind.is_block_like())) {
+			self.parenthesized(id);
+			return;
+		}
+
+		// Expressions starting with an operand pass the start of the statement on
+		// to it
+		let leftmost = |printer: &mut Self, operand: ExprId, level| {
+			printer.stmt_start = stmt_start;
+			printer.expr(operand, level);
+		};
+
+		match kind {
+			&ExprKind::Binary(op, left, right) => {
+				let (left_level, right_level) = Precedence::operands(op);
+				leftmost(self, left, left_level);
+				write!(self.out, " {} ", op.symbol()).unwrap();
+				self.expr(right, right_level);
+			}
+			ExprKind::Unary(op, operand) => {
+				self.out.push_str(op.symbol());
+				self.expr(*operand, Precedence::Unary);
+			}
+			ExprKind::FnInvoke(callee, args) => {
+				leftmost(self, *callee, Precedence::Postfix);
+				self.delimited(|printer| {
+					printer.out.push('(');
+					printer.comma_separated(args, |printer, &arg| {
+						printer.expr(arg, Precedence::Assignment);
+					});
+					printer.out.push(')');
+				});
+			}
+			ExprKind::Typedef(type_params, fields) => self.typedef("", type_params, fields),
+			ExprKind::Enum(type_params, variants) => self.enum_decl("", type_params, variants),
+			ExprKind::Block(block) => self.delimited(|printer| printer.block(block)),
+			ExprKind::If(condition, then_branch, else_branch) => {
+				self.out.push_str("if ");
+				self.condition(*condition);
+				self.out.push(' ');
+				self.block(then_branch);
+
+				if let Some(else_branch) = *else_branch {
+					self.out.push_str(" else ");
+
+					// Unlike a block expression, the else block isn't delimited
+					match &self.arena[else_branch].kind {
+						ExprKind::Block(block) => self.block(block),
+						_ => self.expr(else_branch, Precedence::Primary),
+					}
+				}
+			}
+			ExprKind::While(condition, body) => {
+				self.out.push_str("while ");
+				self.condition(*condition);
+				self.out.push(' ');
+				self.block(body);
+			}
+			ExprKind::For(pattern, iterable, body) => {
+				self.out.push_str("for ");
+				self.pattern(*pattern);
+				self.out.push_str(" in ");
+				self.condition(*iterable);
+				self.out.push(' ');
+				self.block(body);
+			}
+			ExprKind::Match(value, arms) => {
+				self.out.push_str("match ");
+				self.condition(*value);
+				self.out.push_str(" {");
+
+				if !arms.is_empty() {
+					self.delimited(|printer| {
+						printer.indented(|printer| {
+							for arm in arms {
+								printer.newline();
+								printer.pattern(arm.pattern);
+
+								if let Some(guard) = arm.guard {
+									printer.out.push_str(" if ");
+									printer.expr(guard, Precedence::Assignment);
+								}
+
+								printer.out.push_str(" => ");
+								printer.stmt_expr(arm.body);
+
+								if !printer.arena[arm.body].kind.is_block_like() {
+									printer.out.push(',');
+								}
+							}
+						});
+					});
+				}
+
+				self.out.push('}');
+			}
+			ExprKind::Lambda(params, body) => self.delimited(|printer| {
+				printer.out.push('(');
+				printer.comma_separated(params, |printer, param| printer.name(*param));
+				printer.out.push_str(") => ");
+				printer.block(body);
+			}),
+			ExprKind::NumLit(num) => write!(self.out, "{num}").unwrap(),
+			ExprKind::StrLit(str) => write!(self.out, "\"{str}\"").unwrap(),
+			ExprKind::BoolLit(bool) => write!(self.out, "{bool}").unwrap(),
+			ExprKind::UnitLit => self.out.push_str("()"),
+			ExprKind::NilLit => self.out.push_str("nil"),
+			ExprKind::Group(inner) => self.parenthesized(*inner),
+			ExprKind::List(items) => self.delimited(|printer| {
+				printer.out.push('[');
+				printer.comma_separated(items, |printer, &item| {
+					printer.expr(item, Precedence::Assignment);
+				});
+				printer.out.push(']');
+			}),
+			ExprKind::Map(entries) if entries.is_empty() => self.out.push_str("{:}"),
+			ExprKind::Map(entries) => self.delimited(|printer| {
+				printer.out.push_str("{ ");
+
+				for (i, &(key, value)) in entries.iter().enumerate() {
+					// The first key is parsed like a statement, to tell maps
+					// from blocks
+					if i == 0 {
+						printer.stmt_expr(key);
+					} else {
+						printer.out.push_str(", ");
+						printer.expr(key, Precedence::Assignment);
+					}
+
+					printer.out.push_str(": ");
+					printer.expr(value, Precedence::Assignment);
+				}
+
+				printer.out.push_str(" }");
+			}),
+			ExprKind::Tuple(items) => self.delimited(|printer| {
+				printer.out.push('(');
+				printer.comma_separated(items, |printer, &item| {
+					printer.expr(item, Precedence::Assignment);
+				});
+
+				// `(a)` is a group while `(a,)` is a tuple
+				if items.len() == 1 {
+					printer.out.push(',');
+				}
+
+				printer.out.push(')');
+			}),
+			&ExprKind::Index(target, index) => {
+				leftmost(self, target, Precedence::Postfix);
+				self.delimited(|printer| {
+					printer.out.push('[');
+					printer.expr(index, Precedence::Or);
+					printer.out.push(']');
+				});
+			}
+			&ExprKind::Slice(target, start, end) => {
+				leftmost(self, target, Precedence::Postfix);
+				self.delimited(|printer| {
+					printer.out.push('[');
+					if let Some(start) = start {
+						printer.expr(start, Precedence::Or);
+					}
+					printer.out.push_str("..");
+					if let Some(end) = end {
+						printer.expr(end, Precedence::Or);
+					}
+					printer.out.push(']');
+				});
+			}
+			ExprKind::Record(record) => {
+				if let Some(type_name) = &record.type_name {
+					write!(self.out, "{type_name} ").unwrap();
+				}
+
+				if record.base.is_none() && record.fields.is_empty() {
+					self.out.push_str("{}");
+					return;
+				}
+
+				self.delimited(|printer| {
+					printer.out.push_str("{ ");
+
+					if let Some(base) = record.base {
+						printer.out.push_str("..");
+						printer.expr(base, Precedence::Assignment);
+
+						if !record.fields.is_empty() {
+							printer.out.push_str(", ");
+						}
+					}
+
+					printer.comma_separated(&record.fields, |printer, field| {
+						write!(printer.out, "{}: ", field.name).unwrap();
+						printer.expr(field.value, Precedence::Assignment);
+					});
+					printer.out.push_str(" }");
+				});
+			}
+			&ExprKind::Range(start, end, inclusive) => {
+				leftmost(self, start, Precedence::Or);
+				self.out.push_str(if inclusive { "..=" } else { ".." });
+				self.expr(end, Precedence::Or);
+			}
+			&ExprKind::Try(operand) => {
+				leftmost(self, operand, Precedence::Postfix);
+				self.out.push('?');
+			}
+			&ExprKind::Field(target, field) => {
+				leftmost(self, target, Precedence::Postfix);
+				write!(self.out, ".{field}").unwrap();
+			}
+			&ExprKind::Assign(target, value) => {
+				leftmost(self, target, Precedence::Range);
+				self.out.push_str(" = ");
+				self.expr(value, Precedence::Assignment);
+			}
+			&ExprKind::CompoundAssign(op, target, value) => {
+				leftmost(self, target, Precedence::Range);
+				write!(self.out, " {}= ", op.symbol()).unwrap();
+				self.expr(value, Precedence::Assignment);
+			}
+			ExprKind::Reference(name) => self.name(*name),
+			ExprKind::Placeholder => self.out.push('_'),
+		}
+	}
+
+	fn pattern(&mut self, id: PatternId) {
+		match &self.arena[id].kind {
+			PatternKind::Wildcard => self.out.push('_'),
+			PatternKind::Binding(name) => self.name(*name),
+			PatternKind::Literal(literal) => self.expr(*literal, Precedence::Assignment),
+			PatternKind::Tuple(items) => {
+				self.out.push('(');
+				self.comma_separated(items, |printer, &item| printer.pattern(item));
+				if items.len() == 1 {
+					self.out.push(',');
+				}
+				self.out.push(')');
+			}
+			PatternKind::List(items) => {
+				self.out.push('[');
+				self.comma_separated(items, |printer, &item| printer.pattern(item));
+				self.out.push(']');
+			}
+			PatternKind::Rest(name) => {
+				self.out.push_str("..");
+				if let Some(name) = name {
+					self.name(*name);
+				}
+			}
+			PatternKind::Record { type_name, fields } => {
+				if let Some(type_name) = type_name {
+					write!(self.out, "{type_name} ").unwrap();
+				}
+
+				if fields.is_empty() {
+					self.out.push_str("{}");
+					return;
+				}
+
+				self.out.push_str("{ ");
+				self.comma_separated(fields, |printer, field| {
+					printer.out.push_str(field.name.as_str());
+
+					// `name` alone is short for `name: name`
+					let pattern = &printer.arena[field.pattern].kind;
+					if !matches!(pattern, PatternKind::Binding(name) if *name == field.name) {
+						printer.out.push_str(": ");
+						printer.pattern(field.pattern);
+					}
+				});
+				self.out.push_str(" }");
+			}
+			PatternKind::Variant {
+				enum_name,
+				variant,
+				fields,
+			} => {
+				if let Some(enum_name) = enum_name {
+					write!(self.out, "{enum_name}.").unwrap();
+				}
+
+				self.out.push_str(variant.as_str());
+
+				// A bare name would be a binding
+				if !fields.is_empty() || enum_name.is_none() {
+					self.out.push('(');
+					self.comma_separated(fields, |printer, &field| printer.pattern(field));
+					self.out.push(')');
+				}
+			}
+			PatternKind::Or(alternatives) => {
+				for (i, &alternative) in alternatives.iter().enumerate() {
+					if i > 0 {
+						self.out.push_str(" | ");
+					}
+
+					if matches!(self.arena[alternative].kind, PatternKind::Or(_)) {
+						self.out.push('(');
+						self.pattern(alternative);
+						self.out.push(')');
+					} else {
+						self.pattern(alternative);
+					}
+				}
+			}
+		}
+	}
+
+	fn type_expr(&mut self, id: TypeExprId) {
+		match &self.arena[id].kind {
+			TypeExprKind::Named(name, args) => {
+				self.out.push_str(name.as_str());
+
+				if !args.is_empty() {
+					self.out.push('<');
+					self.comma_separated(args, |printer, &arg| printer.type_expr(arg));
+					self.out.push('>');
+				}
+			}
+			TypeExprKind::Function(params, result) => {
+				self.out.push('(');
+				self.comma_separated(params, |printer, &param| printer.type_expr(param));
+				self.out.push_str(") -> ");
+				self.type_expr(*result);
+			}
+			TypeExprKind::List(item) => {
+				self.out.push('[');
+				self.type_expr(*item);
+				self.out.push(']');
+			}
+			TypeExprKind::Map(key, value) => {
+				self.out.push('{');
+				self.type_expr(*key);
+				self.out.push_str(": ");
+				self.type_expr(*value);
+				self.out.push('}');
+			}
+			TypeExprKind::Tuple(items) => {
+				self.out.push('(');
+				self.comma_separated(items, |printer, &item| printer.type_expr(item));
+				if items.len() == 1 {
+					self.out.push(',');
+				}
+				self.out.push(')');
+			}
+			TypeExprKind::Optional(inner) => {
+				// `(A) -> B?` returns an optional
+				if matches!(self.arena[*inner].kind, TypeExprKind::Function(..)) {
+					self.out.push('(');
+					self.type_expr(*inner);
+					self.out.push(')');
+				} else {
+					self.type_expr(*inner);
+				}
+
+				self.out.push('?');
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{fmt, mem, rc::Rc};
+
+	use proptest::{collection::vec, option, prelude::*, strategy::Union};
+
+	use super::*;
+	use crate::{
+		ast::{
+			fold_expr, fold_pattern, fold_stmt, fold_typedef_field, ConstDecl, FieldInit,
+			FieldPattern, Fold, ImplDecl, Import, InterfaceDecl, InterfaceMethod, LetDecl,
+			MatchArm, Span, UnaryOp,
+		},
+		lower::lower,
+		parse::parse,
+	};
+
+	/// Removes every group, leaving only the structure parentheses stand for
+	struct Ungroup<'ast> {
+		source: &'ast Arena,
+		target: Arena,
+	}
+
+	impl<'ast> Fold<'ast> for Ungroup<'ast> {
+		fn source(&self) -> &'ast Arena {
+			self.source
+		}
+
+		fn target(&mut self) -> &mut Arena {
+			&mut self.target
+		}
+
+		fn fold_expr(&mut self, id: ExprId) -> ExprId {
+			match self.source[id].kind {
+				ExprKind::Group(inner) => self.fold_expr(inner),
+				_ => fold_expr(self, id),
+			}
+		}
+	}
+
+	/// Renames names with a `$` like the printer does when none clash
+	struct Rename<'ast> {
+		source: &'ast Arena,
+		target: Arena,
+	}
+
+	impl Rename<'_> {
+		fn name(name: Symbol) -> Symbol {
+			Symbol::intern(&name.as_str().replace('$', "_"))
+		}
+	}
+
+	impl<'ast> Fold<'ast> for Rename<'ast> {
+		fn source(&self) -> &'ast Arena {
+			self.source
+		}
+
+		fn target(&mut self) -> &mut Arena {
+			&mut self.target
+		}
+
+		fn fold_stmt(&mut self, id: StmtId) -> StmtId {
+			let stmt = fold_stmt(self, id);
+			self.target.update_stmt(stmt, |kind| {
+				if let StmtKind::ConstDecl(decl) = kind {
+					decl.name = Self::name(decl.name);
+				}
+			});
+			stmt
+		}
+
+		fn fold_expr(&mut self, id: ExprId) -> ExprId {
+			let expr = fold_expr(self, id);
+			self.target.update_expr(expr, |kind| match kind {
+				ExprKind::Reference(name) => *name = Self::name(*name),
+				ExprKind::Lambda(params, _) => {
+					params
+						.iter_mut()
+						.for_each(|param| *param = Self::name(*param));
+				}
+				_ => {}
+			});
+			expr
+		}
+
+		fn fold_typedef_field(&mut self, field: &'ast TypedefField) -> TypedefField {
+			TypedefField {
+				name: Self::name(field.name),
+				..fold_typedef_field(self, field)
+			}
+		}
+
+		fn fold_pattern(&mut self, id: PatternId) -> PatternId {
+			let pattern = &self.source[id];
+			let kind = match pattern.kind {
+				PatternKind::Binding(name) => PatternKind::Binding(Self::name(name)),
+				PatternKind::Rest(name) => PatternKind::Rest(name.map(Self::name)),
+				_ => return fold_pattern(self, id),
+			};
+			self.target.alloc_pattern(kind, pattern.span)
+		}
+	}
+
+	fn parse_str(source: &str) -> SourceFile {
+		parse(&mut source.as_bytes()).unwrap()
+	}
+
+	/// Folds every statement of a file into a file of its own
+	fn fold_file<'ast>(mut folder: impl Fold<'ast>, file: &SourceFile) -> SourceFile {
+		let stmts = file
+			.stmts
+			.iter()
+			.map(|&stmt| folder.fold_stmt(stmt))
+			.collect();
+
+		SourceFile {
+			arena: mem::take(folder.target()),
+			stmts,
+			..Default::default()
+		}
+	}
+
+	fn ungroup(file: &SourceFile) -> SourceFile {
+		fold_file(
+			Ungroup {
+				source: &file.arena,
+				target: Arena::default(),
+			},
+			file,
+		)
+	}
+
+	fn rename(file: &SourceFile) -> SourceFile {
+		fold_file(
+			Rename {
+				source: &file.arena,
+				target: Arena::default(),
+			},
+			file,
+		)
+	}
+
+	#[test]
+	fn minimal_parentheses() {
+		let cases = [
+			(
+				"let a = ((1 + 2) + 3) * (4 - (5 - 6));",
+				"let a = (1 + 2 + 3) * (4 - (5 - 6));",
+			),
+			(
+				"let a = (-(2 ^ 2)) + (-2) ^ (2 ^ 2);",
+				"let a = -2 ^ 2 + (-2) ^ 2 ^ 2;",
+			),
+			("let a = (2 ^ 3) ^ -(f(x).y);", "let a = (2 ^ 3) ^ -f(x).y;"),
+			(
+				"let a = (x >> f) < (y >> g(_));",
+				"let a = x >> f < y >> g(_);",
+			),
+			(
+				"let a = (a < b) == (c && d);",
+				"let a = (a < b) == (c && d);",
+			),
+			(
+				"let a = (a || b) && !(c | d ~ e);",
+				"let a = (a || b) && !(c | d ~ e);",
+			),
+			("x = (y += (1..2));", "x = y += 1..2;"),
+			(
+				"let a = (1..2)[(3..4)][5..(6 + 7)];",
+				"let a = (1..2)[(3..4)][5..6 + 7];",
+			),
+			("let a = -(x?)?.y[0];", "let a = -x??.y[0];"),
+			(
+				"(if a { b } else { c }) + 1;",
+				"(if a { b } else { c }) + 1;",
+			),
+			(
+				"(match x { _ => { ..p } }).y;",
+				"(match x {\n\t_ => { ..p },\n}).y;",
+			),
+			("-{ a } + ({ b } + c);", "-{ a } + ({ b } + c);"),
+			(
+				"if (P { x: 1 }) == p { f(P { x: 2 }) }",
+				"if (P { x: 1 }) == p { f(P { x: 2 }) }",
+			),
+			("let a = { ({ a: 1 }).a };", "let a = { ({ a: 1 }).a };"),
+		];
+
+		for (source, expected) in cases {
+			assert_eq!(print(&ungroup(&parse_str(source))), format!("{expected}\n"));
+		}
+	}
+
+	#[test]
+	fn renames_made_up_names() {
+		let file = parse_str(
+			"macro m { ($x:ident) => { { let t = 1; $x + t } } }
+			let t_0 = 2;
+			let y = m!(t_0);",
+		);
+		assert_eq!(
+			print(&file),
+			"let t_0 = 2;\nlet y = ({\n\tlet t_0_ = 1;\n\tt_0 + t_0_\n});\n"
+		);
+
+		let file = parse_str("macro m { () => { fn f() { 1 } } } m!();");
+		assert_eq!(print(&file), "fn f_0() { 1 }\n");
+
+		let file = lower(parse_str("let y = x ?>> f(1, _);")).unwrap();
+		assert_eq!(
+			print(&file),
+			"let y = match x {\n\tOk(_piped) | Some(_piped) => f(1, _piped),\n\t_failed => _failed,\n};\n"
+		);
+	}
+
+	#[test]
+	fn prints_expressions_on_their_own() {
+		let file = parse_str("typedef Point = { x: Num } let p = Point { x: 1 } + 2;");
+		let value = |stmt: StmtId| match &file.arena[stmt].kind {
+			StmtKind::LetDecl(decl) => decl.value,
+			_ => unreachable!(),
+		};
+
+		assert_eq!(print_expr(&file.arena, value(file.stmts[0])), None);
+		assert_eq!(
+			print_expr(&file.arena, value(file.stmts[1])).as_deref(),
+			Some("Point { x: 1 } + 2")
+		);
+	}
+
+	#[test]
+	fn prints_declarations() {
+		let source = r#"import "shapes" as shapes;
+import { area, Shape } from "shapes";
+@doc("a point")
+typedef Point<T> = { x: T, y: Num = 0 }
+enum Tree<T> = Leaf | Node(left: Tree<T>, value: T, right: Tree<T>);
+export const fn double(x: Num) -> Num { x * 2 }
+const LIMIT = double(4);
+interface Show {
+	fn show(self) -> Str;
+	fn debug(self, f: ((Num) -> Num)?) -> {Str: [(Num,)]} { "?" }
+}
+impl Show for Point {
+	@inline
+	@pure
+	fn show(self) -> Str { "point" }
+}
+fn main(args: [Str]) {
+	let mut total = 0;
+	for [first, ..rest] in args {
+		total += 1;
+	}
+	match total {
+		0 | -1 => nil,
+		Tree.Leaf | Node(_, (a,), _) if ok => { print(n) }
+		Point { x, y: 0 } => ({ ..p, x: 1 }).x,
+	}
+	while total > 0 {
+		if total % 2 == 0 {
+			total /= 2;
+		} else if total == 1 {
+			total = 0;
+		};
+	}
+	xs[1..] >> map(_, (x) => { x + 1 }) >> sum
+}
+"#;
+
+		assert_eq!(print(&parse_str(source)), source);
+	}
+
+	fn name() -> impl Strategy<Value = Symbol> {
+		"[a-z][a-z0-9]{0,2}"
+			.prop_map(|name| Symbol::intern(&name))
+			.prop_filter("keywords aren't names", |name| name.keyword().is_none())
+	}
+
+	/// Names that can be bound, which besides those in the source include the
+	/// ones macro hygiene and lowering make up
+	fn binding_name() -> impl Strategy<Value = Symbol> {
+		prop_oneof![
+			4 => name(),
+			1 => (name(), 0..10u32).prop_map(|(name, id)| Symbol::intern(&format!("{name}${id}"))),
+			1 => prop_oneof![Just("$piped"), Just("$failed")].prop_map(Symbol::intern),
+		]
+	}
+
+	fn type_name() -> impl Strategy<Value = Symbol> {
+		"[A-Z][a-z]{0,2}".prop_map(|name| Symbol::intern(&name))
+	}
+
+	/// A tree made up by the strategies below, which is only built into an
+	/// arena once a test case uses it
+	struct Build<T>(Rc<dyn Fn(&mut Arena) -> T>);
+
+	impl<T> Build<T> {
+		fn new(build: impl Fn(&mut Arena) -> T + 'static) -> Self {
+			Self(Rc::new(build))
+		}
+
+		fn build(&self, arena: &mut Arena) -> T {
+			(self.0)(arena)
+		}
+	}
+
+	impl<T> Clone for Build<T> {
+		fn clone(&self) -> Self {
+			Self(Rc::clone(&self.0))
+		}
+	}
+
+	impl<T: fmt::Debug> fmt::Debug for Build<T> {
+		fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+			let mut arena = Arena::default();
+			let root = self.build(&mut arena);
+			write!(f, "{root:?} in {arena:?}")
+		}
+	}
+
+	fn build_all<T>(items: &[Build<T>], arena: &mut Arena) -> Vec<T> {
+		items.iter().map(|item| item.build(arena)).collect()
+	}
+
+	fn build_inits(fields: &[(Symbol, Build<ExprId>)], arena: &mut Arena) -> Vec<FieldInit> {
+		fields
+			.iter()
+			.map(|(name, value)| FieldInit {
+				name: *name,
+				value: value.build(arena),
+				span: Span::default(),
+			})
+			.collect()
+	}
+
+	fn expr(kind: Build<ExprKind>) -> Build<ExprId> {
+		Build::new(move |arena| {
+			let kind = kind.build(arena);
+			arena.alloc_expr(kind, Span::default())
+		})
+	}
+
+	fn pattern_of(kind: Build<PatternKind>) -> Build<PatternId> {
+		Build::new(move |arena| {
+			let kind = kind.build(arena);
+			arena.alloc_pattern(kind, Span::default())
+		})
+	}
+
+	fn stmt_of(kind: Build<StmtKind>) -> Build<StmtId> {
+		Build::new(move |arena| {
+			let kind = kind.build(arena);
+			arena.alloc_stmt(kind, Span::default())
+		})
+	}
+
+	fn type_of(kind: Build<TypeExprKind>) -> Build<TypeExprId> {
+		Build::new(move |arena| {
+			let kind = kind.build(arena);
+			arena.alloc_type_expr(kind, Span::default())
+		})
+	}
+
+	fn literal() -> impl Strategy<Value = Build<ExprKind>> {
+		prop_oneof![
+			(0..1000u32).prop_map(|num| Build::new(move |_| ExprKind::NumLit(num.into()))),
+			"[a-z ]{0,4}".prop_map(|s| Build::new(move |_| ExprKind::StrLit(s.clone()))),
+			any::<bool>().prop_map(|b| Build::new(move |_| ExprKind::BoolLit(b))),
+			Just(()).prop_map(|_| Build::new(|_| ExprKind::UnitLit)),
+			Just(()).prop_map(|_| Build::new(|_| ExprKind::NilLit)),
+		]
+	}
+
+	fn binary_op() -> impl Strategy<Value = BinaryOp> {
+		prop::sample::select(vec![
+			BinaryOp::Add,
+			BinaryOp::Subtract,
+			BinaryOp::Multiply,
+			BinaryOp::Divide,
+			BinaryOp::Modulo,
+			BinaryOp::Power,
+			BinaryOp::Pipe,
+			BinaryOp::TryPipe,
+			BinaryOp::Equal,
+			BinaryOp::NotEqual,
+			BinaryOp::LessThan,
+			BinaryOp::LessThanOrEqual,
+			BinaryOp::GreaterThan,
+			BinaryOp::GreaterThanOrEqual,
+			BinaryOp::And,
+			BinaryOp::Or,
+			BinaryOp::BitAnd,
+			BinaryOp::BitOr,
+			BinaryOp::BitXor,
+		])
+	}
+
+	fn compound_op() -> impl Strategy<Value = BinaryOp> {
+		binary_op().prop_filter("only arithmetic and bitwise operators", |op| {
+			op.overload().is_some() && !op.is_comparison()
+		})
+	}
+
+	fn unary_op() -> impl Strategy<Value = UnaryOp> {
+		(0..4).prop_map(|i| match i {
+			0 => UnaryOp::Negate,
+			1 => UnaryOp::Plus,
+			2 => UnaryOp::Not,
+			_ => UnaryOp::BitNot,
+		})
+	}
+
+	fn pattern() -> impl Strategy<Value = Build<PatternId>> {
+		let leaf = prop_oneof![
+			Just(()).prop_map(|_| Build::new(|_| PatternKind::Wildcard)),
+			binding_name().prop_map(|name| Build::new(move |_| PatternKind::Binding(name))),
+			literal().prop_map(|kind| {
+				let literal = expr(kind);
+				Build::new(move |arena| PatternKind::Literal(literal.build(arena)))
+			}),
+			(0..1000u32).prop_map(|num| {
+				let num = expr(Build::new(move |_| ExprKind::NumLit(num.into())));
+				let negated = expr(Build::new(move |arena| {
+					ExprKind::Unary(UnaryOp::Negate, num.build(arena))
+				}));
+				Build::new(move |arena| PatternKind::Literal(negated.build(arena)))
+			}),
+		];
+
+		leaf.prop_map(pattern_of).prop_recursive(3, 16, 3, |inner| {
+			prop_oneof![
+				vec(inner.clone(), 1..3).prop_map(|items| pattern_of(Build::new(move |arena| {
+					PatternKind::Tuple(build_all(&items, arena))
+				}))),
+				(
+					vec(inner.clone(), 0..3),
+					option::of(option::of(binding_name())),
+					any::<prop::sample::Index>()
+				)
+					.prop_map(|(mut items, rest, index)| {
+						if let Some(rest) = rest {
+							let rest = pattern_of(Build::new(move |_| PatternKind::Rest(rest)));
+							items.insert(index.index(items.len() + 1), rest);
+						}
+						pattern_of(Build::new(move |arena| {
+							PatternKind::List(build_all(&items, arena))
+						}))
+					}),
+				(option::of(type_name()), vec((name(), inner.clone()), 0..3)).prop_map(
+					|(type_name, fields)| pattern_of(Build::new(move |arena| {
+						let fields = fields
+							.iter()
+							.map(|(name, pattern)| FieldPattern {
+								name: *name,
+								pattern: pattern.build(arena),
+								span: Span::default(),
+							})
+							.collect();
+						PatternKind::Record { type_name, fields }
+					}))
+				),
+				(
+					option::of(type_name()),
+					type_name(),
+					vec(inner.clone(), 0..3)
+				)
+					.prop_map(|(enum_name, variant, fields)| pattern_of(Build::new(
+						move |arena| PatternKind::Variant {
+							enum_name,
+							variant,
+							fields: build_all(&fields, arena),
+						}
+					))),
+				vec(inner, 2..4).prop_map(|alternatives| pattern_of(Build::new(move |arena| {
+					PatternKind::Or(build_all(&alternatives, arena))
+				}))),
+			]
+		})
+	}
+
+	fn type_expr() -> impl Strategy<Value = Build<TypeExprId>> {
+		let leaf = type_name()
+			.prop_map(|name| type_of(Build::new(move |_| TypeExprKind::Named(name, Vec::new()))));
+
+		leaf.prop_recursive(3, 12, 3, |inner| {
+			prop_oneof![
+				(type_name(), vec(inner.clone(), 1..3)).prop_map(|(name, args)| type_of(
+					Build::new(move |arena| TypeExprKind::Named(name, build_all(&args, arena)))
+				)),
+				(vec(inner.clone(), 0..3), inner.clone()).prop_map(|(params, result)| {
+					type_of(Build::new(move |arena| {
+						TypeExprKind::Function(build_all(&params, arena), result.build(arena))
+					}))
+				}),
+				inner
+					.clone()
+					.prop_map(|item| type_of(Build::new(move |arena| {
+						TypeExprKind::List(item.build(arena))
+					}))),
+				(inner.clone(), inner.clone()).prop_map(|(key, value)| {
+					type_of(Build::new(move |arena| {
+						TypeExprKind::Map(key.build(arena), value.build(arena))
+					}))
+				}),
+				vec(inner.clone(), 0..3).prop_map(|items| type_of(Build::new(move |arena| {
+					TypeExprKind::Tuple(build_all(&items, arena))
+				}))),
+				inner.prop_map(|inner| type_of(Build::new(move |arena| {
+					TypeExprKind::Optional(inner.build(arena))
+				}))),
+			]
+		})
+	}
+
+	fn block(expr: BoxedStrategy<Build<ExprId>>) -> impl Strategy<Value = Build<Block>> {
+		(vec(stmt(expr.clone()), 0..3), option::of(expr)).prop_map(|(stmts, tail)| {
+			Build::new(move |arena| Block {
+				stmts: build_all(&stmts, arena),
+				expr: tail.as_ref().map(|tail| tail.build(arena)),
+			})
+		})
+	}
+
+	fn stmt(expr: BoxedStrategy<Build<ExprId>>) -> impl Strategy<Value = Build<StmtId>> {
+		prop_oneof![
+			(any::<bool>(), pattern(), expr.clone()).prop_map(|(mutable, pattern, value)| {
+				stmt_of(Build::new(move |arena| {
+					StmtKind::LetDecl(Box::new(LetDecl {
+						attributes: Vec::new(),
+						mutable,
+						pattern: pattern.build(arena),
+						value: value.build(arena),
+					}))
+				}))
+			}),
+			expr.prop_map(|expr| stmt_of(Build::new(move |arena| {
+				StmtKind::Expr(expr.build(arena))
+			}))),
+		]
+	}
+
+	/// Variables, fields and list elements
+	fn assign_target(inner: BoxedStrategy<Build<ExprId>>) -> impl Strategy<Value = Build<ExprId>> {
+		prop_oneof![
+			binding_name().prop_map(|name| expr(Build::new(move |_| ExprKind::Reference(name)))),
+			(inner.clone(), name()).prop_map(|(target, field)| expr(Build::new(move |arena| {
+				ExprKind::Field(target.build(arena), field)
+			}))),
+			(inner.clone(), inner).prop_map(|(target, index)| expr(Build::new(move |arena| {
+				ExprKind::Index(target.build(arena), index.build(arena))
+			}))),
+		]
+	}
+
+	fn exprs() -> BoxedStrategy<Build<ExprId>> {
+		let leaf = prop_oneof![
+			4 => literal(),
+			4 => binding_name().prop_map(|name| Build::new(move |_| ExprKind::Reference(name))),
+			1 => Just(()).prop_map(|_| Build::new(|_| ExprKind::Placeholder)),
+		];
+
+		leaf.prop_map(expr)
+			.prop_recursive(4, 32, 3, |inner| {
+				let block = block(inner.clone()).boxed();
+				let field_inits = vec((name(), inner.clone()), 0..3).boxed();
+				let else_branch = prop_oneof![
+					block
+						.clone()
+						.prop_map(|block| expr(Build::new(move |arena| {
+							ExprKind::Block(block.build(arena))
+						}))),
+					(inner.clone(), block.clone()).prop_map(|(condition, then_branch)| {
+						expr(Build::new(move |arena| {
+							ExprKind::If(condition.build(arena), then_branch.build(arena), None)
+						}))
+					}),
+				];
+				// A guard ending in parentheses would be taken for the parameters of a
+				// lambda, so they are only names
+				let arm = (pattern(), option::of(binding_name()), inner.clone()).prop_map(
+					|(pattern, guard, body)| {
+						Build::new(move |arena| MatchArm {
+							pattern: pattern.build(arena),
+							guard: guard.map(|guard| {
+								arena.alloc_expr(ExprKind::Reference(guard), Span::default())
+							}),
+							body: body.build(arena),
+						})
+					},
+				);
+
+				let kinds = vec![
+					(binary_op(), inner.clone(), inner.clone())
+						.prop_map(|(op, left, right)| {
+							Build::new(move |arena| {
+								ExprKind::Binary(op, left.build(arena), right.build(arena))
+							})
+						})
+						.boxed(),
+					(unary_op(), inner.clone())
+						.prop_map(|(op, operand)| {
+							Build::new(move |arena| ExprKind::Unary(op, operand.build(arena)))
+						})
+						.boxed(),
+					(inner.clone(), vec(inner.clone(), 0..3))
+						.prop_map(|(callee, args)| {
+							Build::new(move |arena| {
+								ExprKind::FnInvoke(callee.build(arena), build_all(&args, arena))
+							})
+						})
+						.boxed(),
+					block
+						.clone()
+						.prop_map(|block| {
+							Build::new(move |arena| ExprKind::Block(block.build(arena)))
+						})
+						.boxed(),
+					(inner.clone(), block.clone(), option::of(else_branch))
+						.prop_map(|(condition, then_branch, else_branch)| {
+							Build::new(move |arena| {
+								ExprKind::If(
+									condition.build(arena),
+									then_branch.build(arena),
+									else_branch
+										.as_ref()
+										.map(|else_branch| else_branch.build(arena)),
+								)
+							})
+						})
+						.boxed(),
+					(inner.clone(), block.clone())
+						.prop_map(|(condition, body)| {
+							Build::new(move |arena| {
+								ExprKind::While(condition.build(arena), body.build(arena))
+							})
+						})
+						.boxed(),
+					(pattern(), inner.clone(), block.clone())
+						.prop_map(|(pattern, iterable, body)| {
+							Build::new(move |arena| {
+								ExprKind::For(
+									pattern.build(arena),
+									iterable.build(arena),
+									body.build(arena),
+								)
+							})
+						})
+						.boxed(),
+					(inner.clone(), vec(arm, 0..3))
+						.prop_map(|(value, arms)| {
+							Build::new(move |arena| {
+								ExprKind::Match(value.build(arena), build_all(&arms, arena))
+							})
+						})
+						.boxed(),
+					(vec(binding_name(), 0..3), block)
+						.prop_map(|(params, body)| {
+							Build::new(move |arena| {
+								ExprKind::Lambda(params.clone(), body.build(arena))
+							})
+						})
+						.boxed(),
+					inner
+						.clone()
+						.prop_map(|inner| {
+							Build::new(move |arena| ExprKind::Group(inner.build(arena)))
+						})
+						.boxed(),
+					vec(inner.clone(), 0..3)
+						.prop_map(|items| {
+							Build::new(move |arena| ExprKind::List(build_all(&items, arena)))
+						})
+						.boxed(),
+					vec((inner.clone(), inner.clone()), 0..3)
+						.prop_map(|entries| {
+							Build::new(move |arena| {
+								ExprKind::Map(
+									entries
+										.iter()
+										.map(|(key, value)| (key.build(arena), value.build(arena)))
+										.collect(),
+								)
+							})
+						})
+						.boxed(),
+					vec(inner.clone(), 1..3)
+						.prop_map(|items| {
+							Build::new(move |arena| ExprKind::Tuple(build_all(&items, arena)))
+						})
+						.boxed(),
+					(inner.clone(), inner.clone())
+						.prop_map(|(target, index)| {
+							Build::new(move |arena| {
+								ExprKind::Index(target.build(arena), index.build(arena))
+							})
+						})
+						.boxed(),
+					(
+						inner.clone(),
+						option::of(inner.clone()),
+						option::of(inner.clone()),
+					)
+						.prop_map(|(target, start, end)| {
+							Build::new(move |arena| {
+								ExprKind::Slice(
+									target.build(arena),
+									start.as_ref().map(|start| start.build(arena)),
+									end.as_ref().map(|end| end.build(arena)),
+								)
+							})
+						})
+						.boxed(),
+					(type_name(), field_inits.clone())
+						.prop_map(|(type_name, fields)| {
+							Build::new(move |arena| {
+								ExprKind::Record(RecordLit {
+									type_name: Some(type_name),
+									base: None,
+									fields: build_inits(&fields, arena),
+								})
+							})
+						})
+						.boxed(),
+					(inner.clone(), field_inits)
+						.prop_map(|(base, fields)| {
+							Build::new(move |arena| {
+								ExprKind::Record(RecordLit {
+									type_name: None,
+									base: Some(base.build(arena)),
+									fields: build_inits(&fields, arena),
+								})
+							})
+						})
+						.boxed(),
+					(inner.clone(), inner.clone(), any::<bool>())
+						.prop_map(|(start, end, inclusive)| {
+							Build::new(move |arena| {
+								ExprKind::Range(start.build(arena), end.build(arena), inclusive)
+							})
+						})
+						.boxed(),
+					inner
+						.clone()
+						.prop_map(|operand| {
+							Build::new(move |arena| ExprKind::Try(operand.build(arena)))
+						})
+						.boxed(),
+					(inner.clone(), name())
+						.prop_map(|(target, field)| {
+							Build::new(move |arena| ExprKind::Field(target.build(arena), field))
+						})
+						.boxed(),
+					(assign_target(inner.clone()), inner.clone())
+						.prop_map(|(target, value)| {
+							Build::new(move |arena| {
+								ExprKind::Assign(target.build(arena), value.build(arena))
+							})
+						})
+						.boxed(),
+					(compound_op(), assign_target(inner.clone()), inner)
+						.prop_map(|(op, target, value)| {
+							Build::new(move |arena| {
+								ExprKind::CompoundAssign(
+									op,
+									target.build(arena),
+									value.build(arena),
+								)
+							})
+						})
+						.boxed(),
+				];
+
+				Union::new(kinds).prop_map(expr)
+			})
+			.boxed()
+	}
+
+	fn typedef_field(
+		expr: BoxedStrategy<Build<ExprId>>,
+	) -> impl Strategy<Value = Build<TypedefField>> {
+		(binding_name(), option::of(type_expr()), option::of(expr)).prop_map(
+			|(name, type_expr, default_value)| {
+				Build::new(move |arena| TypedefField {
+					name,
+					type_expr: type_expr.as_ref().map(|type_expr| type_expr.build(arena)),
+					default_value: default_value.as_ref().map(|value| value.build(arena)),
+				})
+			},
+		)
+	}
+
+	fn attribute(expr: BoxedStrategy<Build<ExprId>>) -> impl Strategy<Value = Build<Attribute>> {
+		(name(), vec(expr, 0..3)).prop_map(|(name, args)| {
+			Build::new(move |arena| Attribute {
+				name,
+				args: build_all(&args, arena),
+				span: Span::default(),
+			})
+		})
+	}
+
+	fn fn_decl(
+		expr: BoxedStrategy<Build<ExprId>>,
+		constant: bool,
+	) -> impl Strategy<Value = Build<FnDecl>> {
+		(
+			vec(attribute(expr.clone()), 0..2),
+			name(),
+			vec(type_name(), 0..2),
+			vec(typedef_field(expr.clone()), 0..3),
+			option::of(type_expr()),
+			block(expr),
+		)
+			.prop_map(
+				move |(attributes, name, type_params, params, return_type, body)| {
+					Build::new(move |arena| FnDecl {
+						attributes: build_all(&attributes, arena),
+						constant,
+						name,
+						type_params: type_params.clone(),
+						params: build_all(&params, arena),
+						return_type: return_type
+							.as_ref()
+							.map(|return_type| return_type.build(arena)),
+						body: body.build(arena),
+					})
+				},
+			)
+	}
+
+	/// Declarations that can be exported
+	fn decl(expr: BoxedStrategy<Build<ExprId>>) -> impl Strategy<Value = Build<StmtId>> {
+		let declared = |attributes: Vec<Build<Attribute>>, name, value| {
+			let value = self::expr(value);
+			stmt_of(Build::new(move |arena| {
+				StmtKind::LetDecl(Box::new(LetDecl {
+					attributes: build_all(&attributes, arena),
+					mutable: false,
+					pattern: arena.alloc_pattern(PatternKind::Binding(name), Span::default()),
+					value: value.build(arena),
+				}))
+			}))
+		};
+		let attributes = vec(attribute(expr.clone()), 0..2).boxed();
+
+		prop_oneof![
+			(attributes.clone(), any::<bool>(), pattern(), expr.clone()).prop_map(
+				|(attributes, mutable, pattern, value)| {
+					stmt_of(Build::new(move |arena| {
+						StmtKind::LetDecl(Box::new(LetDecl {
+							attributes: build_all(&attributes, arena),
+							mutable,
+							pattern: pattern.build(arena),
+							value: value.build(arena),
+						}))
+					}))
+				}
+			),
+			(
+				attributes.clone(),
+				type_name(),
+				vec(type_name(), 0..2),
+				vec(typedef_field(expr.clone()), 0..3)
+			)
+				.prop_map(move |(attributes, name, type_params, fields)| {
+					let value = Build::new(move |arena| {
+						ExprKind::Typedef(type_params.clone(), build_all(&fields, arena))
+					});
+					declared(attributes, name, value)
+				}),
+			(
+				attributes.clone(),
+				type_name(),
+				vec(type_name(), 0..2),
+				vec((type_name(), vec(typedef_field(expr.clone()), 0..2)), 1..3)
+			)
+				.prop_map(move |(attributes, name, type_params, variants)| {
+					let value = Build::new(move |arena| {
+						let variants = variants
+							.iter()
+							.map(|(name, fields)| EnumVariant {
+								name: *name,
+								fields: build_all(fields, arena),
+							})
+							.collect();
+						ExprKind::Enum(type_params.clone(), variants)
+					});
+					declared(attributes, name, value)
+				}),
+			(attributes, binding_name(), expr.clone()).prop_map(|(attributes, name, value)| {
+				stmt_of(Build::new(move |arena| {
+					StmtKind::ConstDecl(Box::new(ConstDecl {
+						attributes: build_all(&attributes, arena),
+						name,
+						value: value.build(arena),
+					}))
+				}))
+			}),
+			(fn_decl(expr, false), any::<bool>()).prop_map(|(decl, constant)| {
+				stmt_of(Build::new(move |arena| {
+					StmtKind::FnDecl(Box::new(FnDecl {
+						constant,
+						..decl.build(arena)
+					}))
+				}))
+			}),
+		]
+	}
+
+	fn top_level_stmt() -> impl Strategy<Value = Build<StmtId>> {
+		let expr = exprs();
+		let method = (
+			name(),
+			vec(type_name(), 0..2),
+			vec(typedef_field(expr.clone()), 0..3),
+			option::of(type_expr()),
+			option::of(block(expr.clone())),
+		)
+			.prop_map(|(name, type_params, params, return_type, default)| {
+				Build::new(move |arena| InterfaceMethod {
+					name,
+					type_params: type_params.clone(),
+					params: build_all(&params, arena),
+					return_type: return_type
+						.as_ref()
+						.map(|return_type| return_type.build(arena)),
+					default: default.as_ref().map(|default| default.build(arena)),
+				})
+			});
+
+		prop_oneof![
+			stmt(expr.clone()),
+			decl(expr.clone()),
+			decl(expr.clone()).prop_map(|decl| stmt_of(Build::new(move |arena| {
+				StmtKind::Export(decl.build(arena))
+			}))),
+			(type_name(), vec(method, 0..3)).prop_map(|(name, methods)| {
+				stmt_of(Build::new(move |arena| {
+					StmtKind::InterfaceDecl(Box::new(InterfaceDecl {
+						name,
+						methods: build_all(&methods, arena),
+					}))
+				}))
+			}),
+			(type_name(), type_name(), vec(fn_decl(expr, false), 0..3)).prop_map(
+				|(interface, type_name, methods)| {
+					stmt_of(Build::new(move |arena| {
+						StmtKind::ImplDecl(Box::new(ImplDecl {
+							interface,
+							type_name,
+							methods: build_all(&methods, arena),
+						}))
+					}))
+				}
+			),
+			(
+				"[a-z/]{1,6}",
+				prop_oneof![
+					name().prop_map(ImportNames::Module),
+					vec(name(), 0..3).prop_map(ImportNames::Only),
+				]
+			)
+				.prop_map(|(path, names)| {
+					stmt_of(Build::new(move |_| {
+						StmtKind::Import(Box::new(Import {
+							path: path.clone(),
+							names: names.clone(),
+						}))
+					}))
+				}),
+		]
+	}
+
+	proptest! {
+		#[test]
+		fn round_trips(stmts in vec(top_level_stmt(), 0..4)) {
+			let mut arena = Arena::default();
+			let stmts = build_all(&stmts, &mut arena);
+			let file = SourceFile {
+				arena,
+				stmts,
+				..Default::default()
+			};
+			let printed = print(&file);
+
+			let parsed = parse(&mut printed.as_bytes())
+				.map_err(|error| TestCaseError::fail(format!("{error} in:\n{printed}")))?;
+
+			prop_assert_eq!(print(&parsed), printed.clone());
+			prop_assert_eq!(ungroup(&parsed), ungroup(&rename(&file)), "in:\n{}", printed);
+		}
+	}
+}